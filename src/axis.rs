@@ -0,0 +1,71 @@
+//! Handles the axes of 3D volumetric data.
+
+use crate::MedvizErr;
+use std::fmt;
+use std::str::FromStr;
+
+/// An axis of a volume.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Axis {
+  /// The X-axis.
+  X,
+
+  /// The Y-axis.
+  Y,
+
+  /// The Z-axis.
+  Z,
+}
+
+impl Axis {
+  /// All axes, in order.
+  pub const ALL: [Self; 3] = [Self::X, Self::Y, Self::Z];
+
+  /// The position of the axis in per-axis arrays (e.g. `0` for the
+  /// X-axis).
+  pub fn index(&self) -> usize {
+    match self {
+      Self::X => 0,
+      Self::Y => 1,
+      Self::Z => 2,
+    }
+  }
+}
+
+impl fmt::Display for Axis {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::X => f.write_str("X"),
+      Self::Y => f.write_str("Y"),
+      Self::Z => f.write_str("Z"),
+    }
+  }
+}
+
+impl FromStr for Axis {
+  type Err = MedvizErr;
+
+  /// Parse an axis from its name (e.g. `x` or `X`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    match text.trim() {
+      "x" | "X" => Ok(Self::X),
+      "y" | "Y" => Ok(Self::Y),
+      "z" | "Z" => Ok(Self::Z),
+      _ => Err(MedvizErr::new_invalid_axis(text.into())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod axis_tests {
+  use super::Axis;
+  use crate::MedvizErr;
+
+  #[test]
+  fn parse() {
+    assert_eq!("x".parse(), Ok(Axis::X));
+    assert_eq!("Y".parse(), Ok(Axis::Y));
+    assert_eq!(" z ".parse(), Ok(Axis::Z));
+    assert_eq!("w".parse::<Axis>(), Err(MedvizErr::InvalidAxis { axis: String::from("w") }));
+  }
+}
@@ -0,0 +1,262 @@
+//! Handles rendering of display intensities as colors. The primary
+//! structure is the [colormap struct](Colormap).
+
+use crate::MedvizErr;
+use std::fmt;
+use std::str::FromStr;
+
+/// A colormap (also known as lookup table or LUT).
+///
+/// Maps each 8-bit display intensity (e.g. as produced by
+/// [`Voxel::value_normalized`](crate::Voxel::value_normalized)) onto an
+/// RGB color, for pseudo-color rendering of frames.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Colormap {
+  /// The color of each of the 256 intensities.
+  colors: Vec<[u8; 3]>,
+}
+
+/// Names of the built-in colormaps.
+const BUILTINS: [&str; 6] = ["grey", "hot", "jet", "viridis", "bone", "inferno"];
+
+impl Colormap {
+  /// Number of entries in a colormap.
+  pub const LEN: usize = 256;
+
+  /// Names of the built-in colormaps, as accepted by
+  /// [`Colormap::builtin`].
+  pub fn builtin_names() -> &'static [&'static str] {
+    &BUILTINS
+  }
+
+  /// Find a built-in colormap by name (e.g. `viridis`).
+  pub fn builtin(name: &str) -> Option<Self> {
+    let stops: &[[u8; 3]] = match name.to_ascii_lowercase().as_str() {
+      "grey" | "gray" => &[[0, 0, 0], [255, 255, 255]],
+      "hot" => &[
+        [0, 0, 0],
+        [85, 0, 0],
+        [170, 0, 0],
+        [255, 0, 0],
+        [255, 85, 0],
+        [255, 170, 0],
+        [255, 255, 0],
+        [255, 255, 128],
+        [255, 255, 255],
+      ],
+      "jet" => &[
+        [0, 0, 128],
+        [0, 0, 255],
+        [0, 128, 255],
+        [0, 255, 255],
+        [128, 255, 128],
+        [255, 255, 0],
+        [255, 128, 0],
+        [255, 0, 0],
+        [128, 0, 0],
+      ],
+      "viridis" => &[
+        [68, 1, 84],
+        [71, 45, 123],
+        [59, 82, 139],
+        [44, 114, 142],
+        [33, 145, 140],
+        [40, 174, 128],
+        [94, 201, 98],
+        [173, 220, 48],
+        [253, 231, 37],
+      ],
+      "bone" => &[
+        [0, 0, 0],
+        [28, 28, 39],
+        [56, 56, 78],
+        [84, 84, 117],
+        [112, 124, 145],
+        [140, 164, 173],
+        [168, 203, 201],
+        [211, 229, 229],
+        [255, 255, 255],
+      ],
+      "inferno" => &[
+        [0, 0, 4],
+        [31, 12, 72],
+        [85, 15, 109],
+        [136, 34, 106],
+        [186, 54, 85],
+        [227, 89, 51],
+        [249, 142, 9],
+        [249, 203, 53],
+        [252, 255, 164],
+      ],
+      _ => return None,
+    };
+
+    Some(Self::from_stops(stops))
+  }
+
+  /// Create a colormap by interpolating between evenly spaced colors.
+  ///
+  /// # Notes
+  ///
+  /// Panics if `stops` contains less than 2 colors.
+  pub fn from_stops(stops: &[[u8; 3]]) -> Self {
+    assert!(stops.len() >= 2);
+
+    let segments = (stops.len() - 1) as f64;
+    let colors = (0..Self::LEN)
+      .map(|intensity| {
+        let position = intensity as f64 * segments / (Self::LEN - 1) as f64;
+        let index = (position.floor() as usize).min(stops.len() - 2);
+        let frac = position - index as f64;
+
+        let mut color = [0; 3];
+        for (channel, value) in color.iter_mut().enumerate() {
+          let start = f64::from(stops[index][channel]);
+          let end = f64::from(stops[index + 1][channel]);
+          // Interpolating between two u8 values stays within u8.
+          *value = (start + (end - start) * frac).round() as u8;
+        }
+        color
+      })
+      .collect();
+
+    Self { colors }
+  }
+
+  /// Parse a custom colormap from a buffer.
+  ///
+  /// Every line holds a color as red, green and blue values from 0 to
+  /// 255, separated by whitespace or commas. Empty lines and lines
+  /// starting with `#` are ignored. The colors are spread evenly over
+  /// the range of intensities, interpolating between them when there
+  /// are fewer than 256.
+  ///
+  /// # Arguments
+  ///
+  /// * `buffer` - The contents of the colormap file.
+  ///
+  /// # Returns
+  ///
+  /// The colormap or an error in case a line is invalid or there are
+  /// less than 2 colors.
+  pub fn from_buffer(buffer: &str) -> Result<Self, MedvizErr> {
+    let mut stops = Vec::new();
+
+    for (line_number, line) in buffer.split('\n').enumerate() {
+      let line_number = line_number + 1;
+      let line = line.trim();
+
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let invalid = || MedvizErr::new_lut_invalid_entry(line_number);
+
+      let mut values =
+        line.split(|c: char| c == ',' || c.is_whitespace()).filter(|v| !v.is_empty());
+      let mut color = [0; 3];
+      for value in color.iter_mut() {
+        *value = values.next().ok_or_else(invalid)?.parse().map_err(|_| invalid())?;
+      }
+
+      if values.next().is_some() {
+        return Err(invalid());
+      }
+
+      stops.push(color);
+    }
+
+    if stops.len() < 2 {
+      return Err(MedvizErr::new_lut_too_few_entries(stops.len()));
+    }
+
+    Ok(Self::from_stops(&stops))
+  }
+
+  /// The color of a display intensity.
+  pub fn color(&self, intensity: u8) -> [u8; 3] {
+    self.colors[usize::from(intensity)]
+  }
+}
+
+impl FromStr for Colormap {
+  type Err = MedvizErr;
+
+  /// Parse the name of a [built-in](Colormap::builtin) colormap.
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let text = text.trim();
+    Self::builtin(text).ok_or_else(|| MedvizErr::new_invalid_colormap(text.into()))
+  }
+}
+
+impl fmt::Display for Colormap {
+  /// Write the colormap in the format accepted by
+  /// [`Colormap::from_buffer`].
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for [red, green, blue] in &self.colors {
+      writeln!(f, "{} {} {}", red, green, blue)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod colormap_tests {
+  use super::Colormap;
+  use crate::MedvizErr;
+
+  #[test]
+  fn builtins() {
+    for name in Colormap::builtin_names() {
+      let colormap: Colormap = name.parse().unwrap();
+      assert_eq!(colormap.colors.len(), Colormap::LEN);
+    }
+
+    let grey = Colormap::builtin("grey").unwrap();
+    assert_eq!(grey.color(0), [0, 0, 0]);
+    assert_eq!(grey.color(128), [128, 128, 128]);
+    assert_eq!(grey.color(255), [255, 255, 255]);
+
+    let hot = Colormap::builtin("Hot").unwrap();
+    assert_eq!(hot.color(0), [0, 0, 0]);
+    assert_eq!(hot.color(255), [255, 255, 255]);
+
+    let viridis = Colormap::builtin("viridis").unwrap();
+    assert_eq!(viridis.color(0), [68, 1, 84]);
+    assert_eq!(viridis.color(255), [253, 231, 37]);
+  }
+
+  #[test]
+  fn builtin_unknown() {
+    assert_eq!(
+      "rainbow".parse::<Colormap>(),
+      Err(MedvizErr::new_invalid_colormap("rainbow".into()))
+    );
+  }
+
+  #[test]
+  fn from_buffer() {
+    let colormap = Colormap::from_buffer("# red to blue\n255 0 0\n\n0,0,255\n").unwrap();
+    assert_eq!(colormap.color(0), [255, 0, 0]);
+    assert_eq!(colormap.color(255), [0, 0, 255]);
+    assert_eq!(colormap, Colormap::from_stops(&[[255, 0, 0], [0, 0, 255]]));
+  }
+
+  #[test]
+  fn from_buffer_roundtrip() {
+    let colormap = Colormap::builtin("inferno").unwrap();
+    assert_eq!(Colormap::from_buffer(&colormap.to_string()), Ok(colormap));
+  }
+
+  #[test]
+  fn from_buffer_fail() {
+    assert_eq!(Colormap::from_buffer("0 0 0\n1 2\n"), Err(MedvizErr::new_lut_invalid_entry(2)));
+    assert_eq!(Colormap::from_buffer("0 0 256\n"), Err(MedvizErr::new_lut_invalid_entry(1)));
+    assert_eq!(Colormap::from_buffer("0 0 0 0\n"), Err(MedvizErr::new_lut_invalid_entry(1)));
+    assert_eq!(
+      Colormap::from_buffer("# empty\n0 0 0\n"),
+      Err(MedvizErr::new_lut_too_few_entries(1))
+    );
+  }
+}
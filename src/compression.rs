@@ -0,0 +1,100 @@
+//! Small helpers for compressing and decompressing volume data.
+
+use crate::MedvizErr;
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+/// The first two bytes of gzip data.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether the bytes start like gzip data.
+pub(crate) fn is_gzip(bytes: &[u8]) -> bool {
+  bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompress gzip data, including data made of several gzip
+/// members. At most `limit` bytes are decompressed, so that a small
+/// file cannot expand to fill the memory.
+pub(crate) fn gunzip(bytes: &[u8], limit: usize) -> Result<Vec<u8>, MedvizErr> {
+  read_limited(MultiGzDecoder::new(bytes), limit)
+}
+
+/// Decompress zlib data, as written by MetaImage files with
+/// `CompressedData = True`. At most `limit` bytes are decompressed.
+pub(crate) fn inflate(bytes: &[u8], limit: usize) -> Result<Vec<u8>, MedvizErr> {
+  read_limited(ZlibDecoder::new(bytes), limit)
+}
+
+/// Decompress gzip or zlib data, depending on how the bytes start.
+/// At most `limit` bytes are decompressed.
+pub(crate) fn decompress(bytes: &[u8], limit: usize) -> Result<Vec<u8>, MedvizErr> {
+  if is_gzip(bytes) {
+    gunzip(bytes, limit)
+  } else {
+    inflate(bytes, limit)
+  }
+}
+
+/// Read at most `limit` bytes from a decoder.
+fn read_limited(decoder: impl Read, limit: usize) -> Result<Vec<u8>, MedvizErr> {
+  let mut res = Vec::new();
+  decoder
+    .take(u64::try_from(limit).unwrap_or(u64::MAX))
+    .read_to_end(&mut res)
+    .map_err(|e| MedvizErr::new_decompression(e.to_string()))?;
+  Ok(res)
+}
+
+/// Compress data with gzip.
+pub(crate) fn gzip(bytes: &[u8]) -> Vec<u8> {
+  let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+  // Writing to a vector cannot fail.
+  encoder.write_all(bytes).unwrap();
+  encoder.finish().unwrap()
+}
+
+/// Compress data with zlib, as read by MetaImage files with
+/// `CompressedData = True`.
+pub(crate) fn deflate(bytes: &[u8]) -> Vec<u8> {
+  let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+  // Writing to a vector cannot fail.
+  encoder.write_all(bytes).unwrap();
+  encoder.finish().unwrap()
+}
+
+#[cfg(test)]
+mod compression_tests {
+  use super::{decompress, deflate, gunzip, gzip, inflate, is_gzip};
+
+  #[test]
+  fn gzip_round_trip() {
+    let compressed = gzip(b"medviz");
+
+    assert!(is_gzip(&compressed));
+    assert!(!is_gzip(b"medviz"));
+    assert_eq!(gunzip(&compressed, 6).unwrap(), b"medviz");
+    assert!(gunzip(&compressed[..compressed.len() / 2], 6).is_err());
+  }
+
+  #[test]
+  fn decompress_zlib() {
+    let compressed = deflate(b"medviz");
+
+    assert_eq!(inflate(&compressed, 6).unwrap(), b"medviz");
+    assert_eq!(decompress(&compressed, 6).unwrap(), b"medviz");
+    assert_eq!(decompress(&gzip(b"medviz"), 6).unwrap(), b"medviz");
+    assert!(decompress(b"medviz", 6).is_err());
+  }
+
+  #[test]
+  fn decompress_limit() {
+    let compressed = gzip(&[0; 1 << 20]);
+
+    assert_eq!(gunzip(&compressed, 4).unwrap(), [0; 4]);
+    assert_eq!(inflate(&deflate(b"medviz"), 3).unwrap(), b"med");
+    assert_eq!(decompress(&compressed, usize::MAX).unwrap().len(), 1 << 20);
+  }
+}
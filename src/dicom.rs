@@ -0,0 +1,802 @@
+//! Handles series of single-frame DICOM files. The primary function
+//! loads an [image](Image) [from a directory of files](from_dir).
+
+use crate::metadata::{AnatomicalOrientation, ElementType};
+use crate::vector::{self, Vector};
+use crate::voxel;
+use crate::Image;
+use crate::MedvizErr;
+use crate::VolumeMd;
+use log::{debug, warn};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Transfer syntax of implicit VR little endian files.
+const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+
+/// Transfer syntax of explicit VR little endian files.
+const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+
+/// Length of elements and items of undefined length.
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+/// A DICOM tag, as a group and an element number.
+type Tag = (u16, u16);
+
+/// `TransferSyntaxUID` in the file meta information.
+const TRANSFER_SYNTAX_UID: Tag = (0x0002, 0x0010);
+
+/// `SliceThickness`.
+const SLICE_THICKNESS: Tag = (0x0018, 0x0050);
+
+/// `SeriesInstanceUID`.
+const SERIES_INSTANCE_UID: Tag = (0x0020, 0x000E);
+
+/// `InstanceNumber`.
+const INSTANCE_NUMBER: Tag = (0x0020, 0x0013);
+
+/// `ImagePositionPatient`.
+const IMAGE_POSITION_PATIENT: Tag = (0x0020, 0x0032);
+
+/// `ImageOrientationPatient`.
+const IMAGE_ORIENTATION_PATIENT: Tag = (0x0020, 0x0037);
+
+/// `SamplesPerPixel`.
+const SAMPLES_PER_PIXEL: Tag = (0x0028, 0x0002);
+
+/// `NumberOfFrames`.
+const NUMBER_OF_FRAMES: Tag = (0x0028, 0x0008);
+
+/// `Rows`.
+const ROWS: Tag = (0x0028, 0x0010);
+
+/// `Columns`.
+const COLUMNS: Tag = (0x0028, 0x0011);
+
+/// `PixelSpacing`.
+const PIXEL_SPACING: Tag = (0x0028, 0x0030);
+
+/// `BitsAllocated`.
+const BITS_ALLOCATED: Tag = (0x0028, 0x0100);
+
+/// `PixelRepresentation`.
+const PIXEL_REPRESENTATION: Tag = (0x0028, 0x0103);
+
+/// `RescaleIntercept`.
+const RESCALE_INTERCEPT: Tag = (0x0028, 0x1052);
+
+/// `RescaleSlope`.
+const RESCALE_SLOPE: Tag = (0x0028, 0x1053);
+
+/// `PixelData`.
+const PIXEL_DATA: Tag = (0x7FE0, 0x0010);
+
+/// Start of an item in a sequence.
+const ITEM: Tag = (0xFFFE, 0xE000);
+
+/// End of an item of undefined length.
+const ITEM_DELIMITATION: Tag = (0xFFFE, 0xE00D);
+
+/// End of a sequence of undefined length.
+const SEQUENCE_DELIMITATION: Tag = (0xFFFE, 0xE0DD);
+
+/// A single-frame DICOM image, one slice of a series.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DicomSlice {
+  /// Number of pixels in a row.
+  columns: usize,
+
+  /// Number of rows.
+  rows: usize,
+
+  /// Type of the pixels.
+  element_type: ElementType,
+
+  /// Distance between rows and between columns (`PixelSpacing`).
+  pixel_spacing: Option<[f64; 2]>,
+
+  /// Thickness of the slice.
+  slice_thickness: Option<f64>,
+
+  /// Position of the first pixel (`ImagePositionPatient`).
+  position: Option<Vector>,
+
+  /// Directions of the rows and columns (`ImageOrientationPatient`).
+  orientation: Option<[Vector; 2]>,
+
+  /// Number of the slice in the series.
+  instance_number: Option<i64>,
+
+  /// Slope and intercept mapping pixels to output units.
+  rescale: (f64, f64),
+
+  /// Identifier of the series the slice belongs to.
+  series_uid: Option<String>,
+
+  /// The pixels, in little-endian order.
+  pixel_data: Vec<u8>,
+}
+
+impl DicomSlice {
+  /// Load a [DICOM slice](DicomSlice) from the contents of a DICOM
+  /// file.
+  ///
+  /// # Arguments
+  ///
+  /// * `bytes` - The contents of the file, with or without the
+  ///   128-byte preamble and `DICM` prefix.
+  ///
+  /// # Returns
+  ///
+  /// The [DICOM slice](DicomSlice) or [an error](Err) in case the
+  /// file is truncated, has no pixel data, or uses a transfer syntax
+  /// or pixel format that is not supported.
+  ///
+  /// # Notes
+  ///
+  /// Only uncompressed single-frame grayscale images in explicit or
+  /// implicit VR little endian are supported. Files without file
+  /// meta information are assumed to be implicit VR little endian.
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, MedvizErr> {
+    let (start, has_meta) = match bytes.get(128..132) {
+      Some(b"DICM") => (132, true),
+      _ => (0, false),
+    };
+
+    let mut reader = Reader { bytes, position: start, explicit: true };
+    let mut transfer_syntax = String::from(IMPLICIT_VR_LITTLE_ENDIAN);
+
+    // The file meta information is always explicit VR little endian.
+    while has_meta && reader.peek_group() == Some(0x0002) {
+      let element = reader.element()?.ok_or(MedvizErr::new_dicom_invalid("truncated file"))?;
+      if element.tag == TRANSFER_SYNTAX_UID {
+        transfer_syntax = element.string();
+      }
+    }
+
+    reader.explicit = match transfer_syntax.as_str() {
+      IMPLICIT_VR_LITTLE_ENDIAN => false,
+      EXPLICIT_VR_LITTLE_ENDIAN => true,
+      _ => {
+        return Err(MedvizErr::new_dicom_unsupported(format!(
+          "transfer syntax {}",
+          transfer_syntax
+        )))
+      }
+    };
+
+    let mut elements: HashMap<Tag, &[u8]> = HashMap::new();
+    let pixel_data = loop {
+      let element = match reader.element()? {
+        Some(element) => element,
+        None => return Err(MedvizErr::new_dicom_invalid("missing pixel data")),
+      };
+
+      if element.tag == PIXEL_DATA {
+        break element.value;
+      }
+
+      // Only top-level elements are kept, sequences are skipped.
+      elements.insert(element.tag, element.value);
+    };
+
+    let string = |tag: Tag| elements.get(&tag).map(|value| Element::trim(value));
+    let number = |tag: Tag| -> Result<Option<u16>, MedvizErr> {
+      match elements.get(&tag).copied() {
+        Some(&[low, high, ..]) => Ok(Some(u16::from_le_bytes([low, high]))),
+        Some(_) => Err(MedvizErr::new_dicom_invalid("invalid unsigned short value")),
+        None => Ok(None),
+      }
+    };
+    let decimals = |tag: Tag| -> Result<Option<Vec<f64>>, MedvizErr> {
+      string(tag)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+          value
+            .split('\\')
+            .map(|value| value.trim().parse())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| MedvizErr::new_dicom_invalid("invalid decimal value"))
+        })
+        .transpose()
+    };
+    let decimal_array = |tag: Tag, expected: usize, reason: &'static str| match decimals(tag)? {
+      Some(values) if values.len() != expected => Err(MedvizErr::new_dicom_invalid(reason)),
+      values => Ok(values),
+    };
+
+    let samples = number(SAMPLES_PER_PIXEL)?.unwrap_or(1);
+    if samples != 1 {
+      return Err(MedvizErr::new_dicom_unsupported(format!("samples per pixel {}", samples)));
+    }
+
+    let frames = string(NUMBER_OF_FRAMES).unwrap_or_default();
+    if !frames.is_empty() && frames != "1" {
+      return Err(MedvizErr::new_dicom_unsupported(format!("number of frames {}", frames)));
+    }
+
+    let rows = number(ROWS)?.ok_or(MedvizErr::new_dicom_invalid("missing rows"))?;
+    let columns = number(COLUMNS)?.ok_or(MedvizErr::new_dicom_invalid("missing columns"))?;
+    let bits =
+      number(BITS_ALLOCATED)?.ok_or(MedvizErr::new_dicom_invalid("missing bits allocated"))?;
+    let signed = number(PIXEL_REPRESENTATION)?.unwrap_or(0) == 1;
+
+    let element_type = match (bits, signed) {
+      (8, false) => ElementType::UChar,
+      (8, true) => ElementType::Char,
+      (16, false) => ElementType::UShort,
+      (16, true) => ElementType::Short,
+      (32, false) => ElementType::UInt,
+      (32, true) => ElementType::Int,
+      _ => return Err(MedvizErr::new_dicom_unsupported(format!("bits allocated {}", bits))),
+    };
+
+    let (rows, columns) = (usize::from(rows), usize::from(columns));
+    let expected = rows * columns * element_type.size();
+    // Pixel data of odd length is padded with an extra byte.
+    let pixel_data = match pixel_data.get(..expected) {
+      Some(pixel_data) => pixel_data.to_vec(),
+      None => return Err(MedvizErr::new_data_size_mismatch(pixel_data.len(), expected)),
+    };
+
+    let vector = |values: &[f64]| -> Vector { [values[0], values[1], values[2]] };
+    let single = |tag: Tag| -> Result<Option<f64>, MedvizErr> {
+      Ok(decimals(tag)?.and_then(|values| values.first().copied()))
+    };
+
+    Ok(Self {
+      columns,
+      rows,
+      element_type,
+      pixel_spacing: decimal_array(PIXEL_SPACING, 2, "invalid pixel spacing")?
+        .map(|values| [values[0], values[1]]),
+      slice_thickness: single(SLICE_THICKNESS)?,
+      position: decimal_array(IMAGE_POSITION_PATIENT, 3, "invalid image position")?
+        .map(|values| vector(&values)),
+      orientation: decimal_array(IMAGE_ORIENTATION_PATIENT, 6, "invalid image orientation")?
+        .map(|values| [vector(&values[..3]), vector(&values[3..])]),
+      instance_number: string(INSTANCE_NUMBER).and_then(|value| value.parse().ok()),
+      rescale: (single(RESCALE_SLOPE)?.unwrap_or(1.0), single(RESCALE_INTERCEPT)?.unwrap_or(0.0)),
+      series_uid: string(SERIES_INSTANCE_UID),
+      pixel_data,
+    })
+  }
+
+  /// Identifier of the series the slice belongs to
+  /// (`SeriesInstanceUID`), if known.
+  pub fn series_uid(&self) -> Option<&str> {
+    self.series_uid.as_deref()
+  }
+
+  /// Number of the slice in the series (`InstanceNumber`), if known.
+  pub fn instance_number(&self) -> Option<i64> {
+    self.instance_number
+  }
+
+  /// Position of the first pixel in world (patient) coordinates
+  /// (`ImagePositionPatient`), if known.
+  pub fn position(&self) -> Option<[f64; 3]> {
+    self.position
+  }
+
+  /// Direction of the rows followed by the direction of the columns
+  /// (`ImageOrientationPatient`), if known.
+  pub fn orientation(&self) -> Option<[[f64; 3]; 2]> {
+    self.orientation
+  }
+
+  /// Whether the slope and intercept change the pixel values.
+  fn is_rescaled(&self) -> bool {
+    self.rescale != (1.0, 0.0)
+  }
+}
+
+/// Load an [image](Image) from a series of DICOM files in a
+/// directory.
+///
+/// Files that are not DICOM images (e.g. a `DICOMDIR`) are skipped,
+/// as are subdirectories. DICOM files using unsupported features are
+/// skipped with a warning. When the directory contains several
+/// series, the one with the most slices is used.
+///
+/// # Returns
+///
+/// The [image](Image) or [an error](Err) in case the
+/// directory or a file cannot be read, no supported slices remain or
+/// the slices cannot be combined.
+pub fn from_dir(path: &Path) -> Result<Image, MedvizErr> {
+  let io_err =
+    |path: &Path, e: std::io::Error| MedvizErr::new_io(path.display().to_string(), e.to_string());
+
+  let mut files = fs::read_dir(path)
+    .and_then(|entries| {
+      entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>()
+    })
+    .map_err(|e| io_err(path, e))?;
+  files.sort();
+
+  let mut slices = Vec::new();
+  let mut unsupported = None;
+  for file in files.iter().filter(|file| file.is_file()) {
+    let bytes = fs::read(file).map_err(|e| io_err(file, e))?;
+
+    match DicomSlice::from_bytes(&bytes) {
+      Ok(slice) => slices.push(slice),
+      Err(MedvizErr::DicomInvalid { reason }) => {
+        debug!("Skipping {}: {}", file.display(), reason)
+      }
+      Err(MedvizErr::DicomUnsupported { feature }) => {
+        warn!("Skipping {}: unsupported {}", file.display(), feature);
+        unsupported.get_or_insert(MedvizErr::new_dicom_unsupported(feature));
+      }
+      Err(e) => return Err(e),
+    }
+  }
+
+  debug!("Read {} DICOM slices from {}", slices.len(), path.display());
+
+  // Report why there are no slices when all of them were skipped.
+  if let (true, Some(e)) = (slices.is_empty(), unsupported) {
+    return Err(e);
+  }
+
+  from_slices(slices)
+}
+
+/// Build an [image](Image) from the slices of a DICOM series.
+///
+/// # Arguments
+///
+/// * `slices` - The slices, in any order. When they belong to
+///   several series, the series with the most slices is used.
+///
+/// # Returns
+///
+/// The [image](Image) or [an error](Err) in case there
+/// are no slices, the slices have different sizes or pixel types,
+/// or several slices are at the same position.
+///
+/// # Notes
+///
+/// Slices are sorted along the normal of their
+/// `ImageOrientationPatient` by their `ImagePositionPatient`, or by
+/// their `InstanceNumber` when positions are missing. The spacing
+/// between slices is the average distance between consecutive
+/// positions, falling back to the `SliceThickness`. When a
+/// `RescaleSlope` or `RescaleIntercept` changes the pixel values,
+/// the voxels are rescaled and stored as `MET_FLOAT`.
+pub fn from_slices(slices: Vec<DicomSlice>) -> Result<Image, MedvizErr> {
+  let mut slices = largest_series(slices);
+
+  let first = match slices.first() {
+    Some(first) => first.clone(),
+    None => return Err(MedvizErr::new_dicom_invalid_series("no slices")),
+  };
+
+  if slices.iter().any(|slice| {
+    (slice.columns, slice.rows, slice.element_type)
+      != (first.columns, first.rows, first.element_type)
+  }) {
+    return Err(MedvizErr::new_dicom_invalid_series("slices have different sizes or pixel types"));
+  }
+
+  let mut transform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+  let mut zspacing = first.slice_thickness.filter(|thickness| *thickness > 0.0).unwrap_or(1.0);
+
+  let normal = first.orientation.and_then(|[row, column]| {
+    Some([
+      vector::normalize(row)?,
+      vector::normalize(column)?,
+      vector::normalize(vector::cross(row, column))?,
+    ])
+  });
+
+  match normal {
+    Some(directions) if slices.iter().all(|slice| slice.position.is_some()) => {
+      let normal = directions[2];
+      let distance =
+        |slice: &DicomSlice| slice.position.map_or(0.0, |position| vector::dot(position, normal));
+      slices.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+
+      let distances: Vec<f64> = slices.iter().map(distance).collect();
+      if distances.windows(2).any(|pair| pair[1] - pair[0] < 1e-6) {
+        return Err(MedvizErr::new_dicom_invalid_series("several slices at the same position"));
+      }
+
+      if let (Some(first), Some(last)) = (distances.first(), distances.last()) {
+        if distances.len() > 1 {
+          zspacing = (last - first) / (distances.len() - 1) as f64;
+        }
+      }
+
+      if distances.windows(2).any(|pair| ((pair[1] - pair[0]) - zspacing).abs() > 0.01 * zspacing) {
+        warn!("DICOM slices are not evenly spaced, using an average spacing of {}", zspacing);
+      }
+
+      for (axis, direction) in directions.iter().enumerate() {
+        transform[axis * 3..axis * 3 + 3].copy_from_slice(direction);
+      }
+    }
+    _ => {
+      warn!("DICOM slices have no position and orientation, sorting by instance number");
+      slices.sort_by(|a, b| match (a.instance_number, b.instance_number) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+      });
+    }
+  }
+
+  // `PixelSpacing` is the distance between rows, then between
+  // columns.
+  let [row_spacing, column_spacing] = first.pixel_spacing.unwrap_or([1.0, 1.0]);
+  let offset = slices[0].position.unwrap_or([0.0; 3]);
+
+  let mut metadata = VolumeMd::new(first.columns, first.rows, slices.len())
+    .with_element_type(first.element_type)
+    .with_spacing([column_spacing, row_spacing, zspacing])
+    .with_offset(offset)
+    .with_transform(transform)
+    .with_orientation(AnatomicalOrientation::from_transform(transform));
+
+  let rescaled = slices.iter().any(DicomSlice::is_rescaled);
+  let mut data = Vec::new();
+  for slice in &slices {
+    if rescaled {
+      let (slope, intercept) = slice.rescale;
+      let (element_type, pixels) =
+        voxel::rescale(slice.element_type, false, &slice.pixel_data, slope, intercept);
+      metadata = metadata.with_element_type(element_type);
+      data.extend(pixels);
+    } else {
+      data.extend_from_slice(&slice.pixel_data);
+    }
+  }
+
+  Ok(Image::new(metadata, data))
+}
+
+/// Keep the slices of the series with the most slices.
+fn largest_series(slices: Vec<DicomSlice>) -> Vec<DicomSlice> {
+  let mut series: Vec<(Option<String>, Vec<DicomSlice>)> = Vec::new();
+
+  for slice in slices {
+    match series.iter_mut().find(|(uid, _)| *uid == slice.series_uid) {
+      Some((_, slices)) => slices.push(slice),
+      None => series.push((slice.series_uid.clone(), vec![slice])),
+    }
+  }
+
+  if series.len() > 1 {
+    warn!("Found {} DICOM series, using the one with the most slices", series.len());
+  }
+
+  series.into_iter().map(|(_, slices)| slices).max_by_key(Vec::len).unwrap_or_default()
+}
+
+/// A data element of a DICOM file.
+struct Element<'b> {
+  /// The tag of the element.
+  tag: Tag,
+
+  /// The value of the element, empty for sequences of undefined
+  /// length.
+  value: &'b [u8],
+}
+
+impl Element<'_> {
+  /// The value as a string, without padding.
+  fn string(&self) -> String {
+    Self::trim(self.value)
+  }
+
+  /// A string value without padding.
+  fn trim(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+      .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+      .to_string()
+  }
+}
+
+/// Reads the data elements of a DICOM file.
+struct Reader<'b> {
+  /// The contents of the file.
+  bytes: &'b [u8],
+
+  /// The position of the next element.
+  position: usize,
+
+  /// Whether value representations are explicit.
+  explicit: bool,
+}
+
+impl<'b> Reader<'b> {
+  /// Take the next `len` bytes.
+  fn take(&mut self, len: usize) -> Result<&'b [u8], MedvizErr> {
+    let end = self.position.checked_add(len).filter(|end| *end <= self.bytes.len());
+    let end = end.ok_or(MedvizErr::new_dicom_invalid("truncated file"))?;
+    let res = &self.bytes[self.position..end];
+    self.position = end;
+    Ok(res)
+  }
+
+  /// Read a 16-bit unsigned integer.
+  fn u16(&mut self) -> Result<u16, MedvizErr> {
+    let bytes = self.take(2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+  }
+
+  /// Read a 32-bit unsigned integer.
+  fn u32(&mut self) -> Result<u32, MedvizErr> {
+    let bytes = self.take(4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+  }
+
+  /// The group of the next element, if any.
+  fn peek_group(&self) -> Option<u16> {
+    let bytes = self.bytes.get(self.position..self.position + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+  }
+
+  /// Read the next data element.
+  ///
+  /// # Returns
+  ///
+  /// The element, `None` at the end of the file, or [an error](Err)
+  /// in case the file is truncated or the pixel data is compressed.
+  fn element(&mut self) -> Result<Option<Element<'b>>, MedvizErr> {
+    if self.position >= self.bytes.len() {
+      return Ok(None);
+    }
+
+    let tag = (self.u16()?, self.u16()?);
+
+    let len = if tag.0 == 0xFFFE {
+      // Items and delimiters never have a value representation.
+      self.u32()?
+    } else if self.explicit || tag.0 == 0x0002 {
+      let vr = self.take(2)?;
+      match vr {
+        b"OB" | b"OD" | b"OF" | b"OL" | b"OV" | b"OW" | b"SQ" | b"SV" | b"UC" | b"UN" | b"UR"
+        | b"UT" | b"UV" => {
+          self.take(2)?;
+          self.u32()?
+        }
+        _ => u32::from(self.u16()?),
+      }
+    } else {
+      self.u32()?
+    };
+
+    if len == UNDEFINED_LENGTH {
+      if tag == PIXEL_DATA {
+        return Err(MedvizErr::new_dicom_unsupported(String::from("compressed pixel data")));
+      }
+
+      self.skip_sequence()?;
+      return Ok(Some(Element { tag, value: &[] }));
+    }
+
+    let value = self.take(len as usize)?;
+    Ok(Some(Element { tag, value }))
+  }
+
+  /// Skip the items of a sequence of undefined length, up to its
+  /// delimiter.
+  fn skip_sequence(&mut self) -> Result<(), MedvizErr> {
+    loop {
+      let tag = (self.u16()?, self.u16()?);
+      let len = self.u32()?;
+
+      match tag {
+        SEQUENCE_DELIMITATION => return Ok(()),
+        ITEM if len == UNDEFINED_LENGTH => loop {
+          match self.element()? {
+            Some(element) if element.tag == ITEM_DELIMITATION => break,
+            Some(_) => {}
+            None => return Err(MedvizErr::new_dicom_invalid("truncated file")),
+          }
+        },
+        ITEM => {
+          self.take(len as usize)?;
+        }
+        _ => return Err(MedvizErr::new_dicom_invalid("invalid sequence item")),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod dicom_tests {
+  use super::{from_dir, from_slices, DicomSlice, IMPLICIT_VR_LITTLE_ENDIAN};
+  use crate::metadata::{AnatomicalOrientation, ElementType};
+  use crate::{MedvizErr, Voxel};
+  use std::fs;
+
+  /// Append an explicit VR element.
+  fn explicit(res: &mut Vec<u8>, group: u16, element: u16, vr: &[u8; 2], value: &[u8]) {
+    res.extend(group.to_le_bytes());
+    res.extend(element.to_le_bytes());
+    res.extend(vr);
+    match vr {
+      b"OB" | b"OW" | b"SQ" | b"UN" => {
+        res.extend([0, 0]);
+        res.extend((value.len() as u32).to_le_bytes());
+      }
+      _ => res.extend((value.len() as u16).to_le_bytes()),
+    }
+    res.extend(value);
+  }
+
+  /// Append an implicit VR element.
+  fn implicit(res: &mut Vec<u8>, group: u16, element: u16, value: &[u8]) {
+    res.extend(group.to_le_bytes());
+    res.extend(element.to_le_bytes());
+    res.extend((value.len() as u32).to_le_bytes());
+    res.extend(value);
+  }
+
+  /// A 2x2 slice of signed 16-bit pixels at a height of `z`.
+  fn slice(explicit_vr: bool, z: f64, instance: u16, pixels: [i16; 4]) -> Vec<u8> {
+    let mut res = vec![0; 128];
+    res.extend(b"DICM");
+
+    let transfer_syntax: &[u8] =
+      if explicit_vr { b"1.2.840.10008.1.2.1\0" } else { b"1.2.840.10008.1.2\0" };
+    explicit(&mut res, 0x0002, 0x0010, b"UI", transfer_syntax);
+
+    let position = format!("-10\\20\\{} ", z);
+    let instance = format!("{} ", instance);
+    let pixels: Vec<u8> = pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+
+    // A sequence of undefined length with an item of undefined length.
+    let mut sequence = Vec::new();
+    sequence.extend([0xFE, 0xFF, 0x00, 0xE0, 0xFF, 0xFF, 0xFF, 0xFF]);
+    if explicit_vr {
+      explicit(&mut sequence, 0x0008, 0x0100, b"SH", b"T-D1100 ");
+    } else {
+      implicit(&mut sequence, 0x0008, 0x0100, b"T-D1100 ");
+    }
+    sequence.extend([0xFE, 0xFF, 0x0D, 0xE0, 0, 0, 0, 0]);
+    sequence.extend([0xFE, 0xFF, 0xDD, 0xE0, 0, 0, 0, 0]);
+
+    let elements: Vec<(u16, u16, &[u8; 2], &[u8])> = vec![
+      (0x0018, 0x0050, b"DS", b"5 "),
+      (0x0020, 0x000E, b"UI", b"1.2.3\0"),
+      (0x0020, 0x0013, b"IS", instance.as_bytes()),
+      (0x0020, 0x0032, b"DS", position.as_bytes()),
+      (0x0020, 0x0037, b"DS", b"1\\0\\0\\0\\1\\0 "),
+      (0x0028, 0x0002, b"US", &[1, 0]),
+      (0x0028, 0x0010, b"US", &[2, 0]),
+      (0x0028, 0x0011, b"US", &[2, 0]),
+      (0x0028, 0x0030, b"DS", b"0.5\\0.25"),
+      (0x0028, 0x0100, b"US", &[16, 0]),
+      (0x0028, 0x0103, b"US", &[1, 0]),
+      (0x0028, 0x1052, b"DS", b"-1024 "),
+      (0x0028, 0x1053, b"DS", b"1 "),
+    ];
+
+    for (group, element, vr, value) in elements {
+      if explicit_vr {
+        explicit(&mut res, group, element, vr, value);
+      } else {
+        implicit(&mut res, group, element, value);
+      }
+
+      if (group, element) == (0x0018, 0x0050) {
+        res.extend([0x08, 0x00, 0x14, 0x11]);
+        if explicit_vr {
+          res.extend(b"SQ\0\0");
+        }
+        res.extend([0xFF, 0xFF, 0xFF, 0xFF]);
+        res.extend(&sequence);
+      }
+    }
+
+    if explicit_vr {
+      explicit(&mut res, 0x7FE0, 0x0010, b"OW", &pixels);
+    } else {
+      implicit(&mut res, 0x7FE0, 0x0010, &pixels);
+    }
+
+    res
+  }
+
+  #[test]
+  fn slice_explicit_and_implicit() {
+    for explicit_vr in [true, false] {
+      let slice = DicomSlice::from_bytes(&slice(explicit_vr, 7.5, 3, [1, 2, 3, 4])).unwrap();
+      assert_eq!(slice.series_uid(), Some("1.2.3"));
+      assert_eq!(slice.instance_number(), Some(3));
+      assert_eq!(slice.position(), Some([-10.0, 20.0, 7.5]));
+      assert_eq!(slice.orientation(), Some([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]));
+      assert_eq!(slice.element_type, ElementType::Short);
+      assert_eq!(slice.pixel_spacing, Some([0.5, 0.25]));
+      assert_eq!(slice.rescale, (1.0, -1024.0));
+      assert_eq!(slice.pixel_data, vec![1, 0, 2, 0, 3, 0, 4, 0]);
+    }
+  }
+
+  #[test]
+  fn series_sorted_by_position() {
+    let slices = vec![
+      DicomSlice::from_bytes(&slice(true, 10.0, 1, [20, 21, 22, 23])).unwrap(),
+      DicomSlice::from_bytes(&slice(false, 4.0, 3, [0, 1, 2, 3])).unwrap(),
+      DicomSlice::from_bytes(&slice(true, 7.0, 2, [10, 11, 12, 13])).unwrap(),
+    ];
+
+    let series = from_slices(slices).unwrap();
+    let metadata = series.metadata();
+    assert_eq!(metadata.dims(), [2, 2, 3]);
+    assert_eq!(metadata.spacing(), [0.25, 0.5, 3.0]);
+    assert_eq!(metadata.offset(), [-10.0, 20.0, 4.0]);
+    assert_eq!(metadata.orientation(), Some(AnatomicalOrientation::RAI));
+    assert_eq!(metadata.element_type(), ElementType::Float);
+
+    let volume = series.volume().unwrap();
+    assert_eq!(volume.get(0, 0, 0), Ok(Voxel::F32(-1024.0)));
+    assert_eq!(volume.get(1, 1, 1), Ok(Voxel::F32(-1011.0)));
+    assert_eq!(volume.get(1, 0, 2), Ok(Voxel::F32(-1003.0)));
+  }
+
+  #[test]
+  fn dir_skips_unsupported() {
+    let dir = std::env::temp_dir().join(format!("medviz-dicom-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut bytes = vec![0; 128];
+    bytes.extend(b"DICM");
+    explicit(&mut bytes, 0x0002, 0x0010, b"UI", b"1.2.840.10008.1.2.4.50");
+    fs::write(dir.join("jpeg.dcm"), bytes).unwrap();
+    assert_eq!(
+      from_dir(&dir),
+      Err(MedvizErr::new_dicom_unsupported(String::from("transfer syntax 1.2.840.10008.1.2.4.50")))
+    );
+
+    fs::write(dir.join("DICOMDIR"), [0; 16]).unwrap();
+    fs::write(dir.join("1.dcm"), slice(true, 0.0, 1, [1, 2, 3, 4])).unwrap();
+    fs::write(dir.join("2.dcm"), slice(true, 5.0, 2, [5, 6, 7, 8])).unwrap();
+    let image = from_dir(&dir).unwrap();
+    assert_eq!(image.metadata().zdim(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn fail() {
+    assert_eq!(
+      DicomSlice::from_bytes(&[0; 132]),
+      Err(MedvizErr::new_dicom_invalid("truncated file"))
+    );
+
+    let mut bytes = vec![0; 128];
+    bytes.extend(b"DICM");
+    explicit(&mut bytes, 0x0002, 0x0010, b"UI", b"1.2.840.10008.1.2.4.50");
+    assert_eq!(
+      DicomSlice::from_bytes(&bytes),
+      Err(MedvizErr::new_dicom_unsupported(String::from("transfer syntax 1.2.840.10008.1.2.4.50")))
+    );
+
+    let mut bytes = vec![0; 128];
+    bytes.extend(b"DICM");
+    let transfer_syntax = format!("{}\0", IMPLICIT_VR_LITTLE_ENDIAN);
+    explicit(&mut bytes, 0x0002, 0x0010, b"UI", transfer_syntax.as_bytes());
+    assert_eq!(
+      DicomSlice::from_bytes(&bytes),
+      Err(MedvizErr::new_dicom_invalid("missing pixel data"))
+    );
+
+    let same_position = vec![
+      DicomSlice::from_bytes(&slice(true, 1.0, 1, [0; 4])).unwrap(),
+      DicomSlice::from_bytes(&slice(true, 1.0, 2, [0; 4])).unwrap(),
+    ];
+    assert_eq!(
+      from_slices(same_position),
+      Err(MedvizErr::new_dicom_invalid_series("several slices at the same position"))
+    );
+    assert_eq!(from_slices(Vec::new()), Err(MedvizErr::new_dicom_invalid_series("no slices")));
+  }
+}
@@ -5,6 +5,11 @@
 //! number of modules is small a single error type for the whole
 //! library is workable.
 
+// The enum-level display attribute of `derive_more` 0.99 defines its
+// `impl` inside a function.
+#![allow(non_local_definitions)]
+
+use crate::Axis;
 use derive_more::{Display, From};
 use derive_new::new;
 use std::num::TryFromIntError;
@@ -13,10 +18,6 @@ use std::num::TryFromIntError;
 #[derive(new, Display, From, Debug, PartialEq, Eq)]
 #[display(fmt = "Medviz Error: {}")]
 pub enum Err {
-  /// Value is out of range.
-  #[display(fmt = "Voxel value {} is out of the 0-4095 range.", _0)]
-  VoxelValueOOR(u16),
-
   /// Found a DimSize key without any values.
   #[from(ignore)]
   #[display(fmt = "Metadata Line {}: Expecting values for `DimSize` key", line_number)]
@@ -36,9 +37,9 @@ pub enum Err {
     value: String,
   },
 
-  /// A duplicate `DimSize` key was found.
+  /// A duplicate metadata key was found.
   #[from(ignore)]
-  #[display(fmt = "Metadata Line {}: Duplicated `DimSize` key", line_number)]
+  #[display(fmt = "Metadata Line {}: Duplicated key", line_number)]
   MdDuplicateKey {
     /// The line number at which the error was found.
     line_number: usize,
@@ -49,6 +50,11 @@ pub enum Err {
   #[display(fmt = "Invalid metadata, `DimSize` key not found")]
   MdDimSizeNotFound,
 
+  /// Could not find an `ElementDataFile` key.
+  #[from(ignore)]
+  #[display(fmt = "Invalid metadata, `ElementDataFile` key not found")]
+  MdDataFileNotFound,
+
   /// Found too many values for `DimSize`.
   #[from(ignore)]
   #[display(fmt = "Line {}: Too many values for `DimSize` key", line_number)]
@@ -57,6 +63,58 @@ pub enum Err {
     line_number: usize,
   },
 
+  /// Found a key without any values.
+  #[from(ignore)]
+  #[display(fmt = "Metadata Line {}: Expecting values for `{}` key", line_number, key)]
+  MdMissingValues {
+    /// The line number at which the error was found.
+    line_number: usize,
+
+    /// The key missing its values.
+    key: &'static str,
+  },
+
+  /// One of the values of a key is invalid.
+  #[from(ignore)]
+  #[display(fmt = "Metadata Line {}: Invalid value {} for `{}` key", line_number, value, key)]
+  MdInvalidValue {
+    /// The line number at which the error was found.
+    line_number: usize,
+
+    /// The key the value belongs to.
+    key: &'static str,
+
+    /// The invalid value.
+    value: String,
+  },
+
+  /// Found too many values for a key.
+  #[from(ignore)]
+  #[display(fmt = "Metadata Line {}: Too many values for `{}` key", line_number, key)]
+  MdTooManyValues {
+    /// The line number at which the error was found.
+    line_number: usize,
+
+    /// The key with too many values.
+    key: &'static str,
+  },
+
+  /// The number of dimensions is not supported.
+  #[from(ignore)]
+  #[display(fmt = "Metadata Line {}: Unsupported number of dimensions {}", line_number, ndims)]
+  MdUnsupportedNDims {
+    /// The line number at which the error was found.
+    line_number: usize,
+
+    /// The number of dimensions found.
+    ndims: usize,
+  },
+
+  /// An NRRD header does not start with a `NRRD000X` magic line.
+  #[from(ignore)]
+  #[display(fmt = "Invalid NRRD header, expecting an `NRRD000X` magic line")]
+  NrrdInvalidMagic,
+
   /// Data size does not match metadata information.
   #[from(ignore)]
   #[display(
@@ -72,6 +130,37 @@ pub enum Err {
     expected: usize,
   },
 
+  /// The dimensions of a volume describe more data than can be
+  /// addressed in memory.
+  #[from(ignore)]
+  #[display(fmt = "Volume of {}x{}x{} voxels is too large", xdim, ydim, zdim)]
+  VolumeTooLarge {
+    /// The number of voxels on the X-axis.
+    xdim: usize,
+
+    /// The number of voxels on the Y-axis.
+    ydim: usize,
+
+    /// The number of voxels on the Z-axis.
+    zdim: usize,
+  },
+
+  /// An `ElementDataFile` value names no usable data files.
+  #[from(ignore)]
+  #[display(fmt = "Invalid data file {}", value)]
+  InvalidDataFile {
+    /// The value of the `ElementDataFile` key.
+    value: String,
+  },
+
+  /// The number of data files does not divide the volume data.
+  #[from(ignore)]
+  #[display(fmt = "Volume data cannot be split evenly over {} data files", files)]
+  DataFileCount {
+    /// The number of data files.
+    files: usize,
+  },
+
   /// Data size is uneven.
   #[display(fmt = "Data size of {} bytes is uneven", size)]
   #[from(ignore)]
@@ -80,7 +169,279 @@ pub enum Err {
     size: usize,
   },
 
+  /// An axis could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Invalid axis {}", axis)]
+  InvalidAxis {
+    /// The invalid axis.
+    axis: String,
+  },
+
+  /// An interpolation could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Invalid interpolation {}", interpolation)]
+  InvalidInterpolation {
+    /// The invalid interpolation.
+    interpolation: String,
+  },
+
+  /// A projection could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Invalid projection {}", projection)]
+  InvalidProjection {
+    /// The invalid projection.
+    projection: String,
+  },
+
+  /// A slab of frames is empty or outside the range of frames.
+  #[from(ignore)]
+  #[display(
+    fmt = "Slab {}:{} is empty or outside the {} frames on the {}-axis",
+    start,
+    end,
+    len,
+    axis
+  )]
+  InvalidSlab {
+    /// The axis of the slab.
+    axis: Axis,
+
+    /// The index of the first frame of the slab.
+    start: usize,
+
+    /// The index after the last frame of the slab.
+    end: usize,
+
+    /// The number of frames on the axis.
+    len: usize,
+  },
+
+  /// A voxel spacing is not positive.
+  #[from(ignore)]
+  #[display(fmt = "Invalid spacing {}, expecting positive values", spacing)]
+  InvalidSpacing {
+    /// The invalid spacing.
+    spacing: String,
+  },
+
+  /// A resampled volume would be too large to hold in memory.
+  #[from(ignore)]
+  #[display(fmt = "Resampling to spacing {} produces a volume too large for memory", spacing)]
+  ResampleTooLarge {
+    /// The requested spacing.
+    spacing: String,
+  },
+
+  /// A cropping range is empty or outside the range of voxels.
+  #[from(ignore)]
+  #[display(
+    fmt = "Crop range {}:{} is empty or outside the {} voxels on the {}-axis",
+    start,
+    end,
+    len,
+    axis
+  )]
+  InvalidCrop {
+    /// The axis of the range.
+    axis: Axis,
+
+    /// The index of the first voxel of the range.
+    start: usize,
+
+    /// The index after the last voxel of the range.
+    end: usize,
+
+    /// The number of voxels on the axis.
+    len: usize,
+  },
+
+  /// The plane of an oblique frame cannot be sampled.
+  #[from(ignore)]
+  #[display(fmt = "Invalid oblique frame: {}", reason)]
+  InvalidObliqueFrame {
+    /// Why the plane cannot be sampled.
+    reason: &'static str,
+  },
+
+  /// An orientation convention, flip or rotation could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Invalid orientation {}", orientation)]
+  InvalidOrientation {
+    /// The invalid orientation.
+    orientation: String,
+  },
+
+  /// A frame position could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Invalid frame position {}", position)]
+  InvalidFramePosition {
+    /// The invalid position.
+    position: String,
+  },
+
+  /// A frame position is outside the range of frames.
+  #[from(ignore)]
+  #[display(fmt = "Position {} is outside the {} frames on the {}-axis", position, len, axis)]
+  FramePositionOutOfRange {
+    /// The axis of the frame.
+    axis: Axis,
+
+    /// The position of the frame.
+    position: String,
+
+    /// The number of frames on the axis.
+    len: usize,
+  },
+
+  /// A frame index is outside the range of frames.
+  #[from(ignore)]
+  #[display(fmt = "Frame index {} is outside the {} frames on the {}-axis", index, len, axis)]
+  FrameIndexOutOfRange {
+    /// The axis of the frame.
+    axis: Axis,
+
+    /// The index of the frame.
+    index: usize,
+
+    /// The number of frames on the axis.
+    len: usize,
+  },
+
+  /// The mapping from voxel indexes to world coordinates cannot be
+  /// inverted, because of zero spacing or parallel axes.
+  #[from(ignore)]
+  #[display(fmt = "The transform of the volume cannot be inverted")]
+  SingularTransform,
+
+  /// A voxel position is outside the volume.
+  #[from(ignore)]
+  #[display(fmt = "Voxel ({}, {}, {}) is outside the volume", x, y, z)]
+  VoxelOutOfRange {
+    /// The position on the X-axis.
+    x: usize,
+
+    /// The position on the Y-axis.
+    y: usize,
+
+    /// The position on the Z-axis.
+    z: usize,
+  },
+
+  /// A filename template is invalid.
+  #[from(ignore)]
+  #[display(fmt = "Invalid filename template {}", template)]
+  InvalidTemplate {
+    /// The invalid template.
+    template: String,
+  },
+
+  /// A window could not be parsed or has a non-positive width.
+  #[from(ignore)]
+  #[display(fmt = "Invalid window {}", window)]
+  InvalidWindow {
+    /// The invalid window.
+    window: String,
+  },
+
+  /// A colormap name is not one of the built-in colormaps.
+  #[from(ignore)]
+  #[display(fmt = "Unknown colormap {}", name)]
+  InvalidColormap {
+    /// The unknown name.
+    name: String,
+  },
+
+  /// A colormap entry could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Colormap Line {}: Invalid entry", line_number)]
+  LutInvalidEntry {
+    /// Line number.
+    line_number: usize,
+  },
+
+  /// A colormap has too few entries to interpolate between.
+  #[from(ignore)]
+  #[display(fmt = "Colormap has {} entries, at least 2 are required", entries)]
+  LutTooFewEntries {
+    /// The number of entries.
+    entries: usize,
+  },
+
+  /// A NIfTI header is truncated or inconsistent.
+  #[from(ignore)]
+  #[display(fmt = "Invalid NIfTI header: {}", reason)]
+  NiftiInvalidHeader {
+    /// What is wrong with the header.
+    reason: &'static str,
+  },
+
+  /// A NIfTI datatype has no corresponding element type.
+  #[from(ignore)]
+  #[display(fmt = "Unsupported NIfTI datatype {}", datatype)]
+  NiftiUnsupportedDatatype {
+    /// The datatype code found in the header.
+    datatype: i32,
+  },
+
+  /// A DICOM file is truncated or inconsistent.
+  #[from(ignore)]
+  #[display(fmt = "Invalid DICOM file: {}", reason)]
+  DicomInvalid {
+    /// What is wrong with the file.
+    reason: &'static str,
+  },
+
+  /// A DICOM file uses a feature that is not supported.
+  #[from(ignore)]
+  #[display(fmt = "Unsupported DICOM {}", feature)]
+  DicomUnsupported {
+    /// The unsupported feature and its value.
+    feature: String,
+  },
+
+  /// The files of a DICOM series cannot be combined into a volume.
+  #[from(ignore)]
+  #[display(fmt = "Invalid DICOM series: {}", reason)]
+  DicomInvalidSeries {
+    /// Why the files cannot be combined.
+    reason: &'static str,
+  },
+
+  /// A file or directory could not be read.
+  #[from(ignore)]
+  #[display(fmt = "Cannot read {}: {}", path, reason)]
+  Io {
+    /// The path of the file or directory.
+    path: String,
+
+    /// The underlying error.
+    reason: String,
+  },
+
+  /// Decompression errors.
+  #[from(ignore)]
+  #[display(fmt = "Decompression error: {}", _0)]
+  Decompression(String),
+
+  /// Png encoding errors.
+  #[from(ignore)]
+  #[display(fmt = "Png encoding error: {}", _0)]
+  PngEncoding(String),
+
   /// Dimension conversion errors.
   #[display(fmt = "Dimension conversion error: {}", _0)]
   DimConversion(TryFromIntError),
 }
+
+impl std::error::Error for Err {}
+
+#[cfg(test)]
+mod error_tests {
+  use super::Err;
+
+  #[test]
+  fn display_prefix() {
+    let err = Err::new_volume_too_large(1, 2, 3);
+    assert_eq!(err.to_string(), "Medviz Error: Volume of 1x2x3 voxels is too large");
+  }
+}
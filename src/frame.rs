@@ -0,0 +1,256 @@
+//! Handles frames held in memory. The primary structure is the
+//! [frame struct](Frame).
+
+use crate::MedvizErr;
+use crate::Voxel;
+
+/// A frame whose voxels have been collected into memory.
+///
+/// Frames produced by a [volume](crate::Volume) are lazy iterators,
+/// which is enough for writing them out but not for operations that
+/// need to look at neighboring voxels, like resampling.
+#[derive(Clone)]
+pub struct Frame {
+  /// Number of voxels in a row.
+  width: usize,
+
+  /// Number of rows.
+  height: usize,
+
+  /// Voxels in row-major order.
+  voxels: Vec<Voxel>,
+}
+
+impl Frame {
+  /// The largest number of voxels an axis is stretched to by
+  /// [`with_aspect`](Frame::with_aspect).
+  pub const MAX_STRETCHED_LEN: usize = 16384;
+
+  /// Collect a frame from a row-major iterator over frame voxels.
+  ///
+  /// # Arguments
+  ///
+  /// * `width` - The number of voxels in a row.
+  ///
+  /// * `height` - The number of rows.
+  ///
+  /// * `frame_iter` - The row-major iterator over frame voxels.
+  ///
+  /// # Returns
+  ///
+  /// The collected frame or the first [error](Err) produced by
+  /// `frame_iter`.
+  pub fn from_iter(
+    width: usize,
+    height: usize,
+    frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+  ) -> Result<Self, MedvizErr> {
+    let mut voxels = Vec::with_capacity(width * height);
+
+    for (voxel, _, _) in frame_iter {
+      voxels.push(voxel?);
+    }
+
+    if voxels.len() != width * height {
+      return Err(MedvizErr::new_data_size_mismatch(voxels.len(), width * height));
+    }
+
+    Ok(Self { width, height, voxels })
+  }
+
+  /// Number of voxels in a row.
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  /// Number of rows.
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  /// The voxel at column `x` and row `y`.
+  ///
+  /// # Notes
+  ///
+  /// Panics if `x` or `y` are outside the frame.
+  pub fn voxel(&self, x: usize, y: usize) -> Voxel {
+    assert!(x < self.width && y < self.height);
+    self.voxels[y * self.width + x]
+  }
+
+  /// Create an iterator over the voxels in the frame.
+  ///
+  /// The iterator produces the same items as the frame iterators of
+  /// a [volume](crate::Volume), so it can be passed to the same
+  /// functions (e.g. [`frame_bmp`](crate::utils::frame_bmp)).
+  pub fn iter(&self) -> impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + '_ {
+    self.voxels.iter().enumerate().map(move |(index, voxel)| {
+      // `index` was produced by the call to .enumerate().
+      (Ok(*voxel), index % self.width, index / self.width)
+    })
+  }
+
+  /// Resample the frame to a new size using linear interpolation.
+  ///
+  /// # Arguments
+  ///
+  /// * `width` - The number of voxels in a row of the new frame.
+  ///
+  /// * `height` - The number of rows of the new frame.
+  ///
+  /// # Returns
+  ///
+  /// The resampled frame. An empty frame (with no voxels) has nothing
+  /// to interpolate and is returned unchanged.
+  pub fn resize(&self, width: usize, height: usize) -> Self {
+    if (width == self.width && height == self.height) || self.voxels.is_empty() {
+      return self.clone();
+    }
+
+    let mut voxels = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+      let (y0, y1, yfrac) = source_position(y, height, self.height);
+
+      for x in 0..width {
+        let (x0, x1, xfrac) = source_position(x, width, self.width);
+
+        let value = |x, y| self.voxel(x, y).value();
+        let top = value(x0, y0) * (1.0 - xfrac) + value(x1, y0) * xfrac;
+        let bottom = value(x0, y1) * (1.0 - xfrac) + value(x1, y1) * xfrac;
+        let value = top * (1.0 - yfrac) + bottom * yfrac;
+
+        voxels.push(self.voxel(x0, y0).with_value(value));
+      }
+    }
+
+    Self { width, height, voxels }
+  }
+
+  /// Resample the frame so that its voxels come out square.
+  ///
+  /// The axis with the coarser spacing is stretched to match the
+  /// finer one, so no detail is lost. A stretched axis is limited to
+  /// [`MAX_STRETCHED_LEN`](Frame::MAX_STRETCHED_LEN) voxels (or its
+  /// current length if longer), so extreme spacing ratios cannot
+  /// produce huge frames.
+  ///
+  /// # Arguments
+  ///
+  /// * `hspacing` - The physical distance between voxels in a row.
+  ///
+  /// * `vspacing` - The physical distance between rows.
+  ///
+  /// # Returns
+  ///
+  /// The resampled frame.
+  pub fn with_aspect(&self, hspacing: f64, vspacing: f64) -> Self {
+    let spacing = hspacing.min(vspacing);
+
+    if spacing.is_nan() || spacing <= 0.0 {
+      // Invalid spacing, nothing sensible can be done.
+      return self.clone();
+    }
+
+    // Float to integer casts saturate, so huge ratios are capped too.
+    let stretch = |len: usize, spacing_ratio: f64| {
+      let stretched = (len as f64 * spacing_ratio).round() as usize;
+      stretched.min(len.max(Self::MAX_STRETCHED_LEN)).max(1)
+    };
+
+    self.resize(stretch(self.width, hspacing / spacing), stretch(self.height, vspacing / spacing))
+  }
+}
+
+/// Find the source voxels to interpolate between for a resampled
+/// voxel.
+///
+/// # Arguments
+///
+/// * `index` - The index of the resampled voxel.
+///
+/// * `len` - The number of resampled voxels.
+///
+/// * `source_len` - The number of source voxels.
+///
+/// # Returns
+///
+/// The indexes of the two source voxels and the weight of the second
+/// one.
+fn source_position(index: usize, len: usize, source_len: usize) -> (usize, usize, f64) {
+  // Voxel centers of both frames are aligned on the same extent.
+  let position = (index as f64 + 0.5) * source_len as f64 / len as f64 - 0.5;
+  let position = position.max(0.0).min((source_len - 1) as f64);
+  let index0 = position.floor() as usize;
+  let index1 = (index0 + 1).min(source_len - 1);
+  (index0, index1, position - index0 as f64)
+}
+
+#[cfg(test)]
+mod frame_tests {
+  use super::Frame;
+  use crate::Voxel;
+
+  fn frame(width: usize, height: usize, values: &[u16]) -> Frame {
+    let iter =
+      values.iter().enumerate().map(|(i, value)| (Ok(Voxel::U16(*value)), i % width, i / width));
+    Frame::from_iter(width, height, iter).unwrap()
+  }
+
+  fn values(frame: &Frame) -> Vec<f64> {
+    frame.iter().map(|(voxel, _, _)| voxel.unwrap().value()).collect()
+  }
+
+  #[test]
+  fn from_iter_size_mismatch() {
+    let iter = (0..3).map(|i| (Ok(Voxel::U16(0)), i, 0));
+    assert!(Frame::from_iter(2, 2, iter).is_err());
+  }
+
+  #[test]
+  fn iter_coordinates() {
+    let frame = frame(2, 2, &[1, 2, 3, 4]);
+    let coords: Vec<_> = frame.iter().map(|(_, x, y)| (x, y)).collect();
+    assert_eq!(coords, vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+  }
+
+  #[test]
+  fn resize_identity() {
+    let frame = frame(2, 2, &[1, 2, 3, 4]);
+    assert_eq!(values(&frame.resize(2, 2)), vec![1.0, 2.0, 3.0, 4.0]);
+  }
+
+  #[test]
+  fn resize_stretch() {
+    let frame = frame(1, 2, &[0, 100]);
+    let resized = frame.resize(1, 4);
+    assert_eq!(values(&resized), vec![0.0, 25.0, 75.0, 100.0]);
+  }
+
+  #[test]
+  fn with_aspect() {
+    let frame = frame(4, 2, &[0; 8]);
+    let resized = frame.with_aspect(0.5, 1.0);
+    assert_eq!((resized.width(), resized.height()), (4, 4));
+
+    let resized = frame.with_aspect(1.0, 1.0);
+    assert_eq!((resized.width(), resized.height()), (4, 2));
+  }
+
+  #[test]
+  fn with_aspect_bounded() {
+    let frame = frame(4, 2, &[0; 8]);
+    let resized = frame.with_aspect(1.0, 1e-300);
+    assert_eq!((resized.width(), resized.height()), (Frame::MAX_STRETCHED_LEN, 2));
+  }
+
+  #[test]
+  fn resize_empty() {
+    for (width, height) in [(0, 4), (4, 0)] {
+      let frame = Frame::from_iter(width, height, std::iter::empty()).unwrap();
+      let resized = frame.with_aspect(1.0, 2.0);
+      assert_eq!((resized.width(), resized.height()), (width, height));
+      assert_eq!(resized.iter().count(), 0);
+    }
+  }
+}
@@ -0,0 +1,148 @@
+//! Handles volumes loaded from image files. The primary structure is
+//! the [image struct](Image).
+
+use crate::metadata::DataFile;
+use crate::MedvizErr;
+use crate::Volume;
+use crate::VolumeMd;
+use log::debug;
+use memmap::{Mmap, MmapOptions};
+use std::fs::File;
+use std::ops::Range;
+
+/// The voxels of an image.
+#[derive(Debug)]
+enum ImageData {
+  /// Voxels mapped from a raw data file, within the range following
+  /// its header.
+  Mapped(Mmap, Range<usize>),
+
+  /// Voxels read into memory (e.g. decompressed).
+  Owned(Vec<u8>),
+}
+
+/// A volume loaded from a MetaImage, NRRD or NIfTI file, or from a
+/// DICOM series.
+///
+/// The voxels of an uncompressed data file are mapped into memory
+/// rather than read, so large volumes can be opened quickly. All
+/// other voxels (compressed, local or split over several data files)
+/// are read into memory.
+#[derive(Debug)]
+pub struct Image {
+  /// Metadata related to the volume.
+  metadata: VolumeMd,
+
+  /// The voxels of the volume.
+  data: ImageData,
+}
+
+impl Image {
+  /// Create an [image](Image) from metadata and voxels held in
+  /// memory.
+  ///
+  /// # Arguments
+  ///
+  /// * `metadata` - Metadata related to the volume.
+  ///
+  /// * `data` - The voxels, in the byte order given by `metadata`.
+  pub fn new(metadata: VolumeMd, data: Vec<u8>) -> Self {
+    Self { metadata, data: ImageData::Owned(data) }
+  }
+
+  /// Load the voxels of an [image](Image) described by metadata.
+  ///
+  /// # Arguments
+  ///
+  /// * `metadata` - Metadata related to the volume.
+  ///
+  /// * `data_file` - Where the volume data is stored (see
+  ///   [`resolve_data_file`](VolumeMd::resolve_data_file)).
+  ///
+  /// * `local` - The bytes following the metadata, used for a
+  ///   [local](DataFile::Local) data file.
+  ///
+  /// # Returns
+  ///
+  /// The [image](Image) or [an error](Err) in case a file cannot be
+  /// read or mapped, decompression fails or the size of the data does
+  /// not match the dimensions and element type.
+  pub fn read(metadata: VolumeMd, data_file: &DataFile, local: &[u8]) -> Result<Self, MedvizErr> {
+    let data = match data_file {
+      DataFile::Single(path) if !metadata.compressed() => {
+        let io_err =
+          |e: std::io::Error| MedvizErr::new_io(path.display().to_string(), e.to_string());
+        let file = File::open(path).map_err(io_err)?;
+        // The file is only read while the image exists.
+        let map = unsafe { MmapOptions::new().map(&file) }.map_err(io_err)?;
+        let range = metadata.data_range(map.len())?;
+        debug!("Mapped {} bytes of data from {}", range.len(), path.display());
+        ImageData::Mapped(map, range)
+      }
+      data_file => ImageData::Owned(metadata.read_data(data_file, local)?),
+    };
+
+    Ok(Self { metadata, data })
+  }
+
+  /// Metadata related to the volume.
+  pub fn metadata(&self) -> &VolumeMd {
+    &self.metadata
+  }
+
+  /// The voxels of the volume, in the byte order given by the
+  /// metadata.
+  pub fn data(&self) -> &[u8] {
+    match &self.data {
+      ImageData::Mapped(map, range) => &map[range.clone()],
+      ImageData::Owned(data) => data,
+    }
+  }
+
+  /// Create a [volume](Volume) borrowing the voxels of the image.
+  pub fn volume(&self) -> Result<Volume<'_>, MedvizErr> {
+    Volume::from_slice(self.metadata.clone(), self.data())
+  }
+}
+
+impl PartialEq for Image {
+  fn eq(&self, other: &Self) -> bool {
+    self.metadata == other.metadata && self.data() == other.data()
+  }
+}
+
+#[cfg(test)]
+mod image_tests {
+  use super::Image;
+  use crate::metadata::{DataFile, ElementType, HeaderSize};
+  use crate::{MedvizErr, VolumeMd};
+  use std::fs;
+
+  #[test]
+  fn read_mapped_and_owned() {
+    let dir = std::env::temp_dir().join(format!("medviz-image-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("scan.raw"), [9, 1, 2]).unwrap();
+
+    let metadata = VolumeMd::new(2, 1, 1)
+      .with_element_type(ElementType::UChar)
+      .with_header_size(HeaderSize::Bytes(1));
+    let data_file = DataFile::Single(dir.join("scan.raw"));
+
+    let mapped = Image::read(metadata.clone(), &data_file, &[]).unwrap();
+    assert_eq!(mapped.data(), [1, 2]);
+    assert_eq!(mapped.volume().unwrap().get(1, 0, 0).unwrap().value(), 2.0);
+
+    let local = Image::read(metadata.clone(), &DataFile::Local, &[9, 1, 2]).unwrap();
+    assert_eq!(local, mapped);
+    assert_eq!(local, Image::new(metadata.clone(), vec![1, 2]));
+
+    let metadata = metadata.with_header_size(HeaderSize::Bytes(0));
+    assert_eq!(
+      Image::read(metadata, &data_file, &[]),
+      Err(MedvizErr::new_data_size_mismatch(3, 2))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,157 @@
+//! Handles positions of voxels in a volume. The primary structure is
+//! the [voxel index struct](VoxelIndex).
+
+use crate::Axis;
+use std::fmt;
+
+/// Position of a voxel in a volume.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct VoxelIndex {
+  /// Position on the X-axis.
+  pub x: usize,
+
+  /// Position on the Y-axis.
+  pub y: usize,
+
+  /// Position on the Z-axis.
+  pub z: usize,
+}
+
+/// Which voxels count as neighbors of a voxel.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Connectivity {
+  /// The 6 voxels sharing a face with the voxel.
+  Six,
+
+  /// The 26 voxels sharing a face, an edge or a corner with the voxel.
+  TwentySix,
+}
+
+impl VoxelIndex {
+  /// Create a voxel index.
+  pub fn new(x: usize, y: usize, z: usize) -> Self {
+    Self { x, y, z }
+  }
+
+  /// Position on an axis.
+  pub fn get(&self, axis: Axis) -> usize {
+    match axis {
+      Axis::X => self.x,
+      Axis::Y => self.y,
+      Axis::Z => self.z,
+    }
+  }
+
+  /// Whether the voxel is inside a volume with dimensions `dims`.
+  pub fn is_inside(&self, dims: [usize; 3]) -> bool {
+    self.x < dims[0] && self.y < dims[1] && self.z < dims[2]
+  }
+
+  /// Create an iterator over the neighbors of the voxel.
+  ///
+  /// # Arguments
+  ///
+  /// * `connectivity` - Which voxels count as neighbors.
+  ///
+  /// * `dims` - The dimensions of the volume. Neighbors outside the
+  ///   volume are skipped.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the neighbors inside the volume, Z varying
+  /// slowest and X fastest.
+  pub fn neighbors(
+    self,
+    connectivity: Connectivity,
+    dims: [usize; 3],
+  ) -> impl Iterator<Item = Self> {
+    const OFFSETS: [isize; 3] = [-1, 0, 1];
+
+    let offsets = OFFSETS.iter().flat_map(|&dz| {
+      OFFSETS.iter().flat_map(move |&dy| OFFSETS.iter().map(move |&dx| [dx, dy, dz]))
+    });
+
+    offsets
+      .filter(move |offset| {
+        let distance = offset.iter().filter(|d| **d != 0).count();
+        match connectivity {
+          Connectivity::Six => distance == 1,
+          Connectivity::TwentySix => distance != 0,
+        }
+      })
+      .filter_map(move |[dx, dy, dz]| {
+        let index = Self::new(
+          self.x.checked_add_signed(dx)?,
+          self.y.checked_add_signed(dy)?,
+          self.z.checked_add_signed(dz)?,
+        );
+        index.is_inside(dims).then_some(index)
+      })
+  }
+}
+
+impl From<[usize; 3]> for VoxelIndex {
+  fn from([x, y, z]: [usize; 3]) -> Self {
+    Self::new(x, y, z)
+  }
+}
+
+impl From<(usize, usize, usize)> for VoxelIndex {
+  fn from((x, y, z): (usize, usize, usize)) -> Self {
+    Self::new(x, y, z)
+  }
+}
+
+impl From<VoxelIndex> for [usize; 3] {
+  fn from(index: VoxelIndex) -> Self {
+    [index.x, index.y, index.z]
+  }
+}
+
+impl fmt::Display for VoxelIndex {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "({}, {}, {})", self.x, self.y, self.z)
+  }
+}
+
+#[cfg(test)]
+mod voxel_index_tests {
+  use super::{Connectivity, VoxelIndex};
+  use crate::Axis;
+
+  #[test]
+  fn conversions() {
+    let index = VoxelIndex::new(1, 2, 3);
+    assert_eq!(VoxelIndex::from([1, 2, 3]), index);
+    assert_eq!(VoxelIndex::from((1, 2, 3)), index);
+    assert_eq!(<[usize; 3]>::from(index), [1, 2, 3]);
+    assert_eq!(index.get(Axis::Y), 2);
+    assert_eq!(index.to_string(), "(1, 2, 3)");
+  }
+
+  #[test]
+  fn neighbors_inside() {
+    let index = VoxelIndex::new(1, 1, 1);
+    let six: Vec<_> = index.neighbors(Connectivity::Six, [3, 3, 3]).collect();
+    assert_eq!(
+      six,
+      vec![
+        VoxelIndex::new(1, 1, 0),
+        VoxelIndex::new(1, 0, 1),
+        VoxelIndex::new(0, 1, 1),
+        VoxelIndex::new(2, 1, 1),
+        VoxelIndex::new(1, 2, 1),
+        VoxelIndex::new(1, 1, 2),
+      ]
+    );
+    assert_eq!(index.neighbors(Connectivity::TwentySix, [3, 3, 3]).count(), 26);
+  }
+
+  #[test]
+  fn neighbors_border() {
+    let index = VoxelIndex::new(0, 0, 0);
+    assert_eq!(index.neighbors(Connectivity::Six, [2, 2, 2]).count(), 3);
+    assert_eq!(index.neighbors(Connectivity::TwentySix, [2, 2, 2]).count(), 7);
+    assert_eq!(index.neighbors(Connectivity::TwentySix, [1, 1, 1]).count(), 0);
+  }
+}
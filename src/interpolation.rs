@@ -0,0 +1,254 @@
+//! Handles the ways voxel values are sampled between voxel centers.
+
+use crate::MedvizErr;
+use std::fmt;
+use std::str::FromStr;
+
+/// A way of sampling voxel values at positions between voxel centers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Interpolation {
+  /// The value of the closest voxel.
+  Nearest,
+
+  /// A weighted average of the 8 surrounding voxels.
+  Trilinear,
+
+  /// A cubic B-spline through the voxel values, weighting the 64
+  /// surrounding spline coefficients.
+  Cubic,
+}
+
+impl Interpolation {
+  /// All interpolations, in order.
+  pub const ALL: [Self; 3] = [Self::Nearest, Self::Trilinear, Self::Cubic];
+}
+
+impl fmt::Display for Interpolation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Nearest => f.write_str("nearest"),
+      Self::Trilinear => f.write_str("trilinear"),
+      Self::Cubic => f.write_str("cubic"),
+    }
+  }
+}
+
+impl FromStr for Interpolation {
+  type Err = MedvizErr;
+
+  /// Parse an interpolation from its name (e.g. `trilinear`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let name = text.trim();
+    Self::ALL
+      .iter()
+      .copied()
+      .find(|interpolation| interpolation.to_string().eq_ignore_ascii_case(name))
+      .ok_or_else(|| MedvizErr::new_invalid_interpolation(text.into()))
+  }
+}
+
+/// The coefficients of a cubic B-spline that interpolates the voxels
+/// of a volume.
+///
+/// The coefficients are found with the recursive prefilter of Unser
+/// et al. ("B-spline signal processing", 1993) along each axis, with
+/// mirrored boundaries. They are stored as `f32`, also while being
+/// computed, to halve the memory they take.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct BSpline {
+  /// Number of coefficients on the X-, Y- and Z-axis.
+  dims: [usize; 3],
+
+  /// Coefficients, X varying fastest and Z slowest.
+  coefficients: Vec<f32>,
+}
+
+impl BSpline {
+  /// The pole of the cubic B-spline prefilter, `sqrt(3) - 2`.
+  const POLE: f64 = -0.267_949_192_431_122_7;
+
+  /// The distance in voxels beyond which a voxel no longer changes
+  /// the spline within the precision of its `f32` coefficients. A
+  /// spline through a region of a volume matches the spline through
+  /// the whole volume this far inside the region.
+  pub(crate) const MARGIN: usize = 16;
+
+  /// Compute the coefficients of the spline through voxel values.
+  ///
+  /// # Arguments
+  ///
+  /// * `dims` - Number of voxels on the X-, Y- and Z-axis.
+  ///
+  /// * `values` - The voxel values, X varying fastest and Z slowest.
+  pub(crate) fn new(dims: [usize; 3], values: impl Iterator<Item = f64>) -> Self {
+    let mut coefficients: Vec<f32> = values.map(|value| value as f32).collect();
+    let mut line = Vec::new();
+
+    for axis in 0..3 {
+      let len = dims[axis];
+      let stride: usize = dims[..axis].iter().product();
+
+      for start in 0..coefficients.len() {
+        // Visit each line along `axis` once, from its first element.
+        if !(start / stride).is_multiple_of(len) {
+          continue;
+        }
+
+        line.clear();
+        line.extend((0..len).map(|index| f64::from(coefficients[start + index * stride])));
+        Self::prefilter(&mut line);
+
+        for (index, coefficient) in line.iter().enumerate() {
+          coefficients[start + index * stride] = *coefficient as f32;
+        }
+      }
+    }
+
+    Self { dims, coefficients }
+  }
+
+  /// Turn the values of a line into spline coefficients, in place.
+  fn prefilter(line: &mut [f64]) {
+    let len = line.len();
+    if len < 2 {
+      return;
+    }
+
+    let pole = Self::POLE;
+    let gain = (1.0 - pole) * (1.0 - 1.0 / pole);
+    line.iter_mut().for_each(|value| *value *= gain);
+
+    // Causal initialization, mirroring the line at its start. Terms
+    // below the tolerance are skipped on long lines.
+    let horizon = (f64::EPSILON.ln() / pole.abs().ln()).ceil() as usize;
+    let sum = if horizon < len {
+      let mut power = pole;
+      let mut sum = line[0];
+      for value in &line[1..horizon] {
+        sum += power * value;
+        power *= pole;
+      }
+      sum
+    } else {
+      let mut power = pole;
+      let mut mirrored = pole.powi(len as i32 - 1);
+      let mut sum = line[0] + mirrored * line[len - 1];
+      mirrored *= mirrored / pole;
+      for value in &line[1..len - 1] {
+        sum += (power + mirrored) * value;
+        power *= pole;
+        mirrored /= pole;
+      }
+      sum / (1.0 - power * power)
+    };
+    line[0] = sum;
+
+    for index in 1..len {
+      line[index] += pole * line[index - 1];
+    }
+
+    // Anti-causal initialization, mirroring the line at its end.
+    line[len - 1] = (pole / (pole * pole - 1.0)) * (pole * line[len - 2] + line[len - 1]);
+
+    for index in (0..len - 1).rev() {
+      line[index] = pole * (line[index + 1] - line[index]);
+    }
+  }
+
+  /// Evaluate the spline at a position given in (fractional) voxel
+  /// indexes.
+  pub(crate) fn value(&self, position: [f64; 3]) -> f64 {
+    let mut indexes = [[0; 4]; 3];
+    let mut weights = [[0.0; 4]; 3];
+
+    for axis in 0..3 {
+      let first = position[axis].floor();
+      let t = position[axis] - first;
+      weights[axis] = [
+        (1.0 - t).powi(3) / 6.0,
+        (4.0 - 6.0 * t * t + 3.0 * t.powi(3)) / 6.0,
+        (1.0 + 3.0 * t + 3.0 * t * t - 3.0 * t.powi(3)) / 6.0,
+        t.powi(3) / 6.0,
+      ];
+
+      for (offset, index) in indexes[axis].iter_mut().enumerate() {
+        *index = mirror(first as i64 - 1 + offset as i64, self.dims[axis]);
+      }
+    }
+
+    let mut value = 0.0;
+    for (z, z_weight) in indexes[2].iter().zip(weights[2]) {
+      for (y, y_weight) in indexes[1].iter().zip(weights[1]) {
+        let row = (z * self.dims[1] + y) * self.dims[0];
+        for (x, x_weight) in indexes[0].iter().zip(weights[0]) {
+          value += z_weight * y_weight * x_weight * f64::from(self.coefficients[row + x]);
+        }
+      }
+    }
+
+    value
+  }
+}
+
+/// Mirror an index into the range `0..len`, repeating the line
+/// without repeating its ends (e.g. `-1` is `1`).
+fn mirror(index: i64, len: usize) -> usize {
+  if len < 2 {
+    return 0;
+  }
+
+  let period = 2 * (len as i64 - 1);
+  let index = index.rem_euclid(period);
+  // The index is within 0..period, so the casts are safe.
+  if index < len as i64 {
+    index as usize
+  } else {
+    (period - index) as usize
+  }
+}
+
+#[cfg(test)]
+mod interpolation_tests {
+  use super::{mirror, BSpline, Interpolation};
+  use crate::MedvizErr;
+
+  #[test]
+  fn parse() {
+    assert_eq!("nearest".parse(), Ok(Interpolation::Nearest));
+    assert_eq!(" Trilinear ".parse(), Ok(Interpolation::Trilinear));
+    assert_eq!("CUBIC".parse(), Ok(Interpolation::Cubic));
+    assert_eq!(
+      "quintic".parse::<Interpolation>(),
+      Err(MedvizErr::InvalidInterpolation { interpolation: String::from("quintic") })
+    );
+  }
+
+  #[test]
+  fn bspline_interpolates() {
+    // Short lines use the exact initialization, long lines the
+    // truncated one.
+    for len in [1, 2, 3, 7, 40] {
+      let dims = [len, 3, 2];
+      let values: Vec<f64> =
+        (0..dims.iter().product::<usize>()).map(|index| ((index * 37) % 11) as f64).collect();
+      let spline = BSpline::new(dims, values.iter().copied());
+
+      for (index, value) in values.iter().enumerate() {
+        let position = [(index % len) as f64, ((index / len) % 3) as f64, (index / len / 3) as f64];
+        assert!((spline.value(position) - value).abs() < 1e-4, "{} at {:?}", len, position);
+      }
+    }
+
+    // A linear ramp is reproduced between voxel centers, away from
+    // the mirrored ends.
+    let spline = BSpline::new([40, 1, 1], (0..40).map(f64::from));
+    assert!((spline.value([20.25, 0.0, 0.0]) - 20.25).abs() < 1e-4);
+    assert!((spline.value([19.5, 0.0, 0.0]) - 19.5).abs() < 1e-4);
+  }
+
+  #[test]
+  fn mirror_index() {
+    assert_eq!([-2, -1, 0, 3, 4, 5].map(|index| mirror(index, 4)), [2, 1, 0, 3, 2, 1]);
+    assert_eq!(mirror(-1, 1), 0);
+  }
+}
@@ -8,13 +8,40 @@
 //! instead of "slices" to avoid confusion when also discussing Rust's
 //! slices.
 
+pub mod axis;
+pub mod colormap;
+mod compression;
+pub mod dicom;
 pub mod error;
+pub mod frame;
+pub mod image;
+pub mod index;
+pub mod interpolation;
 pub mod metadata;
+pub mod metaimage;
+pub mod nifti;
+pub mod orientation;
+pub mod position;
+pub mod projection;
 pub mod utils;
+mod vector;
 pub mod volume;
+pub mod volume_buf;
 pub mod voxel;
+pub mod window;
 
+pub use axis::Axis;
+pub use colormap::Colormap;
 pub use error::Err as MedvizErr;
+pub use frame::Frame;
+pub use image::Image;
+pub use index::{Connectivity, VoxelIndex};
+pub use interpolation::Interpolation;
 pub use metadata::VolumeMd;
-pub use volume::Volume;
+pub use orientation::{Convention, Flip, FrameOrientation, Rotation};
+pub use position::FramePosition;
+pub use projection::Projection;
+pub use volume::{FrameIter, Volume};
+pub use volume_buf::VolumeBuf;
 pub use voxel::Voxel;
+pub use window::Window;
@@ -3,19 +3,24 @@
 use clap::Parser;
 use derive_more::{Display, From};
 use derive_new::new;
-use log::{debug, info, trace};
-use medviz::utils;
-use medviz::{MedvizErr, Volume, VolumeMd, Voxel};
-use memmap::MmapOptions;
-use std::io::{self, BufWriter};
+use log::{debug, info, trace, warn};
+use medviz::metadata::{DataFile, HeaderSize};
+use medviz::utils::{self, PngDepth};
+use medviz::{dicom, metaimage, nifti};
+use medviz::{
+  Axis, Colormap, Convention, Flip, Frame, FrameOrientation, FramePosition, Image, Interpolation,
+  MedvizErr, Projection, Rotation, Volume, VolumeMd, Voxel, Window,
+};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
 use std::num::TryFromIntError;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::{fmt, io::Write};
-use std::{fs::File, io::Read};
 
 /// General top-level errors.
 #[derive(new, From, Display)]
-#[display(fmt = "{}")]
 enum Err {
   /// IO Errors.
   #[display(fmt = "IO Error: {}", _0)]
@@ -28,6 +33,56 @@ enum Err {
   /// Errors from the medviz library.
   #[display(fmt = "Library Error: {}", _0)]
   Medviz(MedvizErr),
+
+  /// A frame was requested without an output file.
+  #[from(ignore)]
+  #[display(fmt = "Usage Error: No output file for the {}-frame", _0)]
+  MissingOutput(Axis),
+
+  /// No frames were requested.
+  #[display(fmt = "Usage Error: No output files")]
+  NoOutput,
+
+  /// A crop region was requested without a volume file or frames to
+  /// write it to.
+  #[display(fmt = "Usage Error: --crop requires --convert or a frame output file")]
+  CropOutput,
+
+  /// A series was requested without selecting exactly one axis.
+  #[display(fmt = "Usage Error: A series requires exactly one axis")]
+  SeriesAxis,
+
+  /// A bit depth was requested that the image format does not
+  /// support.
+  #[display(fmt = "Usage Error: 16-bit images require the png format")]
+  BitDepth,
+
+  /// A vector could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Usage Error: Invalid vector {}, expecting X,Y,Z", _0)]
+  Vector(String),
+
+  /// A slab could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Usage Error: Invalid slab {}, expecting START:END", _0)]
+  Slab(String),
+
+  /// A crop region could not be parsed.
+  #[from(ignore)]
+  #[display(fmt = "Usage Error: Invalid region {}, expecting X0:X1,Y0:Y1,Z0:Z1", _0)]
+  Region(String),
+
+  /// A colormap was requested for images that are always grayscale.
+  #[display(fmt = "Usage Error: Colormaps require 8-bit images")]
+  ColormapDepth,
+
+  /// A volume file was requested in an unknown format.
+  #[from(ignore)]
+  #[display(
+    fmt = "Usage Error: Unknown format of volume file {}, expecting .nrrd, .nhdr, .mha or .mhd",
+    _0
+  )]
+  VolumeFormat(String),
 }
 
 /// When returning an error from main(), this will print its Display
@@ -38,6 +93,8 @@ impl fmt::Debug for Err {
   }
 }
 
+impl std::error::Error for Err {}
+
 /// Extract slices from volumetric data.
 #[derive(Debug, clap::Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -46,29 +103,394 @@ struct Opt {
   #[clap(short, long, action = clap::ArgAction::Count)]
   verbose: u8,
 
-  /// Produce raw data instead of bmp images.
+  /// Produce raw (little-endian) data instead of images.
   #[structopt(short, long)]
   raw: bool,
 
-  /// Input: Metadata file.
+  /// Resample images to the physical aspect ratio of the voxels.
+  #[clap(short, long)]
+  physical_aspect: bool,
+
+  /// Format of the produced images.
+  #[clap(short, long, value_enum, default_value_t = ImageFormat::Bmp)]
+  format: ImageFormat,
+
+  /// Bit depth of the produced images (16 requires png).
+  #[clap(short, long, value_enum, default_value_t = BitDepth::Eight)]
+  bit_depth: BitDepth,
+
+  /// Window of voxel values to display, as center and width (e.g.
+  /// -600,1500) or a preset (brain, lung, bone, abdomen).
+  #[clap(short, long, allow_hyphen_values = true)]
+  window: Option<Window>,
+
+  /// Colormap to render images with, as the name of a built-in
+  /// colormap (hot, jet, viridis, bone, inferno) or a colormap file.
+  #[clap(short, long, value_parser = parse_colormap)]
+  colormap: Option<Colormap>,
+
+  /// Convention for displaying the patient's left and right on axial
+  /// and coronal frames (radiological or neurological). Frames are
+  /// oriented using the anatomical orientation of the volume.
+  #[clap(long, default_value = "radiological")]
+  convention: Convention,
+
+  /// Flip frames after orienting them, horizontally and/or
+  /// vertically (e.g. `h` or `h,v`).
+  #[clap(long, value_delimiter = ',')]
+  flip: Vec<Flip>,
+
+  /// Rotate frames clockwise after orienting and flipping them (90,
+  /// 180 or 270).
+  #[clap(long)]
+  rotate: Option<Rotation>,
+
+  /// Input: Metadata file, a NIfTI image (`.nii` or `.nii.gz`)
+  /// containing both metadata and data, or a directory of DICOM
+  /// files.
   #[clap(short, long, name = "metadata-file")]
   metadata: PathBuf,
 
-  /// Input: Volumetric data file.
+  /// Input: Volumetric data file, overriding the data file named in
+  /// the metadata.
   #[clap(short, long, name = "data-file")]
-  data: PathBuf,
+  data: Option<PathBuf>,
 
-  /// Output: X frame file (bmp).
+  /// Output: X frame file.
   #[clap(short, long, name = "x-frame-file")]
-  xfile: PathBuf,
+  xfile: Option<PathBuf>,
 
-  /// Output: Y frame file (bmp).
+  /// Output: Y frame file.
   #[clap(short, long, name = "y-frame-file")]
-  yfile: PathBuf,
+  yfile: Option<PathBuf>,
 
-  /// Output: Z frame file (bmp).
+  /// Output: Z frame file.
   #[clap(short, long, name = "z-frame-file")]
-  zfile: PathBuf,
+  zfile: Option<PathBuf>,
+
+  /// Position of the X frame: an index, a percentage (e.g. 25%) or a
+  /// distance from the first frame in millimetres (e.g. 40mm).
+  #[clap(long, name = "x-position", default_value = "50%")]
+  x_index: FramePosition,
+
+  /// Position of the Y frame: an index, a percentage (e.g. 25%) or a
+  /// distance from the first frame in millimetres (e.g. 40mm).
+  #[clap(long, name = "y-position", default_value = "50%")]
+  y_index: FramePosition,
+
+  /// Position of the Z frame: an index, a percentage (e.g. 25%) or a
+  /// distance from the first frame in millimetres (e.g. 40mm).
+  #[clap(long, name = "z-position", default_value = "50%")]
+  z_index: FramePosition,
+
+  /// Point in world (patient) coordinates in millimetres that the
+  /// frames go through, as X,Y,Z (e.g. -10.5,42,120). Overrides the
+  /// frame positions.
+  #[clap(
+    long,
+    value_parser = parse_vector,
+    allow_hyphen_values = true,
+    conflicts_with_all = ["x-position", "y-position", "z-position"]
+  )]
+  world: Option<[f64; 3]>,
+
+  /// Axes of the frames to produce (e.g. `z` or `x,y`). Defaults to
+  /// all axes with an output file.
+  #[clap(short, long, value_delimiter = ',')]
+  axis: Vec<Axis>,
+
+  /// Output: All frames on the selected axis, using a filename
+  /// template with an `{index}` or `{index:0N}` placeholder (e.g.
+  /// `out/z_{index:04}.bmp`).
+  #[clap(
+    short,
+    long,
+    name = "filename-template",
+    conflicts_with_all = ["x-frame-file", "y-frame-file", "z-frame-file"]
+  )]
+  series: Option<String>,
+
+  /// Output: Oblique frame file, for the plane through `--point` and
+  /// perpendicular to `--normal`.
+  #[clap(
+    short,
+    long,
+    name = "oblique-frame-file",
+    requires = "normal",
+    conflicts_with_all = ["x-frame-file", "y-frame-file", "z-frame-file", "filename-template"]
+  )]
+  oblique_file: Option<PathBuf>,
+
+  /// Output: The whole volume, in the format given by the extension:
+  /// `.nrrd` or `.nhdr` for NRRD, `.mha` or `.mhd` for MetaImage. The
+  /// data is in the `.nrrd` or `.mha` file, or in a `.raw` file next to
+  /// an `.nhdr` or `.mhd` header.
+  #[clap(
+    long,
+    name = "volume-file",
+    conflicts_with_all = [
+      "x-frame-file",
+      "y-frame-file",
+      "z-frame-file",
+      "filename-template",
+      "oblique-frame-file",
+      "projection"
+    ]
+  )]
+  convert: Option<PathBuf>,
+
+  /// Compress the volume data written by `--convert` (gzip for NRRD,
+  /// zlib for MetaImage).
+  #[clap(long, requires = "volume-file")]
+  compress: bool,
+
+  /// Point the oblique frame goes through, as millimetres from the
+  /// first voxel along each axis (e.g. 120,100.5,60). Defaults to the
+  /// center of the volume.
+  #[clap(long, value_parser = parse_vector, allow_hyphen_values = true)]
+  point: Option<[f64; 3]>,
+
+  /// Normal of the plane of the oblique frame (e.g. 0,1,1).
+  #[clap(long, value_parser = parse_vector, allow_hyphen_values = true)]
+  normal: Option<[f64; 3]>,
+
+  /// Interpolation used to sample oblique frames and resampled
+  /// volumes (nearest, trilinear or cubic). Cubic interpolation
+  /// takes 4 bytes of memory per voxel of the sampled region.
+  #[clap(long, default_value = "trilinear")]
+  interpolation: Interpolation,
+
+  /// Project the frames along each selected axis instead of
+  /// extracting a single frame (mip, minip or avgip).
+  #[clap(long, conflicts_with_all = ["filename-template", "oblique-frame-file"])]
+  projection: Option<Projection>,
+
+  /// Frames to project, as START:END with END excluded (e.g.
+  /// 100:140). Defaults to all frames.
+  #[clap(long, value_parser = parse_slab, requires = "projection")]
+  slab: Option<Range<usize>>,
+
+  /// Crop the volume to a region of interest before any output, as
+  /// X0:X1,Y0:Y1,Z0:Z1 voxel indexes with the ends excluded (e.g.
+  /// 100:400,120:380,50:200). Requires `--convert` to save the
+  /// region with its adjusted offset, unless frames are written.
+  #[clap(long, value_parser = parse_region)]
+  crop: Option<[Range<usize>; 3]>,
+
+  /// Resample the volume to a new voxel spacing in millimetres before
+  /// any output, after cropping. Either a single value for isotropic
+  /// voxels (e.g. 0.4) or X,Y,Z (e.g. 0.5,0.5,1).
+  #[clap(long, value_parser = parse_spacing)]
+  resample: Option<[f64; 3]>,
+}
+
+/// Format of the produced images.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ImageFormat {
+  /// Bmp images.
+  #[display(fmt = "bmp")]
+  Bmp,
+
+  /// Png images.
+  #[display(fmt = "png")]
+  Png,
+}
+
+/// Bit depth of the produced images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum BitDepth {
+  /// 8-bit images, with voxel values normalized.
+  #[value(name = "8")]
+  Eight,
+
+  /// 16-bit images, with voxel values kept as is when unsigned and
+  /// offset by 32768 when signed or floating point. Values are
+  /// rounded and clamped to 0-65535, so only 8- and 16-bit voxels are
+  /// kept exactly.
+  #[value(name = "16")]
+  Sixteen,
+}
+
+/// What the program should produce.
+enum Mode<'o> {
+  /// Single frames on some axes, with their output files.
+  Frames(Vec<(Axis, &'o Path)>),
+
+  /// All frames on an axis, with a filename template.
+  Series(Axis, &'o str),
+
+  /// An oblique frame through a point and perpendicular to a normal,
+  /// with its output file.
+  Oblique(Option<[f64; 3]>, [f64; 3], &'o Path),
+
+  /// The whole volume as an NRRD file.
+  Nrrd(&'o Path),
+
+  /// The whole volume as a MetaImage.
+  MetaImage(&'o Path),
+}
+
+impl Opt {
+  /// Output file of the frame on an axis.
+  fn file(&self, axis: Axis) -> Option<&Path> {
+    match axis {
+      Axis::X => self.xfile.as_deref(),
+      Axis::Y => self.yfile.as_deref(),
+      Axis::Z => self.zfile.as_deref(),
+    }
+  }
+
+  /// Position of the frame on an axis.
+  fn position(&self, axis: Axis) -> FramePosition {
+    match axis {
+      Axis::X => self.x_index,
+      Axis::Y => self.y_index,
+      Axis::Z => self.z_index,
+    }
+  }
+
+  /// The flips and rotation requested for all frames.
+  fn orientation(&self) -> FrameOrientation {
+    let orientation = self
+      .flip
+      .iter()
+      .fold(FrameOrientation::IDENTITY, |orientation, flip| orientation.flip(*flip));
+
+    match self.rotate {
+      Some(rotation) => orientation.rotate(rotation),
+      None => orientation,
+    }
+  }
+
+  /// Decide what to produce from the options.
+  fn mode(&self) -> Result<Mode<'_>, Err> {
+    if self.bit_depth == BitDepth::Sixteen && self.format != ImageFormat::Png {
+      return Err(Err::BitDepth);
+    }
+
+    if self.bit_depth == BitDepth::Sixteen && self.colormap.is_some() {
+      return Err(Err::ColormapDepth);
+    }
+
+    if let Some(file) = &self.convert {
+      return if is_nrrd(file) {
+        Ok(Mode::Nrrd(file))
+      } else if is_metaimage(file) {
+        Ok(Mode::MetaImage(file))
+      } else {
+        Err(Err::new_volume_format(file.display().to_string()))
+      };
+    }
+
+    if let (Some(file), Some(normal)) = (&self.oblique_file, self.normal) {
+      return Ok(Mode::Oblique(self.point, normal, file));
+    }
+
+    match &self.series {
+      Some(template) => {
+        // Fail early on an invalid template.
+        utils::format_index(template, 0)?;
+
+        match self.axis.as_slice() {
+          [axis] => Ok(Mode::Series(*axis, template)),
+          _ => Err(Err::SeriesAxis),
+        }
+      }
+      None => match self.outputs() {
+        // Cropping on its own writes the region as a volume file.
+        Err(Err::NoOutput) if self.crop.is_some() => Err(Err::CropOutput),
+        outputs => Ok(Mode::Frames(outputs?)),
+      },
+    }
+  }
+
+  /// The axes of the frames to produce, with their output files.
+  fn outputs(&self) -> Result<Vec<(Axis, &Path)>, Err> {
+    let mut outputs = Vec::new();
+
+    if self.axis.is_empty() {
+      for &axis in &Axis::ALL {
+        if let Some(file) = self.file(axis) {
+          outputs.push((axis, file));
+        }
+      }
+    } else {
+      for &axis in &Axis::ALL {
+        match (self.axis.contains(&axis), self.file(axis)) {
+          (true, Some(file)) => outputs.push((axis, file)),
+          (true, None) => return Err(Err::MissingOutput(axis)),
+          (false, Some(file)) => {
+            warn!("Ignoring {}-frame file {}: axis not selected", axis, file.display())
+          }
+          (false, None) => {}
+        }
+      }
+    }
+
+    if outputs.is_empty() {
+      return Err(Err::NoOutput);
+    }
+
+    Ok(outputs)
+  }
+}
+
+/// Load a colormap, either built-in or from a file.
+fn parse_colormap(name: &str) -> Result<Colormap, Err> {
+  if let Some(colormap) = Colormap::builtin(name) {
+    return Ok(colormap);
+  }
+
+  let mut contents = String::new();
+  File::open(name)?.read_to_string(&mut contents)?;
+  Ok(Colormap::from_buffer(&contents)?)
+}
+
+/// Parse a vector from its components, separated by commas.
+fn parse_vector(text: &str) -> Result<[f64; 3], Err> {
+  let invalid = || Err::new_vector(text.into());
+  let mut components = text.split(',');
+  let mut vector = [0.0; 3];
+
+  for component in vector.iter_mut() {
+    *component = components.next().ok_or_else(invalid)?.trim().parse().map_err(|_| invalid())?;
+  }
+
+  if components.next().is_some() {
+    return Err(invalid());
+  }
+
+  Ok(vector)
+}
+
+/// Parse a slab from the indexes of its first frame and the frame
+/// after its last, separated by a colon.
+fn parse_slab(text: &str) -> Result<Range<usize>, Err> {
+  let invalid = || Err::new_slab(text.into());
+  let (start, end) = text.split_once(':').ok_or_else(invalid)?;
+  let start = start.trim().parse().map_err(|_| invalid())?;
+  let end = end.trim().parse().map_err(|_| invalid())?;
+  Ok(start..end)
+}
+
+/// Parse a voxel spacing, either a single value for all axes or a
+/// vector.
+fn parse_spacing(text: &str) -> Result<[f64; 3], Err> {
+  match text.trim().parse() {
+    Ok(spacing) => Ok([spacing; 3]),
+    Err(_) => parse_vector(text),
+  }
+}
+
+/// Parse a crop region from the ranges of voxel indexes on the X-,
+/// Y- and Z-axis, separated by commas.
+fn parse_region(text: &str) -> Result<[Range<usize>; 3], Err> {
+  let invalid = || Err::new_region(text.into());
+  let ranges: Vec<Range<usize>> = text
+    .split(',')
+    .map(|range| parse_slab(range).map_err(|_| invalid()))
+    .collect::<Result<_, _>>()?;
+  <[Range<usize>; 3]>::try_from(ranges).map_err(|_| invalid())
 }
 
 fn main() -> Result<(), Err> {
@@ -89,66 +511,385 @@ fn main() -> Result<(), Err> {
   debug!("Debug output enabled.");
   trace!("Tracing output enabled.");
 
-  let mut metadata_contents = String::new();
-  File::open(&opt.metadata)?.read_to_string(&mut metadata_contents)?;
-  let metadata = VolumeMd::from_buffer(&metadata_contents)?;
+  let mode = opt.mode()?;
+
+  let image = load(&opt)?;
+  let metadata = image.metadata();
 
   info!("Loaded metadata from {}", opt.metadata.display());
   info!("  X-dim = {}", metadata.xdim());
   info!("  Y-dim = {}", metadata.ydim());
   info!("  Z-dim = {}", metadata.zdim());
+  info!("  Spacing = {:?}", metadata.spacing());
+  info!("  Offset = {:?}", metadata.offset());
+  info!("  Element type = {}", metadata.element_type());
+  if let Some(orientation) = metadata.orientation() {
+    info!("  Orientation = {}", orientation);
+  }
 
-  let file = File::open(&opt.data)?;
-  let map = unsafe { MmapOptions::new().map(&file)? };
+  let image = match &opt.crop {
+    Some(region) => crop(&image, region)?,
+    None => image,
+  };
 
-  info!("Mapped {} bytes of data from {}", map.len(), opt.data.display());
+  let image = match opt.resample {
+    Some(spacing) => resample(&image, spacing, opt.interpolation)?,
+    None => image,
+  };
 
-  let volume = Volume::from_slice(metadata, &map)?;
+  let volume = image.volume()?;
+  let metadata = volume.metadata();
+
+  match mode {
+    Mode::Frames(outputs) => {
+      for (axis, file) in outputs {
+        if let Some(projection) = opt.projection {
+          let frame_name = format!("{}-frame ({})", axis, projection);
+          let frame = match &opt.slab {
+            Some(slab) => {
+              info!("{} slab = {}:{}", frame_name, slab.start, slab.end);
+              volume.slab_projection(axis, projection, slab.clone())?
+            }
+            None => volume.projection(axis, projection)?,
+          };
+          create_frame(&opt, metadata, axis, &frame_name, file, frame.iter())?;
+        } else {
+          let index = match opt.world {
+            Some(world) => metadata.world_frame_index(axis, world)?,
+            None => metadata.frame_index(axis, opt.position(axis))?,
+          };
+          let frame_name = format!("{}-frame", axis);
+          info!("{} index = {}", frame_name, index);
+          create_frame(&opt, metadata, axis, &frame_name, file, volume.frame(axis, index)?)?;
+        }
+      }
+    }
+    Mode::Series(axis, template) => {
+      for (index, frame_iter) in volume.frames(axis) {
+        let file = PathBuf::from(utils::format_index(template, index)?);
+
+        if let Some(dir) = file.parent() {
+          fs::create_dir_all(dir)?;
+        }
+
+        let frame_name = format!("{}-frame {}", axis, index);
+        create_frame(&opt, metadata, axis, &frame_name, &file, frame_iter)?;
+      }
+    }
+    Mode::Oblique(point, normal, file) => {
+      create_oblique_frame(&opt, &volume, point, normal, file)?;
+    }
+    Mode::Nrrd(file) => {
+      write_nrrd(&opt, metadata, image.data(), file)?;
+    }
+    Mode::MetaImage(file) => {
+      write_metaimage(&opt, &volume, file)?;
+    }
+  }
 
-  if opt.raw {
-    // Produce the X-frame, made up of voxels on the Y- and Z-axis.
-    create_frame_raw("X-frame", &opt.xfile, volume.xframe(metadata.xdim() / 2))?;
+  Ok(())
+}
+
+/// Crop the loaded volume to a region of interest.
+fn crop(image: &Image, [x, y, z]: &[Range<usize>; 3]) -> Result<Image, Err> {
+  let cropped = image.volume()?.crop(x.clone(), y.clone(), z.clone())?;
+  let metadata = cropped.metadata().clone();
+
+  info!("Cropped volume to {}:{},{}:{},{}:{}", x.start, x.end, y.start, y.end, z.start, z.end);
+  info!("  Offset = {:?}", metadata.offset());
+
+  Ok(Image::new(metadata, cropped.to_bytes()))
+}
+
+/// Resample the loaded volume to a new voxel spacing.
+fn resample(image: &Image, spacing: [f64; 3], interpolation: Interpolation) -> Result<Image, Err> {
+  let resampled = image.volume()?.resample(spacing, interpolation)?;
+  let metadata = resampled.metadata().clone();
+
+  info!("Resampled volume to spacing {:?} ({} interpolation)", spacing, interpolation);
+  info!("  X-dim = {}", metadata.xdim());
+  info!("  Y-dim = {}", metadata.ydim());
+  info!("  Z-dim = {}", metadata.zdim());
+
+  Ok(Image::new(metadata, resampled.to_bytes()))
+}
+
+/// Whether a file is a NIfTI image, from its extension (`.nii` or
+/// `.nii.gz`).
+fn is_nifti(path: &Path) -> bool {
+  let name = path.to_string_lossy().to_ascii_lowercase();
+  name.ends_with(".nii") || name.ends_with(".nii.gz")
+}
+
+/// Whether a file is an NRRD file or header, from its extension
+/// (`.nrrd` or `.nhdr`).
+fn is_nrrd(path: &Path) -> bool {
+  path.extension().is_some_and(|extension| {
+    extension.eq_ignore_ascii_case("nrrd") || extension.eq_ignore_ascii_case("nhdr")
+  })
+}
 
-    // Produce the Y-frame, made up of voxels on the X- and Z-axis.
-    create_frame_raw("Y-frame", &opt.yfile, volume.yframe(metadata.ydim() / 2))?;
+/// Whether a file is a MetaImage file or header, from its extension
+/// (`.mha` or `.mhd`).
+fn is_metaimage(path: &Path) -> bool {
+  path.extension().is_some_and(|extension| {
+    extension.eq_ignore_ascii_case("mha") || extension.eq_ignore_ascii_case("mhd")
+  })
+}
 
-    // Produce the Z-frame, made up of voxels on the X- and Y-axis.
-    create_frame_raw("Z-frame", &opt.zfile, volume.zframe(metadata.zdim() / 2))?;
+/// Load the volume, either from a directory of DICOM files, from a
+/// NIfTI image, or from an NRRD or MetaImage file and the data files
+/// it names.
+fn load(opt: &Opt) -> Result<Image, Err> {
+  let image = if opt.metadata.is_dir() {
+    dicom::from_dir(&opt.metadata)?
+  } else if is_nifti(&opt.metadata) {
+    if let Some(data) = &opt.data {
+      warn!("Ignoring data file {} for NIfTI image", data.display());
+    }
+
+    nifti::from_bytes(&fs::read(&opt.metadata)?)?
+  } else if is_nrrd(&opt.metadata) {
+    let contents = fs::read(&opt.metadata)?;
+    let (metadata, local) = VolumeMd::from_nrrd(&contents)?;
+
+    let data_file = match &opt.data {
+      Some(data) => DataFile::Single(data.clone()),
+      // NRRD data is attached unless a data file is given.
+      None if metadata.data_file().is_none() => DataFile::Local,
+      // Data files are relative to the header.
+      None => metadata.resolve_data_file(opt.metadata.parent().unwrap_or_else(|| Path::new("")))?,
+    };
+
+    Image::read(metadata, &data_file, local)?
   } else {
-    // Produce the X-frame, made up of voxels on the Y- and Z-axis.
-    create_frame_image(
-      "X-frame",
-      &opt.xfile,
-      metadata.ydim(),
-      metadata.zdim(),
-      volume.xframe(metadata.xdim() / 2),
-    )?;
-
-    // Produce the Y-frame, made up of voxels on the X- and Z-axis.
-    create_frame_image(
-      "Y-frame",
-      &opt.yfile,
-      metadata.xdim(),
-      metadata.zdim(),
-      volume.yframe(metadata.ydim() / 2),
-    )?;
-
-    // Produce the Z-frame, made up of voxels on the X- and Y-axis.
-    create_frame_image(
-      "Z-frame",
-      &opt.zfile,
-      metadata.xdim(),
-      metadata.ydim(),
-      volume.zframe(metadata.zdim() / 2),
-    )?;
+    match &opt.data {
+      Some(data) => metaimage::from_files(&opt.metadata, data)?,
+      None => metaimage::from_file(&opt.metadata)?,
+    }
+  };
+
+  info!("Loaded {} bytes of data for {}", image.data().len(), opt.metadata.display());
+  Ok(image)
+}
+
+/// Write the whole volume to an NRRD file.
+///
+/// The data follows the header in an `.nrrd` file, or is written to a
+/// `.raw` (`.raw.gz` when compressed) file next to an `.nhdr` header.
+fn write_nrrd(opt: &Opt, metadata: &VolumeMd, data: &[u8], file: &Path) -> Result<(), Err> {
+  let detached = file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("nhdr"));
+  let data_path =
+    detached.then(|| file.with_extension(if opt.compress { "raw.gz" } else { "raw" }));
+  let data_file = data_path
+    .as_ref()
+    .and_then(|path| path.file_name())
+    .map(|name| name.to_string_lossy().into_owned());
+
+  let metadata = metadata
+    .clone()
+    .with_data_file(data_file)
+    .with_header_size(HeaderSize::Bytes(0))
+    .with_compressed(opt.compress);
+
+  info!("Encoding {} bytes of volume data", data.len());
+  let header = metadata.to_nrrd_header();
+  let data = metadata.encode_nrrd_data(data);
+
+  match data_path {
+    Some(data_path) => {
+      fs::write(file, header)?;
+      info!("Saved NRRD header to {}", file.display());
+      fs::write(&data_path, data)?;
+      info!("Saved volume data to {}", data_path.display());
+    }
+    None => {
+      let mut writer = BufWriter::new(File::create(file)?);
+      writer.write_all(header.as_bytes())?;
+      writer.write_all(&data)?;
+      writer.flush()?;
+      info!("Saved NRRD file to {}", file.display());
+    }
+  }
+
+  Ok(())
+}
+
+/// Write the whole volume to a MetaImage file.
+///
+/// The data follows the header in an `.mha` file, or is written to a
+/// `.raw` (`.zraw` when compressed) file next to an `.mhd` header.
+fn write_metaimage(opt: &Opt, volume: &Volume, file: &Path) -> Result<(), Err> {
+  let detached = file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("mhd"));
+  let data_path = detached.then(|| file.with_extension(if opt.compress { "zraw" } else { "raw" }));
+  let data_file = match &data_path {
+    Some(path) => path.file_name().map(|name| name.to_string_lossy().into_owned()),
+    None => Some(String::from("LOCAL")),
+  };
+
+  let metadata = volume
+    .metadata()
+    .clone()
+    .with_data_file(data_file)
+    .with_header_size(HeaderSize::Bytes(0))
+    .with_compressed(opt.compress);
+
+  // Compressed data is encoded first as its size is part of the
+  // header.
+  let encoded = opt.compress.then(|| {
+    let mut raw = Vec::new();
+    // Writing to a vector cannot fail.
+    volume.write_raw(&mut raw).unwrap();
+    metadata.encode_metaimage_data(&raw)
+  });
+  let metadata = metadata.with_compressed_data_size(encoded.as_ref().map(Vec::len));
+  let header = metadata.to_metaimage_header();
+
+  let mut writer = match &data_path {
+    Some(data_path) => {
+      fs::write(file, header)?;
+      info!("Saved MetaImage header to {}", file.display());
+      BufWriter::new(File::create(data_path)?)
+    }
+    None => {
+      let mut writer = BufWriter::new(File::create(file)?);
+      writer.write_all(header.as_bytes())?;
+      writer
+    }
+  };
+
+  match encoded {
+    Some(encoded) => {
+      writer.write_all(&encoded)?;
+      writer.flush()?;
+    }
+    None => volume.write_raw(&mut writer)?,
   }
 
+  info!("Saved volume data to {}", data_path.as_deref().unwrap_or(file).display());
+
   Ok(())
 }
 
+/// Produce a raw or image file of the selected frame, depending on
+/// the options.
+fn create_frame(
+  opt: &Opt,
+  metadata: &VolumeMd,
+  axis: Axis,
+  frame_name: &str,
+  filename: &Path,
+  frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+) -> Result<(), Err> {
+  let dims = metadata.frame_dims(axis);
+  let spacing = metadata.frame_spacing(axis);
+  let orientation =
+    FrameOrientation::from_metadata(metadata, axis, opt.convention).then(opt.orientation());
+  create_oriented_frame(opt, frame_name, filename, dims, spacing, orientation, frame_iter)
+}
+
+/// Orient the selected frame, then produce a raw or image file of it.
+fn create_oriented_frame(
+  opt: &Opt,
+  frame_name: &str,
+  filename: &Path,
+  (dim1, dim2): (usize, usize),
+  spacing: (f64, f64),
+  orientation: FrameOrientation,
+  frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+) -> Result<(), Err> {
+  if orientation.is_identity() {
+    return create_frame_file(opt, frame_name, filename, dim1, dim2, spacing, frame_iter);
+  }
+
+  let frame = orientation.apply(&Frame::from_iter(dim1, dim2, frame_iter)?);
+  info!("Oriented {} to {}x{}", frame_name, frame.width(), frame.height());
+
+  let spacing = orientation.apply_spacing(spacing);
+  create_frame_file(opt, frame_name, filename, frame.width(), frame.height(), spacing, frame.iter())
+}
+
+/// Produce a raw or image file of the selected frame.
+fn create_frame_file(
+  opt: &Opt,
+  frame_name: &str,
+  filename: &Path,
+  dim1: usize,
+  dim2: usize,
+  spacing: (f64, f64),
+  frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+) -> Result<(), Err> {
+  if opt.raw {
+    create_frame_raw(frame_name, filename, frame_iter)
+  } else {
+    let spacing = opt.physical_aspect.then_some(spacing);
+    create_frame_image(opt, frame_name, filename, dim1, dim2, spacing, frame_iter)
+  }
+}
+
+/// The largest number of voxels on each side of an oblique frame.
+const MAX_OBLIQUE_SIZE: usize = 16384;
+
+/// Produce a raw or image file of an oblique frame, large enough to
+/// cover the whole volume.
+fn create_oblique_frame(
+  opt: &Opt,
+  volume: &Volume,
+  point: Option<[f64; 3]>,
+  normal: [f64; 3],
+  filename: &Path,
+) -> Result<(), Err> {
+  let metadata = volume.metadata();
+  let spacing = metadata.spacing();
+  let dims = [metadata.xdim(), metadata.ydim(), metadata.zdim()];
+
+  // Extent of the volume in millimetres along each axis.
+  let mut extent = [0.0; 3];
+  for axis in 0..3 {
+    extent[axis] = dims[axis].saturating_sub(1) as f64 * spacing[axis];
+  }
+
+  let point = point.unwrap_or([extent[0] / 2.0, extent[1] / 2.0, extent[2] / 2.0]);
+  let (u_axis, v_axis) = utils::plane_axes(normal)?;
+
+  // Sample at the finest spacing, over the diagonal of the volume so
+  // that the frame covers the whole volume.
+  let step = spacing.iter().copied().fold(f64::INFINITY, f64::min);
+  if !(step.is_finite() && step > 0.0) {
+    let reason = "The spacing of the volume is not positive";
+    return Err(MedvizErr::new_invalid_oblique_frame(reason).into());
+  }
+
+  // A frame this large would take too long to sample, which happens
+  // when the spacing on one axis is tiny compared to the others.
+  let diagonal = extent.iter().map(|extent| extent * extent).sum::<f64>().sqrt();
+  let size = (diagonal / step).ceil() + 1.0;
+  if size > MAX_OBLIQUE_SIZE as f64 {
+    return Err(MedvizErr::new_invalid_oblique_frame("The frame is too large").into());
+  }
+  let size = size as usize;
+
+  let half = (size - 1) as f64 / 2.0 * step;
+  let mut origin = [0.0; 3];
+  for axis in 0..3 {
+    origin[axis] = point[axis] - (u_axis[axis] + v_axis[axis]) * half;
+  }
+
+  let frame_name = "oblique-frame";
+  info!("{} point = {:?}, normal = {:?}", frame_name, point, normal);
+  info!("{} size = {}x{}, step = {}mm", frame_name, size, size, step);
+
+  let frame_iter =
+    volume.oblique_frame(origin, u_axis, v_axis, size, size, step, opt.interpolation)?;
+
+  let dims = (size, size);
+  let spacing = (step, step);
+  create_oriented_frame(opt, frame_name, filename, dims, spacing, opt.orientation(), frame_iter)
+}
+
 /// Produce a file with raw contents of the selected frame.
 fn create_frame_raw(
-  frame_name: &'static str,
+  frame_name: &str,
   filename: &Path,
   frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
 ) -> Result<(), Err> {
@@ -158,26 +899,68 @@ fn create_frame_raw(
 
   info!("Writing {} (raw) to {}", frame_name, filename.display());
   for (voxel, _, _) in frame_iter {
-    let voxel = voxel?;
-    writer.write_all(&voxel.value().to_le_bytes())?;
+    voxel?.write_le_bytes(&mut writer)?;
   }
 
   Ok(())
 }
 
-/// Produce a bmp image file of the selected frame.
+/// Produce an image file of the selected frame.
 fn create_frame_image(
-  frame_name: &'static str,
+  opt: &Opt,
+  frame_name: &str,
   filename: &Path,
   dim1: usize,
   dim2: usize,
+  spacing: Option<(f64, f64)>,
   frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
 ) -> Result<(), Err> {
-  info!("Creating {} (bmp)", frame_name);
-  let image = utils::frame_bmp(dim1, dim2, frame_iter)?;
+  match spacing {
+    Some((hspacing, vspacing)) => {
+      let frame = Frame::from_iter(dim1, dim2, frame_iter)?.with_aspect(hspacing, vspacing);
+      info!("Resampled {} to {}x{}", frame_name, frame.width(), frame.height());
+      save_frame_image(opt, frame_name, filename, frame.width(), frame.height(), frame.iter())
+    }
+    None => save_frame_image(opt, frame_name, filename, dim1, dim2, frame_iter),
+  }
+}
 
-  info!("Saving {} (bmp) to {}", frame_name, filename.display());
-  image.save(filename)?;
+/// Encode the selected frame as an image and save it to a file.
+fn save_frame_image(
+  opt: &Opt,
+  frame_name: &str,
+  filename: &Path,
+  dim1: usize,
+  dim2: usize,
+  frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+) -> Result<(), Err> {
+  info!("Creating {} ({})", frame_name, opt.format);
+
+  match opt.format {
+    ImageFormat::Bmp => {
+      let image =
+        utils::frame_bmp(dim1, dim2, opt.window.as_ref(), opt.colormap.as_ref(), frame_iter)?;
+      info!("Saving {} (bmp) to {}", frame_name, filename.display());
+      image.save(filename)?;
+    }
+    ImageFormat::Png => {
+      let depth = match opt.bit_depth {
+        BitDepth::Eight => PngDepth::Eight,
+        BitDepth::Sixteen => PngDepth::Sixteen,
+      };
+
+      let image = utils::frame_png(
+        dim1,
+        dim2,
+        depth,
+        opt.window.as_ref(),
+        opt.colormap.as_ref(),
+        frame_iter,
+      )?;
+      info!("Saving {} (png) to {}", frame_name, filename.display());
+      fs::write(filename, image)?;
+    }
+  }
 
   Ok(())
 }
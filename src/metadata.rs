@@ -1,13 +1,315 @@
 //! Handles metadata related to 3D volumetric data. The primary
 //! structure is the [volume metadata struct](VolumeMd).
 
-use crate::MedvizErr;
+use crate::compression;
+use crate::vector::{self, Vector};
+use crate::{Axis, FramePosition, MedvizErr};
 use atoi::FromRadix10Checked;
-use derive_new::new;
 use log::{debug, warn};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Type of the voxels in a volume, as named by the `ElementType`
+/// metadata key.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ElementType {
+  /// `MET_CHAR`: Signed 8-bit integer.
+  Char,
+
+  /// `MET_UCHAR`: Unsigned 8-bit integer.
+  UChar,
+
+  /// `MET_SHORT`: Signed 16-bit integer.
+  Short,
+
+  /// `MET_USHORT`: Unsigned 16-bit integer.
+  UShort,
+
+  /// `MET_INT`: Signed 32-bit integer.
+  Int,
+
+  /// `MET_UINT`: Unsigned 32-bit integer.
+  UInt,
+
+  /// `MET_LONG`: Signed 32-bit integer.
+  Long,
+
+  /// `MET_ULONG`: Unsigned 32-bit integer.
+  ULong,
+
+  /// `MET_LONG_LONG`: Signed 64-bit integer.
+  LongLong,
+
+  /// `MET_ULONG_LONG`: Unsigned 64-bit integer.
+  ULongLong,
+
+  /// `MET_FLOAT`: 32-bit floating point.
+  Float,
+
+  /// `MET_DOUBLE`: 64-bit floating point.
+  Double,
+}
+
+impl ElementType {
+  /// All element types.
+  const ALL: [Self; 12] = [
+    Self::Char,
+    Self::UChar,
+    Self::Short,
+    Self::UShort,
+    Self::Int,
+    Self::UInt,
+    Self::Long,
+    Self::ULong,
+    Self::LongLong,
+    Self::ULongLong,
+    Self::Float,
+    Self::Double,
+  ];
+
+  /// Find the element type with the given name (e.g. `MET_USHORT`).
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|element_type| element_type.name() == name)
+  }
+
+  /// The size of a voxel of this type in bytes.
+  pub fn size(&self) -> usize {
+    match self {
+      Self::Char | Self::UChar => 1,
+      Self::Short | Self::UShort => 2,
+      Self::Int | Self::UInt | Self::Long | Self::ULong | Self::Float => 4,
+      Self::LongLong | Self::ULongLong | Self::Double => 8,
+    }
+  }
+
+  /// The name of the element type as used in metadata files.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Char => "MET_CHAR",
+      Self::UChar => "MET_UCHAR",
+      Self::Short => "MET_SHORT",
+      Self::UShort => "MET_USHORT",
+      Self::Int => "MET_INT",
+      Self::UInt => "MET_UINT",
+      Self::Long => "MET_LONG",
+      Self::ULong => "MET_ULONG",
+      Self::LongLong => "MET_LONG_LONG",
+      Self::ULongLong => "MET_ULONG_LONG",
+      Self::Float => "MET_FLOAT",
+      Self::Double => "MET_DOUBLE",
+    }
+  }
+}
+
+impl fmt::Display for ElementType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+/// Size of the header preceding the volume data in the data file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HeaderSize {
+  /// A fixed number of bytes to skip.
+  Bytes(usize),
+
+  /// The header size is computed such that the volume data is at the
+  /// end of the data file (a `HeaderSize` of `-1`).
+  Auto,
+}
+
+impl fmt::Display for HeaderSize {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Bytes(bytes) => write!(f, "{}", bytes),
+      Self::Auto => f.write_str("-1"),
+    }
+  }
+}
+
+/// Where the volume data is stored, as resolved from the
+/// `ElementDataFile` metadata key.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DataFile {
+  /// The data follows the metadata in the same file (`LOCAL`).
+  Local,
+
+  /// The data is in a single file.
+  Single(PathBuf),
+
+  /// The data is split over several files, in order.
+  List(Vec<PathBuf>),
+}
+
+/// An anatomical direction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+  /// Patient's right.
+  Right,
+
+  /// Patient's left.
+  Left,
+
+  /// Patient's front.
+  Anterior,
+
+  /// Patient's back.
+  Posterior,
+
+  /// Patient's head.
+  Superior,
+
+  /// Patient's feet.
+  Inferior,
+}
+
+impl Direction {
+  /// Find the direction for a single-letter code (e.g. `R`).
+  pub fn from_letter(letter: char) -> Option<Self> {
+    match letter.to_ascii_uppercase() {
+      'R' => Some(Self::Right),
+      'L' => Some(Self::Left),
+      'A' => Some(Self::Anterior),
+      'P' => Some(Self::Posterior),
+      'S' => Some(Self::Superior),
+      'I' => Some(Self::Inferior),
+      _ => None,
+    }
+  }
+
+  /// The single-letter code of the direction.
+  pub fn letter(&self) -> char {
+    match self {
+      Self::Right => 'R',
+      Self::Left => 'L',
+      Self::Anterior => 'A',
+      Self::Posterior => 'P',
+      Self::Superior => 'S',
+      Self::Inferior => 'I',
+    }
+  }
+
+  /// The direction pointing the other way.
+  pub fn opposite(&self) -> Self {
+    match self {
+      Self::Right => Self::Left,
+      Self::Left => Self::Right,
+      Self::Anterior => Self::Posterior,
+      Self::Posterior => Self::Anterior,
+      Self::Superior => Self::Inferior,
+      Self::Inferior => Self::Superior,
+    }
+  }
+
+  /// Whether both directions lie on the same anatomical axis.
+  pub fn is_parallel(&self, other: Self) -> bool {
+    *self == other || *self == other.opposite()
+  }
+}
+
+/// Anatomical orientation of the volume, as found in the
+/// `AnatomicalOrientation` metadata key.
+///
+/// Follows the MetaImage convention where each letter names the side
+/// of the patient an axis starts from. For example, `RAI` means the
+/// X-axis goes from right to left, the Y-axis from anterior to
+/// posterior and the Z-axis from inferior to superior.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AnatomicalOrientation([Direction; 3]);
+
+impl AnatomicalOrientation {
+  /// The X-axis goes from right to left, the Y-axis from anterior to
+  /// posterior and the Z-axis from inferior to superior.
+  pub const RAI: Self = Self([Direction::Right, Direction::Anterior, Direction::Inferior]);
+
+  /// Create an orientation from the directions the X-, Y- and Z-axis
+  /// start from.
+  ///
+  /// Returns `None` if any two of the directions are parallel.
+  pub fn new(x: Direction, y: Direction, z: Direction) -> Option<Self> {
+    if x.is_parallel(y) || x.is_parallel(z) || y.is_parallel(z) {
+      return None;
+    }
+
+    Some(Self([x, y, z]))
+  }
+
+  /// Parse an orientation from a three-letter code (e.g. `RAI`).
+  pub fn from_code(code: &str) -> Option<Self> {
+    let mut letters = code.chars().map(Direction::from_letter);
+    let x = letters.next()??;
+    let y = letters.next()??;
+    let z = letters.next()??;
+
+    if letters.next().is_some() {
+      return None;
+    }
+
+    Self::new(x, y, z)
+  }
+
+  /// Find the orientation closest to the directions of a transform
+  /// matrix, as found in the `TransformMatrix` metadata key.
+  ///
+  /// World coordinates increase towards the patient's left, back and
+  /// head, so an identity transform is `RAI`.
+  ///
+  /// # Returns
+  ///
+  /// The orientation, or `None` in case two axes are closest to the
+  /// same anatomical axis (e.g. a degenerate transform).
+  pub fn from_transform(transform: [f64; 9]) -> Option<Self> {
+    let direction = |axis: usize| {
+      let column = &transform[axis * 3..axis * 3 + 3];
+      let (component, value) =
+        column.iter().copied().enumerate().max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))?;
+
+      let (increasing, decreasing) = match component {
+        0 => (Direction::Right, Direction::Left),
+        1 => (Direction::Anterior, Direction::Posterior),
+        _ => (Direction::Inferior, Direction::Superior),
+      };
+
+      match value {
+        value if value > 0.0 => Some(increasing),
+        value if value < 0.0 => Some(decreasing),
+        _ => None,
+      }
+    };
+
+    Self::new(direction(0)?, direction(1)?, direction(2)?)
+  }
+
+  /// The direction the X-axis starts from.
+  pub fn x(&self) -> Direction {
+    self.0[0]
+  }
+
+  /// The direction the Y-axis starts from.
+  pub fn y(&self) -> Direction {
+    self.0[1]
+  }
+
+  /// The direction the Z-axis starts from.
+  pub fn z(&self) -> Direction {
+    self.0[2]
+  }
+}
+
+impl fmt::Display for AnatomicalOrientation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}{}", self.x().letter(), self.y().letter(), self.z().letter())
+  }
+}
 
 /// Volume metadata.
-#[derive(new, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct VolumeMd {
   /// Number of voxels on the X-axis.
   xdim: usize,
@@ -17,18 +319,81 @@ pub struct VolumeMd {
 
   /// Number of voxels on the Z-axis.
   zdim: usize,
+
+  /// Physical distance between voxel centers on each axis.
+  spacing: [f64; 3],
+
+  /// Physical position of the first voxel.
+  offset: [f64; 3],
+
+  /// Direction cosines of the X-, Y- and Z-axis, three values each.
+  transform: [f64; 9],
+
+  /// Type of the voxels.
+  element_type: ElementType,
+
+  /// Whether voxels are stored with the most significant byte first.
+  byte_order_msb: bool,
+
+  /// The file containing the volume data, as written in the metadata.
+  data_file: Option<String>,
+
+  /// The data files following a `LIST` data file.
+  data_file_list: Vec<String>,
+
+  /// Size of the header in the data file.
+  header_size: HeaderSize,
+
+  /// Whether the volume data is compressed.
+  compressed: bool,
+
+  /// Whether the [header](VolumeMd::header_size) of a compressed
+  /// data file is compressed along with the volume data, as in NRRD
+  /// files, rather than preceding it, as in MetaImage files.
+  header_compressed: bool,
+
+  /// Size of the compressed volume data in bytes, if known.
+  compressed_data_size: Option<usize>,
+
+  /// Anatomical orientation of the volume axes.
+  orientation: Option<AnatomicalOrientation>,
 }
 
 impl VolumeMd {
+  /// Create [volume metadata](VolumeMd) with the given dimensions.
+  ///
+  /// All other fields are set to the same defaults used when their
+  /// keys are missing from the metadata.
+  pub fn new(xdim: usize, ydim: usize, zdim: usize) -> Self {
+    Self {
+      xdim,
+      ydim,
+      zdim,
+      spacing: [1.0; 3],
+      offset: [0.0; 3],
+      transform: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+      element_type: ElementType::UShort,
+      byte_order_msb: false,
+      data_file: None,
+      data_file_list: Vec::new(),
+      header_size: HeaderSize::Bytes(0),
+      compressed: false,
+      header_compressed: false,
+      compressed_data_size: None,
+      orientation: None,
+    }
+  }
+
   /// Load [volume metadata](VolumeMd) from a buffered reader.
   ///
-  /// Finds the first line in the metadata with the `DimSize` key and
-  /// loads the values for it.
+  /// Reads the MetaImage keys describing the volume, stopping after
+  /// the `ElementDataFile` key which must be the last one. Unknown
+  /// keys are skipped.
   ///
   /// # Notes
   ///
-  /// This is not a real parser. Instead, it works by splitting lines
-  /// and tries to graciously handle invalid data.
+  /// Only the `DimSize` key is mandatory. When `ElementType` is
+  /// missing the voxels are assumed to be `MET_USHORT`.
   ///
   /// # Arguments
   ///
@@ -39,14 +404,26 @@ impl VolumeMd {
   /// A populated [volume metadata structure](VolumeMd) or [an
   /// error](Err).
   pub fn from_buffer(buffer: &str) -> Result<Self, MedvizErr> {
-    // The resulting VolumeMd. None if we haven't found a valid
-    // `DimSize` entry and Some(...) if we have.
-    let mut res = None;
+    // Each field is None until its key is found, which is also used
+    // to detect duplicate keys.
+    let mut dims = None;
+    let mut spacing = None;
+    let mut offset = None;
+    let mut transform = None;
+    let mut element_type = None;
+    let mut byte_order_msb = None;
+    let mut data_file = None;
+    let mut header_size = None;
+    let mut compressed = None;
+    let mut compressed_data_size = None;
+    let mut orientation = None;
+
+    let mut lines = buffer.split('\n').enumerate();
 
-    for (line_index, line) in buffer.split(|c| c == '\n').enumerate() {
+    for (line_index, line) in &mut lines {
       let line_number = line_index + 1;
 
-      let mut entry = line.split('=');
+      let mut entry = line.splitn(2, '=');
 
       let key = match entry.next() {
         Some(key) => key.trim(),
@@ -61,135 +438,1448 @@ impl VolumeMd {
         continue;
       }
 
-      if key != "DimSize" {
-        debug!("Line {}: Skipping key {}", line_number, key);
+      let value = entry.next().map(str::trim);
+
+      /// Get the value of the current entry.
+      ///
+      /// # Arguments
+      ///
+      /// * `$field` - The field the value is meant for.
+      ///
+      /// * `$key` - The key name to use in errors.
+      ///
+      /// # Uses
+      ///
+      /// * `value` - The value of the current entry.
+      ///
+      /// * `line_number` - The current input line number for errors.
+      ///
+      /// # Returns
+      ///
+      /// The value or exits the function with [an error](Err) in case
+      /// `$field` was already set or the value is missing.
+      macro_rules! entry_value {
+        ($field:ident, $key:literal) => {{
+          if $field.is_some() {
+            return Err(MedvizErr::new_md_duplicate_key(line_number));
+          }
+
+          match value {
+            Some(value) => value,
+            None => return Err(MedvizErr::new_md_missing_values(line_number, $key)),
+          }
+        }};
+      }
+
+      match key {
+        "NDims" => {
+          let value = match value {
+            Some(value) => value,
+            None => return Err(MedvizErr::new_md_missing_values(line_number, "NDims")),
+          };
+
+          let [ndims] = parse_values::<usize, 1>(line_number, "NDims", value)?;
+
+          if ndims != 3 {
+            return Err(MedvizErr::new_md_unsupported_n_dims(line_number, ndims));
+          }
+        }
+        "DimSize" => {
+          if dims.is_some() {
+            // We've already found a valid `DimSize` entry.
+            return Err(MedvizErr::new_md_duplicate_key(line_number));
+          }
+
+          let value = match value {
+            Some(value) => value,
+            None => return Err(MedvizErr::new_md_missing_dim_size_values(line_number)),
+          };
+
+          dims = Some(parse_dim_size(line_number, value)?);
+        }
+        "ElementSpacing" => {
+          let value = entry_value!(spacing, "ElementSpacing");
+          spacing = Some(parse_values(line_number, "ElementSpacing", value)?);
+        }
+        "Offset" | "Origin" | "Position" => {
+          let value = entry_value!(offset, "Offset");
+          offset = Some(parse_values(line_number, "Offset", value)?);
+        }
+        "TransformMatrix" | "Rotation" | "Orientation" => {
+          let value = entry_value!(transform, "TransformMatrix");
+          transform = Some(parse_values(line_number, "TransformMatrix", value)?);
+        }
+        "ElementType" => {
+          let value = entry_value!(element_type, "ElementType");
+          match ElementType::from_name(value) {
+            Some(value) => element_type = Some(value),
+            None => {
+              return Err(MedvizErr::new_md_invalid_value(line_number, "ElementType", value.into()))
+            }
+          }
+        }
+        "ElementByteOrderMSB" | "BinaryDataByteOrderMSB" => {
+          let value = entry_value!(byte_order_msb, "ElementByteOrderMSB");
+          byte_order_msb = Some(parse_bool(line_number, "ElementByteOrderMSB", value)?);
+        }
+        "HeaderSize" => {
+          let value = entry_value!(header_size, "HeaderSize");
+          if value == "-1" {
+            header_size = Some(HeaderSize::Auto);
+          } else {
+            let [bytes] = parse_values(line_number, "HeaderSize", value)?;
+            header_size = Some(HeaderSize::Bytes(bytes));
+          }
+        }
+        "CompressedData" => {
+          let value = entry_value!(compressed, "CompressedData");
+          compressed = Some(parse_bool(line_number, "CompressedData", value)?);
+        }
+        "CompressedDataSize" => {
+          let value = entry_value!(compressed_data_size, "CompressedDataSize");
+          let [size] = parse_values(line_number, "CompressedDataSize", value)?;
+          compressed_data_size = Some(size);
+        }
+        "AnatomicalOrientation" => {
+          let value = entry_value!(orientation, "AnatomicalOrientation");
+          if value.contains('?') {
+            // Unknown orientation.
+            orientation = Some(None);
+          } else {
+            match AnatomicalOrientation::from_code(value) {
+              Some(value) => orientation = Some(Some(value)),
+              None => {
+                return Err(MedvizErr::new_md_invalid_value(
+                  line_number,
+                  "AnatomicalOrientation",
+                  value.into(),
+                ))
+              }
+            }
+          }
+        }
+        "ElementDataFile" => {
+          let value = entry_value!(data_file, "ElementDataFile");
+          if value.is_empty() {
+            return Err(MedvizErr::new_md_missing_values(line_number, "ElementDataFile"));
+          }
+
+          data_file = Some(String::from(value));
+
+          // `ElementDataFile` is always the last key.
+          break;
+        }
+        _ => debug!("Line {}: Skipping key {}", line_number, key),
+      }
+    }
+
+    // A `LIST` of data files follows the `ElementDataFile` key, one
+    // file per line.
+    let data_file_list = match &data_file {
+      Some(data_file) if is_list(data_file) => lines
+        .map(|(_, line)| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect(),
+      _ => Vec::new(),
+    };
+
+    let (xdim, ydim, zdim) = match dims {
+      Some(dims) => dims,
+      None => return Err(MedvizErr::new_md_dim_size_not_found()),
+    };
+
+    let mut res = Self::new(xdim, ydim, zdim);
+    res.data_file_list = data_file_list;
+    res.spacing = spacing.unwrap_or(res.spacing);
+    res.offset = offset.unwrap_or(res.offset);
+    res.transform = transform.unwrap_or(res.transform);
+    res.element_type = element_type.unwrap_or(res.element_type);
+    res.byte_order_msb = byte_order_msb.unwrap_or(res.byte_order_msb);
+    res.data_file = data_file;
+    res.header_size = header_size.unwrap_or(res.header_size);
+    res.compressed = compressed.unwrap_or(res.compressed);
+    res.compressed_data_size = compressed_data_size;
+    res.orientation = orientation.unwrap_or(res.orientation);
+    Ok(res)
+  }
+
+  /// Load [volume metadata](VolumeMd) from the contents of a
+  /// MetaImage file, which may be followed by the volume data.
+  ///
+  /// # Arguments
+  ///
+  /// * `bytes` - The contents of an `.mhd` file, or of an `.mha` file
+  ///   whose data follows the `ElementDataFile = LOCAL` key.
+  ///
+  /// # Returns
+  ///
+  /// A populated [volume metadata structure](VolumeMd) and the bytes
+  /// following the `ElementDataFile` key (or the list of files
+  /// following it), which are the data of a `LOCAL` data file, or
+  /// [an error](Err).
+  pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), MedvizErr> {
+    let mut header_end = bytes.len();
+    let mut line_start = 0;
+
+    for line in bytes.split(|&b| b == b'\n') {
+      let line_end = (line_start + line.len() + 1).min(bytes.len());
+      let key = line.split(|&b| b == b'=').next().unwrap_or_default();
+
+      if key.trim_ascii() == b"ElementDataFile" {
+        header_end = line_end;
+        break;
+      }
+
+      line_start = line_end;
+    }
+
+    let mut res = Self::from_buffer(&String::from_utf8_lossy(&bytes[..header_end]))?;
+
+    match res.data_file.as_deref() {
+      Some(data_file) if is_list(data_file) => {
+        // The data files are listed after the header.
+        res.data_file_list = String::from_utf8_lossy(&bytes[header_end..])
+          .lines()
+          .map(str::trim)
+          .filter(|line| !line.is_empty())
+          .map(String::from)
+          .collect();
+        Ok((res, &[]))
+      }
+      _ => Ok((res, &bytes[header_end..])),
+    }
+  }
+
+  /// Load [volume metadata](VolumeMd) from an NRRD header.
+  ///
+  /// Reads the NRRD fields describing the volume, up to the empty
+  /// line ending the header. Comments, key/value pairs and unknown
+  /// fields are skipped.
+  ///
+  /// # Notes
+  ///
+  /// Only 3-dimensional volumes with `raw` or `gzip` encoding are
+  /// supported. The `data file` field is kept as the [data
+  /// file](VolumeMd::data_file), `byte skip` as the [header
+  /// size](VolumeMd::header_size) and a `gzip` encoding marks the
+  /// data as [compressed](VolumeMd::compressed). Directions and the
+  /// origin are converted to the LPS+ world of MetaImage when the
+  /// `space` is `right-anterior-superior` or
+  /// `left-anterior-superior`.
+  ///
+  /// # Arguments
+  ///
+  /// * `bytes` - The contents of an `.nrrd` file or of a detached
+  ///   `.nhdr` header.
+  ///
+  /// # Returns
+  ///
+  /// A populated [volume metadata structure](VolumeMd) and the bytes
+  /// following the header, which are the data of an `.nrrd` file, or
+  /// [an error](Err).
+  pub fn from_nrrd(bytes: &[u8]) -> Result<(Self, &[u8]), MedvizErr> {
+    // The header ends at the first empty line.
+    let mut header_end = bytes.len();
+    let mut data_start = bytes.len();
+    let mut line_start = 0;
+    while line_start < bytes.len() {
+      let line_end = bytes[line_start..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |p| line_start + p);
+      let line = &bytes[line_start..line_end];
+
+      if line.is_empty() || line == b"\r" {
+        header_end = line_start;
+        data_start = (line_end + 1).min(bytes.len());
+        break;
+      }
+
+      line_start = line_end + 1;
+    }
+
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+    let mut lines = header.lines().enumerate();
+
+    match lines.next() {
+      Some((_, magic)) if magic.starts_with("NRRD000") => {}
+      _ => return Err(MedvizErr::new_nrrd_invalid_magic()),
+    }
+
+    let mut dims = None;
+    let mut element_type = None;
+    let mut space = None;
+    let mut directions = None;
+    let mut spacings = None;
+    let mut origin = None;
+    let mut byte_order_msb = None;
+    let mut compressed = None;
+    let mut data_file = None;
+    let mut header_size = None;
+
+    for (line_index, line) in lines {
+      let line_number = line_index + 1;
+
+      if line.starts_with('#') {
+        continue;
+      }
+
+      if line.contains(":=") {
+        debug!("Line {}: Skipping key/value pair", line_number);
         continue;
       }
 
-      if res.is_some() {
-        // We've already found a valid `DimSize` entry.
-        return Err(MedvizErr::new_md_duplicate_key(line_number));
+      let (field, value) = match line.split_once(':') {
+        Some((field, value)) => (field.trim().to_ascii_lowercase(), value.trim()),
+        None => {
+          warn!("Line {}: Skipping entry without a `:` sign", line_number);
+          continue;
+        }
+      };
+
+      /// Check that the field of the current entry is not set yet.
+      ///
+      /// # Arguments
+      ///
+      /// * `$field` - The field the value is meant for.
+      ///
+      /// # Uses
+      ///
+      /// * `line_number` - The current input line number for errors.
+      macro_rules! check_duplicate {
+        ($field:ident) => {
+          if $field.is_some() {
+            return Err(MedvizErr::new_md_duplicate_key(line_number));
+          }
+        };
+      }
+
+      match field.as_str() {
+        "dimension" => {
+          let [ndims] = parse_values::<usize, 1>(line_number, "dimension", value)?;
+
+          if ndims != 3 {
+            return Err(MedvizErr::new_md_unsupported_n_dims(line_number, ndims));
+          }
+        }
+        "sizes" => {
+          check_duplicate!(dims);
+          dims = Some(parse_dim_size(line_number, value)?);
+        }
+        "type" => {
+          check_duplicate!(element_type);
+          match nrrd_element_type(value) {
+            Some(value) => element_type = Some(value),
+            None => return Err(MedvizErr::new_md_invalid_value(line_number, "type", value.into())),
+          }
+        }
+        "space" => {
+          check_duplicate!(space);
+          // The signs turning coordinates of the space into LPS+
+          // coordinates.
+          space = Some(match value.to_ascii_lowercase().as_str() {
+            "left-posterior-superior" | "lps" => [1.0, 1.0, 1.0],
+            "right-anterior-superior" | "ras" => [-1.0, -1.0, 1.0],
+            "left-anterior-superior" | "las" => [1.0, -1.0, 1.0],
+            _ => return Err(MedvizErr::new_md_invalid_value(line_number, "space", value.into())),
+          });
+        }
+        "space dimension" => {
+          let [ndims] = parse_values::<usize, 1>(line_number, "space dimension", value)?;
+
+          if ndims != 3 {
+            return Err(MedvizErr::new_md_unsupported_n_dims(line_number, ndims));
+          }
+        }
+        "space directions" => {
+          check_duplicate!(directions);
+          let vectors = parse_nrrd_vectors(line_number, "space directions", value)?;
+          match <[Vector; 3]>::try_from(vectors) {
+            Ok(vectors) => directions = Some(vectors),
+            Err(_) => {
+              return Err(MedvizErr::new_md_invalid_value(
+                line_number,
+                "space directions",
+                value.into(),
+              ))
+            }
+          }
+        }
+        "spacings" => {
+          check_duplicate!(spacings);
+          spacings = Some(parse_values::<f64, 3>(line_number, "spacings", value)?);
+        }
+        "space origin" => {
+          check_duplicate!(origin);
+          match parse_nrrd_vectors(line_number, "space origin", value)?.as_slice() {
+            [vector] => origin = Some(*vector),
+            _ => {
+              return Err(MedvizErr::new_md_invalid_value(
+                line_number,
+                "space origin",
+                value.into(),
+              ))
+            }
+          }
+        }
+        "endian" => {
+          check_duplicate!(byte_order_msb);
+          byte_order_msb = Some(match value {
+            "little" => false,
+            "big" => true,
+            _ => return Err(MedvizErr::new_md_invalid_value(line_number, "endian", value.into())),
+          });
+        }
+        "encoding" => {
+          check_duplicate!(compressed);
+          compressed = Some(match value {
+            "raw" => false,
+            "gzip" | "gz" => true,
+            _ => {
+              return Err(MedvizErr::new_md_invalid_value(line_number, "encoding", value.into()))
+            }
+          });
+        }
+        "data file" | "datafile" => {
+          check_duplicate!(data_file);
+          if value.is_empty() {
+            return Err(MedvizErr::new_md_missing_values(line_number, "data file"));
+          }
+          data_file = Some(String::from(value));
+        }
+        "byte skip" | "byteskip" => {
+          check_duplicate!(header_size);
+          if value == "-1" {
+            header_size = Some(HeaderSize::Auto);
+          } else {
+            let [bytes] = parse_values(line_number, "byte skip", value)?;
+            header_size = Some(HeaderSize::Bytes(bytes));
+          }
+        }
+        "line skip" | "lineskip" => {
+          let [lines] = parse_values::<usize, 1>(line_number, "line skip", value)?;
+
+          if lines != 0 {
+            return Err(MedvizErr::new_md_invalid_value(line_number, "line skip", value.into()));
+          }
+        }
+        _ => debug!("Line {}: Skipping field {}", line_number, field),
+      }
+    }
+
+    let (xdim, ydim, zdim) = match dims {
+      Some(dims) => dims,
+      None => return Err(MedvizErr::new_md_dim_size_not_found()),
+    };
+
+    let mut res = Self::new(xdim, ydim, zdim);
+    let signs = space.unwrap_or([1.0; 3]);
+    let to_lps = |vector: Vector| -> Vector {
+      [vector[0] * signs[0], vector[1] * signs[1], vector[2] * signs[2]]
+    };
+
+    if let Some(directions) = directions {
+      for (axis, direction) in directions.iter().enumerate() {
+        let direction = to_lps(*direction);
+        if let Some(unit) = vector::normalize(direction) {
+          res.spacing[axis] = vector::norm(direction);
+          res.transform[axis * 3..axis * 3 + 3].copy_from_slice(&unit);
+        }
+      }
+      res.orientation = AnatomicalOrientation::from_transform(res.transform);
+    } else if let Some(spacings) = spacings {
+      for (spacing, value) in res.spacing.iter_mut().zip(spacings) {
+        if value.is_finite() && value != 0.0 {
+          *spacing = value.abs();
+        }
+      }
+    }
+
+    res.offset = origin.map(to_lps).unwrap_or(res.offset);
+    res.element_type = element_type.unwrap_or(res.element_type);
+    res.byte_order_msb = byte_order_msb.unwrap_or(res.byte_order_msb);
+    res.data_file = data_file;
+    res.header_size = header_size.unwrap_or(res.header_size);
+    res.compressed = compressed.unwrap_or(res.compressed);
+    // Only recorded when there is a header to skip, so that the
+    // metadata compares equal to the MetaImage equivalent otherwise.
+    res.header_compressed = res.compressed && res.header_size != HeaderSize::Bytes(0);
+    Ok((res, &bytes[data_start..]))
+  }
+
+  /// Write the metadata as an NRRD header, including the empty line
+  /// ending it.
+  ///
+  /// The volume data follows the header in an `.nrrd` file, or is
+  /// stored in the [data file](VolumeMd::data_file) for a detached
+  /// `.nhdr` header. Compressed data is `gzip` encoded, see
+  /// [`encode_nrrd_data`](VolumeMd::encode_nrrd_data).
+  pub fn to_nrrd_header(&self) -> String {
+    /// Format a vector as an NRRD vector, e.g. `(1,0,0)`.
+    fn vector(vector: Vector) -> String {
+      format!("({},{},{})", vector[0], vector[1], vector[2])
+    }
+
+    let directions: Vec<String> = (0..3)
+      .map(|axis| {
+        let mut direction = [0.0; 3];
+        direction.copy_from_slice(&self.transform[axis * 3..axis * 3 + 3]);
+        vector(vector::scale(direction, self.spacing[axis]))
+      })
+      .collect();
+
+    let mut res = String::from("NRRD0004\n");
+    res += &format!("type: {}\n", nrrd_type_name(self.element_type));
+    res += "dimension: 3\n";
+    res += "space: left-posterior-superior\n";
+    res += &format!("sizes: {} {} {}\n", self.xdim, self.ydim, self.zdim);
+    res += &format!("space directions: {}\n", directions.join(" "));
+    res += "kinds: domain domain domain\n";
+    res += &format!("endian: {}\n", if self.byte_order_msb { "big" } else { "little" });
+    res += &format!("encoding: {}\n", if self.compressed { "gzip" } else { "raw" });
+    res += &format!("space origin: {}\n", vector(self.offset));
+
+    if let Some(data_file) = &self.data_file {
+      res += &format!("data file: {}\n", data_file);
+    }
+
+    if self.header_size != HeaderSize::Bytes(0) {
+      res += &format!("byte skip: {}\n", self.header_size);
+    }
+
+    res += "\n";
+    res
+  }
+
+  /// Encode volume data for an NRRD file, compressing it with gzip
+  /// if the data is [compressed](VolumeMd::compressed).
+  pub fn encode_nrrd_data(&self, data: &[u8]) -> Vec<u8> {
+    if self.compressed {
+      compression::gzip(data)
+    } else {
+      data.to_vec()
+    }
+  }
+
+  /// Serialize the metadata as a MetaImage header.
+  ///
+  /// # Returns
+  ///
+  /// A header for an `.mhd` file (or the start of an `.mha` file)
+  /// with every known field. `ElementDataFile` is the [data
+  /// file](VolumeMd::data_file), `LOCAL` if there is none, followed
+  /// by the [list of data files](VolumeMd::data_file_list) if any.
+  ///
+  /// # Notes
+  ///
+  /// `CompressedDataSize` is only written for
+  /// [compressed](VolumeMd::compressed) data of a known
+  /// [size](VolumeMd::compressed_data_size).
+  pub fn to_metaimage_header(&self) -> String {
+    /// Format values separated by spaces.
+    fn values(values: &[f64]) -> String {
+      values.iter().map(f64::to_string).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Format a boolean as a MetaImage boolean.
+    fn boolean(value: bool) -> &'static str {
+      if value {
+        "True"
+      } else {
+        "False"
+      }
+    }
+
+    let mut res = String::from("ObjectType = Image\n");
+    res += "NDims = 3\n";
+    res += "BinaryData = True\n";
+    res += &format!("BinaryDataByteOrderMSB = {}\n", boolean(self.byte_order_msb));
+    res += &format!("CompressedData = {}\n", boolean(self.compressed));
+
+    if let (true, Some(size)) = (self.compressed, self.compressed_data_size) {
+      res += &format!("CompressedDataSize = {}\n", size);
+    }
+
+    res += &format!("TransformMatrix = {}\n", values(&self.transform));
+    res += &format!("Offset = {}\n", values(&self.offset));
+
+    if let Some(orientation) = self.orientation {
+      res += &format!("AnatomicalOrientation = {}\n", orientation);
+    }
+
+    res += &format!("ElementSpacing = {}\n", values(&self.spacing));
+    res += &format!("DimSize = {} {} {}\n", self.xdim, self.ydim, self.zdim);
+
+    if self.header_size != HeaderSize::Bytes(0) {
+      res += &format!("HeaderSize = {}\n", self.header_size);
+    }
+
+    res += &format!("ElementType = {}\n", self.element_type);
+    res += &format!("ElementDataFile = {}\n", self.data_file.as_deref().unwrap_or("LOCAL"));
+
+    for data_file in &self.data_file_list {
+      res += &format!("{}\n", data_file);
+    }
+
+    res
+  }
+
+  /// Encode volume data for a MetaImage file, compressing it with
+  /// zlib if the data is [compressed](VolumeMd::compressed).
+  pub fn encode_metaimage_data(&self, data: &[u8]) -> Vec<u8> {
+    if self.compressed {
+      compression::deflate(data)
+    } else {
+      data.to_vec()
+    }
+  }
+
+  /// Resolve the [data file](VolumeMd::data_file) named by the
+  /// metadata.
+  ///
+  /// # Arguments
+  ///
+  /// * `dir` - The directory of the metadata file, which relative
+  ///   data files are resolved against.
+  ///
+  /// # Returns
+  ///
+  /// Where the volume data is stored, or [an error](Err) in case
+  /// there is no data file or a `LIST` or file pattern is invalid.
+  ///
+  /// # Notes
+  ///
+  /// The data file is either `LOCAL`, a single file, `LIST` followed
+  /// by one file per line, or a printf-like pattern followed by the
+  /// first index, the last index and the step between indexes (e.g.
+  /// `slice%03d.raw 1 40 1`). A pattern may not name more files than
+  /// there are slices.
+  pub fn resolve_data_file(&self, dir: &Path) -> Result<DataFile, MedvizErr> {
+    let data_file = match self.data_file.as_deref() {
+      Some(data_file) => data_file,
+      None => return Err(MedvizErr::new_md_data_file_not_found()),
+    };
+    let invalid = || MedvizErr::new_invalid_data_file(data_file.into());
+
+    if data_file == "LOCAL" {
+      return Ok(DataFile::Local);
+    }
+
+    if is_list(data_file) {
+      if self.data_file_list.is_empty() {
+        return Err(invalid());
+      }
+
+      return Ok(DataFile::List(self.data_file_list.iter().map(|file| dir.join(file)).collect()));
+    }
+
+    let parts: Vec<&str> = data_file.split_whitespace().collect();
+    let range: Option<Vec<i64>> = parts.iter().skip(1).map(|part| part.parse().ok()).collect();
+
+    match (parts.as_slice(), range.as_deref()) {
+      ([pattern, ..], Some(&[first, last, step])) if pattern.contains('%') => {
+        if step == 0 {
+          return Err(invalid());
+        }
+
+        format_pattern(pattern, first).ok_or_else(invalid)?;
+
+        // Each data file holds at least one slice, so the range is
+        // checked before the list of files is built.
+        let count = ((i128::from(last) - i128::from(first)) / i128::from(step) + 1).max(0);
+        if count == 0 {
+          return Err(invalid());
+        }
+        if count > self.zdim as i128 {
+          return Err(MedvizErr::new_data_file_count(usize::try_from(count).unwrap_or(usize::MAX)));
+        }
+
+        let files = iter::successors(Some(first), |index| index.checked_add(step))
+          .take(count as usize)
+          .map(|index| {
+            format_pattern(pattern, index).map(|file| dir.join(file)).ok_or_else(invalid)
+          })
+          .collect::<Result<_, _>>()?;
+
+        Ok(DataFile::List(files))
+      }
+      _ => Ok(DataFile::Single(dir.join(data_file))),
+    }
+  }
+
+  /// The number of voxels in the volume.
+  ///
+  /// # Returns
+  ///
+  /// The number of voxels, or [an error](Err) in case the [volume
+  /// data](VolumeMd::data_size) is too large.
+  pub fn voxel_count(&self) -> Result<usize, MedvizErr> {
+    Ok(self.data_size()? / self.element_type.size())
+  }
+
+  /// The size of the volume data in bytes.
+  ///
+  /// # Returns
+  ///
+  /// The size of the volume data, or [an error](Err) in case it
+  /// overflows or is larger than can be addressed in memory.
+  pub fn data_size(&self) -> Result<usize, MedvizErr> {
+    [self.xdim, self.ydim, self.zdim]
+      .iter()
+      .try_fold(self.element_type.size(), |size, dim| size.checked_mul(*dim))
+      .filter(|size| *size <= isize::MAX as usize)
+      .ok_or_else(|| MedvizErr::new_volume_too_large(self.xdim, self.ydim, self.zdim))
+  }
+
+  /// Find the volume data in an uncompressed data file, after its
+  /// [header](VolumeMd::header_size).
+  ///
+  /// # Arguments
+  ///
+  /// * `len` - The size of the data file in bytes.
+  ///
+  /// # Returns
+  ///
+  /// The range of the volume data in the data file, or [an
+  /// error](Err) in case the size of the data does not match the
+  /// dimensions and element type.
+  pub fn data_range(&self, len: usize) -> Result<Range<usize>, MedvizErr> {
+    skip_header(self.header_size, len, self.data_size()?)
+  }
+
+  /// Extract the volume data from the contents of a data file.
+  ///
+  /// # Arguments
+  ///
+  /// * `bytes` - The contents of the data file, which are
+  ///   decompressed first (gzip or zlib) if the data is
+  ///   [compressed](VolumeMd::compressed).
+  ///
+  /// # Returns
+  ///
+  /// The volume data following the [header](VolumeMd::header_size),
+  /// or [an error](Err) in case decompression fails or the size of
+  /// the data does not match the dimensions and element type.
+  ///
+  /// # Notes
+  ///
+  /// * The header of a MetaImage data file precedes the compressed
+  ///   data, while the byte skip of an NRRD data file is applied to
+  ///   the decompressed data. A `byte skip` of `-1` is only allowed
+  ///   for raw NRRD data, so compressed NRRD data has no header then.
+  ///
+  /// * Only the first [`compressed_data_size`
+  ///   bytes](VolumeMd::compressed_data_size) of the compressed data
+  ///   are decompressed when that size is known.
+  ///
+  /// * Decompression stops shortly after the expected size of the
+  ///   data, so that a small file cannot expand to fill the memory.
+  pub fn decode_data<'b>(&self, bytes: &'b [u8]) -> Result<Cow<'b, [u8]>, MedvizErr> {
+    self.decode_part(bytes, self.data_size()?, self.compressed_data_size)
+  }
+
+  /// Extract `expected` bytes of volume data from the contents of a
+  /// data file, whose compressed data is `compressed_size` bytes
+  /// long if known.
+  fn decode_part<'b>(
+    &self,
+    bytes: &'b [u8],
+    expected: usize,
+    compressed_size: Option<usize>,
+  ) -> Result<Cow<'b, [u8]>, MedvizErr> {
+    if !self.compressed {
+      let range = skip_header(self.header_size, bytes.len(), expected)?;
+      return Ok(Cow::Borrowed(&bytes[range]));
+    }
+
+    let (bytes, header_size) = if self.header_compressed {
+      match self.header_size {
+        HeaderSize::Bytes(header_size) => (bytes, header_size),
+        HeaderSize::Auto => (bytes, 0),
+      }
+    } else {
+      let compressed = match (self.header_size, compressed_size) {
+        (HeaderSize::Auto, Some(size)) => skip_header(HeaderSize::Auto, bytes.len(), size)?,
+        (HeaderSize::Auto, None) => 0..bytes.len(),
+        (HeaderSize::Bytes(start), size) => {
+          let end = size.map_or(bytes.len(), |size| start.saturating_add(size));
+          if end > bytes.len() {
+            let available = bytes.len().saturating_sub(start);
+            return Err(MedvizErr::new_data_size_mismatch(available, end - start));
+          }
+          start..end
+        }
+      };
+      (&bytes[compressed], 0)
+    };
+
+    // One byte more than needed, so that too much data is reported as
+    // a size mismatch.
+    let limit = expected.saturating_add(header_size).saturating_add(1);
+    let mut data = compression::decompress(bytes, limit)?;
+
+    let range = skip_header(HeaderSize::Bytes(header_size), data.len(), expected)?;
+    data.drain(..range.start);
+    Ok(Cow::Owned(data))
+  }
+
+  /// Read the volume data into memory, decompressing it if needed.
+  ///
+  /// # Arguments
+  ///
+  /// * `data_file` - Where the volume data is stored (see
+  ///   [`resolve_data_file`](VolumeMd::resolve_data_file)).
+  ///
+  /// * `local` - The bytes following the metadata, used for a
+  ///   [local](DataFile::Local) data file.
+  ///
+  /// # Returns
+  ///
+  /// The volume data, or [an error](Err) in case a file cannot be
+  /// read, decompression fails or the size of the data does not
+  /// match the dimensions and element type.
+  pub fn read_data(&self, data_file: &DataFile, local: &[u8]) -> Result<Vec<u8>, MedvizErr> {
+    match data_file {
+      DataFile::Local => Ok(self.decode_data(local)?.into_owned()),
+      DataFile::Single(file) => {
+        let bytes = fs::read(file)
+          .map_err(|e| MedvizErr::new_io(file.display().to_string(), e.to_string()))?;
+        Ok(self.decode_data(&bytes)?.into_owned())
+      }
+      DataFile::List(files) => self.read_data_files(files),
+    }
+  }
+
+  /// Read the volume data split over several data files, each
+  /// holding an equal part of the volume after its
+  /// [header](VolumeMd::header_size).
+  ///
+  /// # Returns
+  ///
+  /// The volume data, or [an error](Err) in case a file cannot be
+  /// read, the number of files does not divide the volume, or the
+  /// size of a file does not match its part.
+  pub fn read_data_files(&self, files: &[PathBuf]) -> Result<Vec<u8>, MedvizErr> {
+    let size = self.data_size()?;
+    if files.is_empty() || !size.is_multiple_of(files.len()) {
+      return Err(MedvizErr::new_data_file_count(files.len()));
+    }
+
+    let expected = size / files.len();
+    let mut res = Vec::with_capacity(size);
+
+    for file in files {
+      let bytes =
+        fs::read(file).map_err(|e| MedvizErr::new_io(file.display().to_string(), e.to_string()))?;
+      res.extend_from_slice(&self.decode_part(&bytes, expected, None)?);
+    }
+
+    Ok(res)
+  }
+
+  /// Change the type of the voxels.
+  pub fn with_element_type(mut self, element_type: ElementType) -> Self {
+    self.element_type = element_type;
+    self
+  }
+
+  /// Change whether voxels are stored with the most significant byte
+  /// first.
+  pub fn with_byte_order_msb(mut self, byte_order_msb: bool) -> Self {
+    self.byte_order_msb = byte_order_msb;
+    self
+  }
+
+  /// Change the physical distance between voxel centers on each axis.
+  pub fn with_spacing(mut self, spacing: [f64; 3]) -> Self {
+    self.spacing = spacing;
+    self
+  }
+
+  /// Change the physical position of the first voxel.
+  pub fn with_offset(mut self, offset: [f64; 3]) -> Self {
+    self.offset = offset;
+    self
+  }
+
+  /// Change the direction cosines of the X-, Y- and Z-axis, three
+  /// values each.
+  pub fn with_transform(mut self, transform: [f64; 9]) -> Self {
+    self.transform = transform;
+    self
+  }
+
+  /// Change the anatomical orientation of the volume axes.
+  pub fn with_orientation(mut self, orientation: Option<AnatomicalOrientation>) -> Self {
+    self.orientation = orientation;
+    self
+  }
+
+  /// Change the file containing the volume data.
+  ///
+  /// Any [list of data files](VolumeMd::data_file_list) is cleared.
+  pub fn with_data_file(mut self, data_file: Option<String>) -> Self {
+    self.data_file = data_file;
+    self.data_file_list.clear();
+    self
+  }
+
+  /// Change the number of voxels on the X-, Y- and Z-axis.
+  pub fn with_dims(mut self, xdim: usize, ydim: usize, zdim: usize) -> Self {
+    self.xdim = xdim;
+    self.ydim = ydim;
+    self.zdim = zdim;
+    self
+  }
+
+  /// Change the size of the header in the data file.
+  pub fn with_header_size(mut self, header_size: HeaderSize) -> Self {
+    self.header_size = header_size;
+    self
+  }
+
+  /// Change whether the volume data is compressed.
+  pub fn with_compressed(mut self, compressed: bool) -> Self {
+    self.compressed = compressed;
+    self
+  }
+
+  /// Change the size of the compressed volume data.
+  pub fn with_compressed_data_size(mut self, compressed_data_size: Option<usize>) -> Self {
+    self.compressed_data_size = compressed_data_size;
+    self
+  }
+
+  /// Number of voxels in the X dimension.
+  pub fn xdim(&self) -> usize {
+    self.xdim
+  }
+
+  /// Number of voxels in the Y dimension.
+  pub fn ydim(&self) -> usize {
+    self.ydim
+  }
+
+  /// Number of voxels in the Z dimension.
+  pub fn zdim(&self) -> usize {
+    self.zdim
+  }
+
+  /// Number of voxels on the X-, Y- and Z-axis.
+  pub fn dims(&self) -> [usize; 3] {
+    [self.xdim, self.ydim, self.zdim]
+  }
+
+  /// Number of voxels on an axis.
+  pub fn dim(&self, axis: Axis) -> usize {
+    match axis {
+      Axis::X => self.xdim,
+      Axis::Y => self.ydim,
+      Axis::Z => self.zdim,
+    }
+  }
+
+  /// Return an error if `index` is outside the range of frames on
+  /// `axis`.
+  pub(crate) fn check_frame_index(&self, axis: Axis, index: usize) -> Result<(), MedvizErr> {
+    let len = self.dim(axis);
+
+    if index >= len {
+      return Err(MedvizErr::new_frame_index_out_of_range(axis, index, len));
+    }
+
+    Ok(())
+  }
+
+  /// Resolve the position of a frame to its index.
+  ///
+  /// Percentages are rounded to the nearest frame, with `0%` being
+  /// the first frame and `100%` the last one. Millimetres are
+  /// measured from the first frame using the spacing of the axis and
+  /// rounded to the nearest frame.
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis the frame is on.
+  ///
+  /// * `position` - The position of the frame.
+  ///
+  /// # Returns
+  ///
+  /// The index of the frame or [an error](Err) in case the position
+  /// is outside the range of frames.
+  pub fn frame_index(&self, axis: Axis, position: FramePosition) -> Result<usize, MedvizErr> {
+    let len = self.dim(axis);
+    let last = len.saturating_sub(1) as f64;
+
+    let index = match position {
+      FramePosition::Index(index) => Some(index),
+      FramePosition::Percent(percent) if (0.0..=100.0).contains(&percent) => {
+        Some((percent / 100.0 * last).round() as usize)
+      }
+      FramePosition::Millimetres(millimetres) => {
+        let index = (millimetres / self.spacing[axis.index()]).round();
+        if index >= 0.0 && index <= last {
+          Some(index as usize)
+        } else {
+          None
+        }
+      }
+      FramePosition::Percent(_) => None,
+    };
+
+    match index {
+      Some(index) if index < len => Ok(index),
+      _ => Err(MedvizErr::new_frame_position_out_of_range(axis, position.to_string(), len)),
+    }
+  }
+
+  /// The columns of the matrix mapping voxel indexes to world
+  /// coordinates, relative to the offset: the direction of each axis
+  /// scaled by its spacing.
+  fn index_to_world_columns(&self) -> [Vector; 3] {
+    let mut columns = [[0.0; 3]; 3];
+
+    for (axis, column) in columns.iter_mut().enumerate() {
+      let direction =
+        [self.transform[axis * 3], self.transform[axis * 3 + 1], self.transform[axis * 3 + 2]];
+      *column = vector::scale(direction, self.spacing[axis]);
+    }
+
+    columns
+  }
+
+  /// Map a voxel index to world (patient) coordinates in millimetres.
+  ///
+  /// Uses the offset of the volume as the position of the first
+  /// voxel, the spacing and the transform matrix, whose first three
+  /// values are the direction of the X-axis, the next three the
+  /// direction of the Y-axis and the last three the direction of the
+  /// Z-axis.
+  ///
+  /// # Arguments
+  ///
+  /// * `index` - The voxel index, which can be fractional to refer to
+  ///   positions between voxel centers.
+  ///
+  /// # Returns
+  ///
+  /// The world coordinates of the voxel center.
+  pub fn index_to_world(&self, index: [f64; 3]) -> [f64; 3] {
+    let [c0, c1, c2] = self.index_to_world_columns();
+    let position = vector::add(vector::scale(c0, index[0]), vector::scale(c1, index[1]));
+    vector::add(self.offset, vector::add(position, vector::scale(c2, index[2])))
+  }
+
+  /// Map world (patient) coordinates in millimetres to a voxel index,
+  /// the inverse of [`index_to_world`](VolumeMd::index_to_world).
+  ///
+  /// # Arguments
+  ///
+  /// * `world` - The world coordinates.
+  ///
+  /// # Returns
+  ///
+  /// The fractional voxel index, which may be outside the volume, or
+  /// [an error](Err) in case the transform cannot be inverted (e.g. a
+  /// spacing of zero).
+  pub fn world_to_index(&self, world: [f64; 3]) -> Result<[f64; 3], MedvizErr> {
+    let relative = vector::add(world, vector::scale(self.offset, -1.0));
+    vector::solve(self.index_to_world_columns(), relative).ok_or(MedvizErr::SingularTransform)
+  }
+
+  /// Find the frame on an axis that contains a point in world
+  /// coordinates.
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis the frame is on.
+  ///
+  /// * `world` - The world coordinates of the point.
+  ///
+  /// # Returns
+  ///
+  /// The index of the frame closest to the point, or [an error](Err)
+  /// in case the point is outside the range of frames or the
+  /// transform cannot be inverted.
+  pub fn world_frame_index(&self, axis: Axis, world: [f64; 3]) -> Result<usize, MedvizErr> {
+    let index = self.world_to_index(world)?[axis.index()].round();
+    let len = self.dim(axis);
+
+    if index >= 0.0 && index < len as f64 {
+      Ok(index as usize)
+    } else {
+      let position = format!("({}, {}, {})", world[0], world[1], world[2]);
+      Err(MedvizErr::new_frame_position_out_of_range(axis, position, len))
+    }
+  }
+
+  /// Number of voxels in a row and number of rows of a frame on an
+  /// axis.
+  pub fn frame_dims(&self, axis: Axis) -> (usize, usize) {
+    match axis {
+      Axis::X => (self.ydim, self.zdim),
+      Axis::Y => (self.xdim, self.zdim),
+      Axis::Z => (self.xdim, self.ydim),
+    }
+  }
+
+  /// Number of voxels in a frame on the X-axis.
+  pub fn xframe_len(&self) -> usize {
+    self.ydim * self.zdim
+  }
+
+  /// Number of voxels in a frame on the Y-axis.
+  pub fn yframe_len(&self) -> usize {
+    self.xdim * self.zdim
+  }
+
+  /// Number of voxels in a frame on the Z-axis.
+  pub fn zframe_len(&self) -> usize {
+    self.xdim * self.ydim
+  }
+
+  /// Physical distance between voxel centers on the X-, Y- and
+  /// Z-axis, usually in millimetres.
+  pub fn spacing(&self) -> [f64; 3] {
+    self.spacing
+  }
+
+  /// Physical distance between voxels in a row and between rows of a
+  /// frame on an axis.
+  pub fn frame_spacing(&self, axis: Axis) -> (f64, f64) {
+    match axis {
+      Axis::X => (self.spacing[1], self.spacing[2]),
+      Axis::Y => (self.spacing[0], self.spacing[2]),
+      Axis::Z => (self.spacing[0], self.spacing[1]),
+    }
+  }
+
+  /// Physical position of the first voxel, usually in millimetres.
+  pub fn offset(&self) -> [f64; 3] {
+    self.offset
+  }
+
+  /// Direction cosines of the volume axes.
+  ///
+  /// The first three values are the direction of the X-axis, followed
+  /// by the directions of the Y- and Z-axis.
+  pub fn transform(&self) -> [f64; 9] {
+    self.transform
+  }
+
+  /// Type of the voxels.
+  pub fn element_type(&self) -> ElementType {
+    self.element_type
+  }
+
+  /// Whether voxels are stored with the most significant byte first
+  /// (big-endian).
+  pub fn byte_order_msb(&self) -> bool {
+    self.byte_order_msb
+  }
+
+  /// The file containing the volume data, exactly as written in the
+  /// metadata.
+  pub fn data_file(&self) -> Option<&str> {
+    self.data_file.as_deref()
+  }
+
+  /// The data files following a `LIST` [data
+  /// file](VolumeMd::data_file), exactly as written in the metadata.
+  pub fn data_file_list(&self) -> &[String] {
+    &self.data_file_list
+  }
+
+  /// Size of the header preceding the volume data in the data file.
+  pub fn header_size(&self) -> HeaderSize {
+    self.header_size
+  }
+
+  /// Whether the volume data is compressed.
+  pub fn compressed(&self) -> bool {
+    self.compressed
+  }
+
+  /// Size of the compressed volume data in bytes
+  /// (`CompressedDataSize`), if known.
+  pub fn compressed_data_size(&self) -> Option<usize> {
+    self.compressed_data_size
+  }
+
+  /// Anatomical orientation of the volume axes, if known.
+  pub fn orientation(&self) -> Option<AnatomicalOrientation> {
+    self.orientation
+  }
+}
+
+/// Find `expected` bytes of data in a data file of `len` bytes,
+/// after a header of `header_size`.
+fn skip_header(
+  header_size: HeaderSize,
+  len: usize,
+  expected: usize,
+) -> Result<Range<usize>, MedvizErr> {
+  let start = match header_size {
+    HeaderSize::Bytes(bytes) => bytes,
+    HeaderSize::Auto => len.saturating_sub(expected),
+  };
+
+  let actual = len.saturating_sub(start);
+  if actual != expected {
+    return Err(MedvizErr::new_data_size_mismatch(actual, expected));
+  }
+
+  Ok(start..len)
+}
+
+/// Parse the values of a `DimSize` key.
+///
+/// # Arguments
+///
+/// * `line_number` - The current input line number for errors.
+///
+/// * `value` - The value of the `DimSize` entry.
+///
+/// # Returns
+///
+/// The X, Y and Z dimensions or [an error](Err).
+fn parse_dim_size(line_number: usize, value: &str) -> Result<(usize, usize, usize), MedvizErr> {
+  let mut dims = value.split_whitespace();
+
+  /// Read a dimension value from the dims iterator.
+  ///
+  /// # Uses
+  ///
+  /// * `dims` - The iterator over dimension values.
+  ///
+  /// * `line_number` - The current input line number for errors.
+  ///
+  /// # Returns
+  ///
+  /// The value read or exits the function with [an error](Err) in
+  /// case there are no more values to be read.
+  macro_rules! read_dimension_size {
+    () => {
+      match dims.next() {
+        Some(dim) => dim,
+        None => return Err(MedvizErr::new_md_missing_dim_size_values(line_number)),
+      }
+    };
+  }
+
+  let xdim_text = read_dimension_size!();
+  let ydim_text = read_dimension_size!();
+  let zdim_text = read_dimension_size!();
+
+  if dims.next().is_some() {
+    // There were more than 3 values provided.
+    return Err(MedvizErr::new_md_too_many_dim_size_values(line_number));
+  }
+
+  /// Parse a dimension value from a string.
+  ///
+  /// # Arguments
+  ///
+  /// * `$text` - The input text to parse the value from.
+  ///
+  /// # Uses
+  ///
+  /// * `line_number` - The current input line number for errors.
+  ///
+  /// # Returns
+  ///
+  /// The value parsed or exits the function with [an error](Err)
+  /// in case of invalid input or overflow.
+  macro_rules! parse_dimension_size {
+    ($text:ident) => {{
+      let text: &str = $text;
+      let (dim, rem) = usize::from_radix_10_checked(text.as_bytes());
+
+      if rem == 0 {
+        // The input was not a valid number.
+        return Err(MedvizErr::new_md_invalid_dim_size_value(line_number, text.into()));
+      }
+
+      match dim {
+        Some(dim) => dim,
+        None => {
+          // The input value would overflow usize.
+          return Err(MedvizErr::new_md_invalid_dim_size_value(line_number, text.into()));
+        }
       }
+    }};
+  }
 
-      let value = match entry.next() {
-        Some(value) => value.trim(),
-        None => return Err(MedvizErr::new_md_missing_dim_size_values(line_number)),
-      };
+  let xdim = parse_dimension_size!(xdim_text);
+  let ydim = parse_dimension_size!(ydim_text);
+  let zdim = parse_dimension_size!(zdim_text);
 
-      let mut dims = value.split_whitespace();
+  Ok((xdim, ydim, zdim))
+}
 
-      /// Read a dimension value from the dims iterator.
-      ///
-      /// # Uses
-      ///
-      /// * `dims` - The iterator over dimension values.
-      ///
-      /// * `line_number` - The current input line number for errors.
-      ///
-      /// # Returns
-      ///
-      /// The value read or exits the function with [an error](Err) in
-      /// case there are no more values to be read.
-      macro_rules! read_dimension_size {
-        () => {
-          match dims.next() {
-            Some(dim) => dim,
-            None => return Err(MedvizErr::new_md_missing_dim_size_values(line_number)),
-          }
-        };
-      }
+/// Whether a data file is a `LIST` of files (optionally followed by
+/// the dimension of the files, e.g. `LIST 2D`).
+fn is_list(data_file: &str) -> bool {
+  data_file.split_whitespace().next() == Some("LIST")
+}
 
-      let xdim_text = read_dimension_size!();
-      let ydim_text = read_dimension_size!();
-      let zdim_text = read_dimension_size!();
+/// Format the index of a data file with a printf-like pattern
+/// containing a `%d` placeholder, optionally with a width (e.g.
+/// `%3d`) and zero padding (e.g. `%03d`).
+fn format_pattern(pattern: &str, index: i64) -> Option<String> {
+  let start = pattern.find('%')?;
+  let rest = &pattern[start + 1..];
+  let end = rest.find('d')?;
+  let spec = &rest[..end];
 
-      if dims.next().is_some() {
-        // There were more than 3 values provided.
-        return Err(MedvizErr::new_md_too_many_dim_size_values(line_number));
-      }
+  if !spec.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
 
-      /// Parse a dimension value from a string.
-      ///
-      /// # Arguments
-      ///
-      /// * `$text` - The input text to parse the value from.
-      ///
-      /// # Uses
-      ///
-      /// * `line_number` - The current input line number for errors.
-      ///
-      /// # Returns
-      ///
-      /// The value parsed or exits the function with [an error](Err)
-      /// in case of invalid input or overflow.
-      macro_rules! parse_dimension_size {
-        ($text:ident) => {{
-          let text: &str = $text;
-          let (dim, rem) = usize::from_radix_10_checked(text.as_bytes());
-
-          if rem == 0 {
-            // The input was not a valid number.
-            return Err(MedvizErr::new_md_invalid_dim_size_value(line_number, text.into()));
-          }
+  let width = if spec.is_empty() { 0 } else { spec.parse().ok()? };
+  let index = if spec.starts_with('0') {
+    format!("{:0width$}", index, width = width)
+  } else {
+    format!("{:width$}", index, width = width)
+  };
 
-          match dim {
-            Some(dim) => dim,
-            None => {
-              // The input value would overflow usize.
-              return Err(MedvizErr::new_md_invalid_dim_size_value(line_number, text.into()));
-            }
-          }
-        }};
+  Some(format!("{}{}{}", &pattern[..start], index, &rest[end + 1..]))
+}
+
+/// Parse whitespace-separated NRRD vectors (e.g. `(1,0,0) none`),
+/// skipping `none` vectors of non-spatial axes.
+fn parse_nrrd_vectors(
+  line_number: usize,
+  key: &'static str,
+  value: &str,
+) -> Result<Vec<Vector>, MedvizErr> {
+  let invalid = || MedvizErr::new_md_invalid_value(line_number, key, value.into());
+
+  value
+    .split_whitespace()
+    .filter(|text| *text != "none")
+    .map(|text| {
+      let text =
+        text.strip_prefix('(').and_then(|text| text.strip_suffix(')')).ok_or_else(invalid)?;
+      let mut res = [0.0; 3];
+      let mut components = text.split(',');
+
+      for res in res.iter_mut() {
+        *res = components.next().and_then(|c| c.trim().parse().ok()).ok_or_else(invalid)?;
       }
 
-      let xdim = parse_dimension_size!(xdim_text);
-      let ydim = parse_dimension_size!(ydim_text);
-      let zdim = parse_dimension_size!(zdim_text);
+      match components.next() {
+        Some(_) => Err(invalid()),
+        None => Ok(res),
+      }
+    })
+    .collect()
+}
 
-      res = Some(Self { xdim, ydim, zdim });
+/// The element type of an NRRD `type` field.
+fn nrrd_element_type(name: &str) -> Option<ElementType> {
+  match name {
+    "signed char" | "int8" | "int8_t" => Some(ElementType::Char),
+    "uchar" | "unsigned char" | "uint8" | "uint8_t" => Some(ElementType::UChar),
+    "short" | "short int" | "signed short" | "signed short int" | "int16" | "int16_t" => {
+      Some(ElementType::Short)
     }
-
-    match res {
-      Some(res) => Ok(res),
-      None => Err(MedvizErr::new_md_dim_size_not_found()),
+    "ushort" | "unsigned short" | "unsigned short int" | "uint16" | "uint16_t" => {
+      Some(ElementType::UShort)
+    }
+    "int" | "signed int" | "int32" | "int32_t" => Some(ElementType::Int),
+    "uint" | "unsigned int" | "uint32" | "uint32_t" => Some(ElementType::UInt),
+    "longlong"
+    | "long long"
+    | "long long int"
+    | "signed long long"
+    | "signed long long int"
+    | "int64"
+    | "int64_t" => Some(ElementType::LongLong),
+    "ulonglong" | "unsigned long long" | "unsigned long long int" | "uint64" | "uint64_t" => {
+      Some(ElementType::ULongLong)
     }
+    "float" => Some(ElementType::Float),
+    "double" => Some(ElementType::Double),
+    _ => None,
   }
+}
 
-  /// Number of voxels in the X dimension.
-  pub fn xdim(&self) -> usize {
-    self.xdim
+/// The NRRD `type` field of an element type.
+fn nrrd_type_name(element_type: ElementType) -> &'static str {
+  match element_type {
+    ElementType::Char => "int8",
+    ElementType::UChar => "uint8",
+    ElementType::Short => "int16",
+    ElementType::UShort => "uint16",
+    ElementType::Int | ElementType::Long => "int32",
+    ElementType::UInt | ElementType::ULong => "uint32",
+    ElementType::LongLong => "int64",
+    ElementType::ULongLong => "uint64",
+    ElementType::Float => "float",
+    ElementType::Double => "double",
   }
+}
 
-  /// Number of voxels in the Y dimension.
-  pub fn ydim(&self) -> usize {
-    self.ydim
-  }
+/// Parse exactly `N` whitespace-separated values of a key.
+///
+/// # Arguments
+///
+/// * `line_number` - The current input line number for errors.
+///
+/// * `key` - The key name to use in errors.
+///
+/// * `value` - The value of the entry.
+///
+/// # Returns
+///
+/// The parsed values or [an error](Err) in case there are too few or
+/// too many values, or one of them is invalid.
+fn parse_values<T: FromStr + Copy + Default, const N: usize>(
+  line_number: usize,
+  key: &'static str,
+  value: &str,
+) -> Result<[T; N], MedvizErr> {
+  let mut res = [T::default(); N];
+  let mut values = value.split_whitespace();
 
-  /// Number of voxels in the Z dimension.
-  pub fn zdim(&self) -> usize {
-    self.zdim
-  }
+  for res in res.iter_mut() {
+    let text = match values.next() {
+      Some(text) => text,
+      None => return Err(MedvizErr::new_md_missing_values(line_number, key)),
+    };
 
-  /// Number of voxels in a frame on the X-axis.
-  pub fn xframe_len(&self) -> usize {
-    self.ydim * self.zdim
+    *res = match text.parse() {
+      Ok(value) => value,
+      Err(_) => return Err(MedvizErr::new_md_invalid_value(line_number, key, text.into())),
+    };
   }
 
-  /// Number of voxels in a frame on the Y-axis.
-  pub fn yframe_len(&self) -> usize {
-    self.xdim * self.zdim
+  if values.next().is_some() {
+    return Err(MedvizErr::new_md_too_many_values(line_number, key));
   }
 
-  /// Number of voxels in a frame on the Z-axis.
-  pub fn zframe_len(&self) -> usize {
-    self.xdim * self.ydim
+  Ok(res)
+}
+
+/// Parse a boolean value (`True` or `False`) of a key.
+fn parse_bool(line_number: usize, key: &'static str, value: &str) -> Result<bool, MedvizErr> {
+  if value.eq_ignore_ascii_case("true") || value == "1" {
+    Ok(true)
+  } else if value.eq_ignore_ascii_case("false") || value == "0" {
+    Ok(false)
+  } else {
+    Err(MedvizErr::new_md_invalid_value(line_number, key, value.into()))
   }
 }
 
 #[cfg(test)]
 mod volume_metadata_tests {
-  use super::VolumeMd;
-  use crate::MedvizErr;
+  use super::{AnatomicalOrientation, DataFile, Direction, ElementType, HeaderSize, VolumeMd};
+  use crate::{Axis, FramePosition, MedvizErr};
+  use std::path::Path;
 
   #[test]
   fn from_reader_success() {
@@ -212,6 +1902,384 @@ mod volume_metadata_tests {
     assert_eq!(metadata.zdim, 333);
   }
 
+  #[test]
+  fn from_reader_success_full() {
+    let input = "ObjectType = Image\n\
+                 NDims = 3\n\
+                 BinaryData = True\n\
+                 BinaryDataByteOrderMSB = True\n\
+                 CompressedData = False\n\
+                 TransformMatrix = 1 0 0 0 -1 0 0 0 1\n\
+                 Offset = -102.5 -110 -148.75\n\
+                 CenterOfRotation = 0 0 0\n\
+                 AnatomicalOrientation = RAI\n\
+                 ElementSpacing = 0.402344 0.402344 0.899994\n\
+                 DimSize = 512 512 333\n\
+                 HeaderSize = -1\n\
+                 ElementType = MET_SHORT\n\
+                 ElementDataFile = sinus.raw\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    assert_eq!(metadata.spacing(), [0.402344, 0.402344, 0.899994]);
+    assert_eq!(metadata.offset(), [-102.5, -110.0, -148.75]);
+    assert_eq!(metadata.transform(), [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(metadata.element_type(), ElementType::Short);
+    assert!(metadata.byte_order_msb());
+    assert_eq!(metadata.data_file(), Some("sinus.raw"));
+    assert_eq!(metadata.header_size(), HeaderSize::Auto);
+    assert!(!metadata.compressed());
+    assert_eq!(
+      metadata.orientation(),
+      AnatomicalOrientation::new(Direction::Right, Direction::Anterior, Direction::Inferior)
+    );
+  }
+
+  #[test]
+  fn from_reader_success_defaults() {
+    let metadata = VolumeMd::from_buffer("DimSize = 2 3 4").unwrap();
+    assert_eq!(metadata, VolumeMd::new(2, 3, 4));
+    assert_eq!(metadata.spacing(), [1.0, 1.0, 1.0]);
+    assert_eq!(metadata.element_type(), ElementType::UShort);
+    assert_eq!(metadata.header_size(), HeaderSize::Bytes(0));
+    assert_eq!(metadata.orientation(), None);
+  }
+
+  #[test]
+  fn index_to_world() {
+    let input = "DimSize = 10 10 10\nElementSpacing = 0.5 0.5 2\nOffset = -10 20 5\n\
+                 TransformMatrix = 1 0 0 0 -1 0 0 0 1\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    assert_eq!(metadata.index_to_world([0.0, 0.0, 0.0]), [-10.0, 20.0, 5.0]);
+    assert_eq!(metadata.index_to_world([2.0, 4.0, 1.5]), [-9.0, 18.0, 8.0]);
+    assert_eq!(metadata.world_to_index([-9.0, 18.0, 8.0]), Ok([2.0, 4.0, 1.5]));
+    assert_eq!(metadata.world_frame_index(Axis::Y, [-9.0, 18.0, 8.0]), Ok(4));
+    assert_eq!(metadata.world_frame_index(Axis::Z, [0.0, 0.0, 8.4]), Ok(2));
+    assert_eq!(
+      metadata.world_frame_index(Axis::Y, [0.0, 21.0, 0.0]),
+      Err(MedvizErr::new_frame_position_out_of_range(Axis::Y, "(0, 21, 0)".into(), 10))
+    );
+  }
+
+  #[test]
+  fn index_to_world_oblique() {
+    // The X-axis points along Y in world coordinates and the Y-axis
+    // along -X.
+    let input = "DimSize = 4 4 4\nElementSpacing = 1 2 3\nOffset = 1 1 1\n\
+                 TransformMatrix = 0 1 0 -1 0 0 0 0 1\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    assert_eq!(metadata.index_to_world([1.0, 1.0, 1.0]), [-1.0, 2.0, 4.0]);
+    assert_eq!(metadata.world_to_index([-1.0, 2.0, 4.0]), Ok([1.0, 1.0, 1.0]));
+  }
+
+  #[test]
+  fn world_to_index_singular() {
+    let metadata = VolumeMd::new(2, 2, 2).with_spacing([1.0, 0.0, 1.0]);
+    assert_eq!(metadata.world_to_index([0.0; 3]), Err(MedvizErr::SingularTransform));
+  }
+
+  #[test]
+  fn with_fields() {
+    let metadata = VolumeMd::new(2, 3, 4)
+      .with_element_type(ElementType::Float)
+      .with_byte_order_msb(true)
+      .with_spacing([0.5, 0.5, 2.0])
+      .with_offset([-10.0, 0.0, 5.0]);
+    let input = "DimSize = 2 3 4\nElementType = MET_FLOAT\nElementByteOrderMSB = True\n\
+                 ElementSpacing = 0.5 0.5 2\nOffset = -10 0 5\n";
+    assert_eq!(metadata, VolumeMd::from_buffer(input).unwrap());
+  }
+
+  #[test]
+  fn data_size_too_large() {
+    let metadata = VolumeMd::new(2, 3, 4).with_element_type(ElementType::Float);
+    assert_eq!(metadata.data_size(), Ok(96));
+    assert_eq!(metadata.voxel_count(), Ok(24));
+
+    let metadata = VolumeMd::from_buffer(
+      "DimSize = 4294967296 4294967296 2
+",
+    )
+    .unwrap();
+    let too_large = || MedvizErr::new_volume_too_large(1 << 32, 1 << 32, 2);
+    assert_eq!(metadata.data_size(), Err(too_large()));
+    assert_eq!(metadata.voxel_count(), Err(too_large()));
+    assert_eq!(metadata.data_range(16), Err(too_large()));
+    let compressed = metadata.clone().with_compressed(true);
+    assert_eq!(compressed.decode_data(&[]), Err(too_large()));
+    assert_eq!(crate::Volume::from_slice(metadata, &[]).err(), Some(too_large()));
+  }
+
+  #[test]
+  fn from_reader_success_aliases() {
+    let input = "DimSize = 2 3 4\n\
+                 Origin = 1 2 3\n\
+                 Rotation = 0 1 0 1 0 0 0 0 1\n\
+                 ElementByteOrderMSB = False\n\
+                 AnatomicalOrientation = ???\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    assert_eq!(metadata.offset(), [1.0, 2.0, 3.0]);
+    assert_eq!(metadata.transform(), [0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    assert!(!metadata.byte_order_msb());
+    assert_eq!(metadata.orientation(), None);
+  }
+
+  #[test]
+  fn from_reader_success_stops_at_data_file() {
+    let input = "DimSize = 2 3 4\n\
+                 ElementDataFile = LOCAL\n\
+                 DimSize = 2 3 4\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    assert_eq!(metadata.data_file(), Some("LOCAL"));
+  }
+
+  #[test]
+  fn from_bytes_local() {
+    let input = b"DimSize = 2 1 1\n\
+                  ElementType = MET_UCHAR\n\
+                  HeaderSize = -1\n\
+                  ElementDataFile = LOCAL\n\
+                  \x00\x01\x02";
+    let (metadata, local) = VolumeMd::from_bytes(input).unwrap();
+    assert_eq!(metadata.data_file(), Some("LOCAL"));
+    assert_eq!(metadata.resolve_data_file(Path::new("dir")), Ok(DataFile::Local));
+    assert_eq!(local, b"\x00\x01\x02");
+    assert_eq!(metadata.decode_data(local).unwrap().as_ref(), [1, 2]);
+    assert_eq!(metadata.data_range(3), Ok(1..3));
+    assert_eq!(
+      metadata.with_header_size(HeaderSize::Bytes(2)).data_range(3),
+      Err(MedvizErr::new_data_size_mismatch(1, 2))
+    );
+  }
+
+  #[test]
+  fn resolve_data_file() {
+    let dir = Path::new("scans");
+    let resolve = |input: &str| VolumeMd::from_buffer(input).unwrap().resolve_data_file(dir);
+
+    assert_eq!(
+      resolve("DimSize = 1 1 1\nElementDataFile = scan.raw\n"),
+      Ok(DataFile::Single(dir.join("scan.raw")))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 2\nElementDataFile = LIST 2D\nslice1.raw\n\n  slice2.raw\n"),
+      Ok(DataFile::List(vec![dir.join("slice1.raw"), dir.join("slice2.raw")]))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 3\nElementDataFile = slice%03d.raw 8 12 2\n"),
+      Ok(DataFile::List(vec![
+        dir.join("slice008.raw"),
+        dir.join("slice010.raw"),
+        dir.join("slice012.raw")
+      ]))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 2\nElementDataFile = %d.raw 2 1 -1\n"),
+      Ok(DataFile::List(vec![dir.join("2.raw"), dir.join("1.raw")]))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 1\nElementDataFile = %d.raw 1 2 0\n"),
+      Err(MedvizErr::new_invalid_data_file("%d.raw 1 2 0".into()))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 1\nElementDataFile = %s.raw 1 2 1\n"),
+      Err(MedvizErr::new_invalid_data_file("%s.raw 1 2 1".into()))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 3\nElementDataFile = %d.raw 0 9223372036854775807 1\n"),
+      Err(MedvizErr::new_data_file_count(1 << 63))
+    );
+    assert_eq!(
+      resolve(
+        "DimSize = 1 1 2\nElementDataFile = %d.raw 9223372036854775806 9223372036854775807 1\n"
+      ),
+      Ok(DataFile::List(vec![
+        dir.join("9223372036854775806.raw"),
+        dir.join("9223372036854775807.raw")
+      ]))
+    );
+    assert_eq!(
+      resolve("DimSize = 1 1 1\nElementDataFile = LIST\n"),
+      Err(MedvizErr::new_invalid_data_file("LIST".into()))
+    );
+    assert_eq!(resolve("DimSize = 1 1 1\n"), Err(MedvizErr::MdDataFileNotFound));
+
+    let (metadata, local) =
+      VolumeMd::from_bytes(b"DimSize = 1 1 2\nElementDataFile = LIST\na.raw\nb.raw\n").unwrap();
+    assert_eq!(metadata.data_file_list(), ["a.raw", "b.raw"]);
+    assert!(local.is_empty());
+  }
+
+  #[test]
+  fn read_data_files() {
+    let dir = std::env::temp_dir().join(format!("medviz-data-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("1.raw"), [9, 1, 2]).unwrap();
+    std::fs::write(dir.join("2.raw"), [9, 3, 4]).unwrap();
+
+    let input = "DimSize = 2 1 2\n\
+                 ElementType = MET_UCHAR\n\
+                 HeaderSize = 1\n\
+                 ElementDataFile = %d.raw 1 2 1\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    let files = match metadata.resolve_data_file(&dir).unwrap() {
+      DataFile::List(files) => files,
+      data_file => panic!("Unexpected data file {:?}", data_file),
+    };
+    assert_eq!(metadata.read_data_files(&files), Ok(vec![1, 2, 3, 4]));
+    assert_eq!(metadata.read_data_files(&files[..1]), Err(MedvizErr::new_data_size_mismatch(2, 4)));
+    assert_eq!(
+      metadata.read_data_files(&[files[0].clone(), files[1].clone(), files[0].clone()]),
+      Err(MedvizErr::new_data_file_count(3))
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn metaimage_round_trip() {
+    let input = "ObjectType = Image\n\
+                 NDims = 3\n\
+                 BinaryData = True\n\
+                 BinaryDataByteOrderMSB = True\n\
+                 CompressedData = True\n\
+                 CompressedDataSize = 1234\n\
+                 TransformMatrix = 0 1 0 -1 0 0 0 0 1\n\
+                 Offset = -120.5 0.1 3\n\
+                 CenterOfRotation = 0 0 0\n\
+                 AnatomicalOrientation = PLS\n\
+                 ElementSpacing = 0.402344 0.402344 0.899994\n\
+                 DimSize = 512 512 333\n\
+                 HeaderSize = -1\n\
+                 ElementType = MET_SHORT\n\
+                 ElementDataFile = scan.zraw\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    let header = metadata.to_metaimage_header();
+    assert_eq!(header, input.replace("CenterOfRotation = 0 0 0\n", ""));
+    assert_eq!(VolumeMd::from_buffer(&header), Ok(metadata.clone()));
+
+    // Defaults, with the data following the header.
+    let metadata = VolumeMd::new(2, 3, 4);
+    let header = metadata.to_metaimage_header();
+    assert!(header.ends_with("ElementDataFile = LOCAL\n"));
+    assert!(!header.contains("CompressedDataSize"));
+    assert!(!header.contains("HeaderSize"));
+    assert_eq!(
+      VolumeMd::from_buffer(&header),
+      Ok(metadata.with_data_file(Some(String::from("LOCAL"))))
+    );
+
+    // A list of data files.
+    let (metadata, _) =
+      VolumeMd::from_bytes(b"DimSize = 1 1 2\nElementDataFile = LIST\na.raw\nb.raw\n").unwrap();
+    let (parsed, _) = VolumeMd::from_bytes(metadata.to_metaimage_header().as_bytes()).unwrap();
+    assert_eq!(parsed, metadata);
+    assert_eq!(parsed.data_file_list(), ["a.raw", "b.raw"]);
+
+    // A list of data files converted to a single `.mha` file.
+    let metadata = metadata.with_data_file(Some(String::from("LOCAL")));
+    assert!(metadata.data_file_list().is_empty());
+    let mut mha = metadata.to_metaimage_header().into_bytes();
+    mha.extend_from_slice(&[1, 0, 2, 0]);
+    let (parsed, local) = VolumeMd::from_bytes(&mha).unwrap();
+    assert_eq!(parsed, metadata);
+    assert_eq!(parsed.decode_data(local).unwrap().as_ref(), [1, 0, 2, 0]);
+  }
+
+  #[test]
+  fn encode_metaimage_data() {
+    let metadata = VolumeMd::new(2, 1, 1).with_element_type(ElementType::UChar);
+    assert_eq!(metadata.encode_metaimage_data(&[1, 2]), [1, 2]);
+
+    let metadata = metadata.with_compressed(true);
+    let encoded = metadata.encode_metaimage_data(&[1, 2]);
+    assert_ne!(encoded, [1, 2]);
+    assert_eq!(metadata.decode_data(&encoded).unwrap().as_ref(), [1, 2]);
+  }
+
+  #[test]
+  fn decode_compressed_metaimage_data() {
+    let metadata =
+      VolumeMd::new(2, 1, 1).with_element_type(ElementType::UChar).with_compressed(true);
+
+    // The header precedes the compressed data.
+    let mut bytes = vec![9, 9];
+    bytes.extend(metadata.encode_metaimage_data(&[1, 2]));
+    let compressed_size = bytes.len() - 2;
+    let metadata = metadata.with_header_size(HeaderSize::Bytes(2));
+    assert_eq!(metadata.decode_data(&bytes).unwrap().as_ref(), [1, 2]);
+
+    bytes.extend_from_slice(&[7, 7, 7]);
+    let metadata = metadata.with_compressed_data_size(Some(compressed_size));
+    assert_eq!(metadata.decode_data(&bytes).unwrap().as_ref(), [1, 2]);
+
+    let metadata = metadata.with_header_size(HeaderSize::Auto);
+    bytes.truncate(compressed_size + 2);
+    assert_eq!(metadata.decode_data(&bytes).unwrap().as_ref(), [1, 2]);
+
+    // Decompression stops one byte after the expected size.
+    let metadata = metadata.with_header_size(HeaderSize::Bytes(0)).with_compressed_data_size(None);
+    let bytes = metadata.encode_metaimage_data(&[0; 1 << 20]);
+    assert_eq!(metadata.decode_data(&bytes), Err(MedvizErr::new_data_size_mismatch(3, 2)));
+  }
+
+  #[test]
+  fn frame_index() {
+    let input = "DimSize = 512 512 333\n\
+                 ElementSpacing = 0.5 0.5 2\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+    assert_eq!(metadata.frame_index(Axis::X, FramePosition::Index(511)), Ok(511));
+    assert_eq!(metadata.frame_index(Axis::X, FramePosition::Percent(50.0)), Ok(256));
+    assert_eq!(metadata.frame_index(Axis::Z, FramePosition::Percent(50.0)), Ok(166));
+    assert_eq!(metadata.frame_index(Axis::Z, FramePosition::Percent(0.0)), Ok(0));
+    assert_eq!(metadata.frame_index(Axis::Z, FramePosition::Percent(100.0)), Ok(332));
+    assert_eq!(metadata.frame_index(Axis::Y, FramePosition::Millimetres(10.0)), Ok(20));
+    assert_eq!(metadata.frame_index(Axis::Z, FramePosition::Millimetres(10.0)), Ok(5));
+  }
+
+  #[test]
+  fn orientation_from_transform() {
+    let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    assert_eq!(AnatomicalOrientation::from_transform(identity), Some(AnatomicalOrientation::RAI));
+
+    // Sagittal acquisition, slightly tilted.
+    let sagittal = [0.0, 1.0, 0.1, 0.0, 0.0, -1.0, -1.0, 0.0, 0.0];
+    assert_eq!(
+      AnatomicalOrientation::from_transform(sagittal),
+      AnatomicalOrientation::from_code("ASL")
+    );
+
+    let degenerate = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+    assert_eq!(AnatomicalOrientation::from_transform(degenerate), None);
+  }
+
+  #[test]
+  fn frame_index_out_of_range() {
+    let input = "DimSize = 512 512 333\n\
+                 ElementSpacing = 0.5 0.5 2\n";
+    let metadata = VolumeMd::from_buffer(input).unwrap();
+
+    let err = metadata.frame_index(Axis::Z, FramePosition::Index(333));
+    let expected = |position: &str| {
+      Err(MedvizErr::FramePositionOutOfRange {
+        axis: Axis::Z,
+        position: String::from(position),
+        len: 333,
+      })
+    };
+    assert_eq!(err, expected("333"));
+
+    let err = metadata.frame_index(Axis::Z, FramePosition::Percent(100.5));
+    assert_eq!(err, expected("100.5%"));
+
+    let err = metadata.frame_index(Axis::Z, FramePosition::Percent(-1.0));
+    assert_eq!(err, expected("-1%"));
+
+    let err = metadata.frame_index(Axis::Z, FramePosition::Millimetres(666.0));
+    assert_eq!(err, expected("666mm"));
+
+    let err = metadata.frame_index(Axis::Z, FramePosition::Millimetres(-2.0));
+    assert_eq!(err, expected("-2mm"));
+  }
+
   #[test]
   fn from_reader_fail_dimsize_values() {
     let input = "\n\
@@ -246,6 +2314,15 @@ mod volume_metadata_tests {
     assert_eq!(err, Err(MedvizErr::MdDuplicateKey { line_number: 5 }));
   }
 
+  #[test]
+  fn from_reader_fail_duplicate_alias() {
+    let input = "DimSize = 512 512 333\n\
+                 Offset = 0 0 0\n\
+                 Origin = 0 0 0\n";
+    let err = VolumeMd::from_buffer(input);
+    assert_eq!(err, Err(MedvizErr::MdDuplicateKey { line_number: 3 }));
+  }
+
   #[test]
   fn from_reader_fail_key_not_found() {
     let input = "\n\
@@ -265,6 +2342,66 @@ mod volume_metadata_tests {
     assert_eq!(err, Err(MedvizErr::MdTooManyDimSizeValues { line_number: 3 }));
   }
 
+  #[test]
+  fn from_reader_fail_ndims() {
+    let input = "NDims = 2\n\
+                 DimSize = 512 512\n";
+    let err = VolumeMd::from_buffer(input);
+    assert_eq!(err, Err(MedvizErr::MdUnsupportedNDims { line_number: 1, ndims: 2 }));
+  }
+
+  #[test]
+  fn from_reader_fail_spacing_values() {
+    let input = "DimSize = 512 512 333\n\
+                 ElementSpacing = 0.402344 0.402344\n";
+    let err = VolumeMd::from_buffer(input);
+    assert_eq!(err, Err(MedvizErr::MdMissingValues { line_number: 2, key: "ElementSpacing" }));
+
+    let input = "DimSize = 512 512 333\n\
+                 ElementSpacing = 0.402344 0.402344 0.899994 1\n";
+    let err = VolumeMd::from_buffer(input);
+    assert_eq!(err, Err(MedvizErr::MdTooManyValues { line_number: 2, key: "ElementSpacing" }));
+  }
+
+  #[test]
+  fn from_reader_fail_invalid_values() {
+    let err = VolumeMd::from_buffer("DimSize = 1 1 1\nOffset = 0 x 0");
+    assert_eq!(
+      err,
+      Err(MedvizErr::MdInvalidValue { line_number: 2, key: "Offset", value: String::from("x") })
+    );
+
+    let err = VolumeMd::from_buffer("DimSize = 1 1 1\nElementType = MET_FOO");
+    assert_eq!(
+      err,
+      Err(MedvizErr::MdInvalidValue {
+        line_number: 2,
+        key: "ElementType",
+        value: String::from("MET_FOO")
+      })
+    );
+
+    let err = VolumeMd::from_buffer("DimSize = 1 1 1\nCompressedData = Maybe");
+    assert_eq!(
+      err,
+      Err(MedvizErr::MdInvalidValue {
+        line_number: 2,
+        key: "CompressedData",
+        value: String::from("Maybe")
+      })
+    );
+
+    let err = VolumeMd::from_buffer("DimSize = 1 1 1\nAnatomicalOrientation = RLI");
+    assert_eq!(
+      err,
+      Err(MedvizErr::MdInvalidValue {
+        line_number: 2,
+        key: "AnatomicalOrientation",
+        value: String::from("RLI")
+      })
+    );
+  }
+
   #[test]
   fn from_reader_fail_empty() {
     let input = "";
@@ -281,3 +2418,152 @@ mod volume_metadata_tests {
     assert_eq!(err, Err(MedvizErr::MdDimSizeNotFound));
   }
 }
+
+#[cfg(test)]
+mod nrrd_tests {
+  use super::{AnatomicalOrientation, ElementType, HeaderSize, VolumeMd};
+  use crate::MedvizErr;
+  use std::borrow::Cow;
+
+  #[test]
+  fn from_nrrd_attached() {
+    let input = b"NRRD0004\n\
+                  # Complete NRRD file format specification at:\n\
+                  type: short\n\
+                  dimension: 3\n\
+                  space: right-anterior-superior\n\
+                  sizes: 2 1 1\n\
+                  space directions: (-0.5,0,0) (0,-0.5,0) (0,0,2)\n\
+                  kinds: domain domain domain\n\
+                  endian: big\n\
+                  encoding: raw\n\
+                  space origin: (10,20,30)\n\
+                  modality:=CT\n\
+                  \n\
+                  \x00\x01\x00\x02";
+    let (metadata, data) = VolumeMd::from_nrrd(input).unwrap();
+    assert_eq!(metadata.dims(), [2, 1, 1]);
+    assert_eq!(metadata.element_type(), ElementType::Short);
+    assert!(metadata.byte_order_msb());
+    assert!(!metadata.compressed());
+    assert_eq!(metadata.spacing(), [0.5, 0.5, 2.0]);
+    assert_eq!(metadata.offset(), [-10.0, -20.0, 30.0]);
+    assert_eq!(metadata.transform(), [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(metadata.orientation(), Some(AnatomicalOrientation::RAI));
+    assert_eq!(metadata.data_file(), None);
+    assert_eq!(data, b"\x00\x01\x00\x02");
+    assert_eq!(metadata.decode_data(data), Ok(Cow::Borrowed(data)));
+  }
+
+  #[test]
+  fn from_nrrd_detached() {
+    let input = b"NRRD0004\n\
+                  type: float\n\
+                  dimension: 3\n\
+                  sizes: 4 5 6\n\
+                  spacings: 0.5 0.5 -3\n\
+                  encoding: gzip\n\
+                  byte skip: -1\n\
+                  data file: scan.raw.gz\n";
+    let (metadata, data) = VolumeMd::from_nrrd(input).unwrap();
+    assert_eq!(metadata.dims(), [4, 5, 6]);
+    assert_eq!(metadata.element_type(), ElementType::Float);
+    assert_eq!(metadata.spacing(), [0.5, 0.5, 3.0]);
+    assert_eq!(metadata.orientation(), None);
+    assert!(metadata.compressed());
+    assert_eq!(metadata.header_size(), HeaderSize::Auto);
+    assert_eq!(metadata.data_file(), Some("scan.raw.gz"));
+    assert!(data.is_empty());
+  }
+
+  #[test]
+  fn nrrd_round_trip() {
+    let metadata = VolumeMd::new(2, 2, 1)
+      .with_element_type(ElementType::UChar)
+      .with_spacing([0.5, 0.75, 2.0])
+      .with_offset([-1.5, 2.0, 3.25])
+      .with_transform([0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0])
+      .with_orientation(AnatomicalOrientation::from_code("ALI"))
+      .with_compressed(true);
+
+    let mut file = metadata.to_nrrd_header().into_bytes();
+    file.extend(metadata.encode_nrrd_data(&[1, 2, 3, 4]));
+
+    let (read, data) = VolumeMd::from_nrrd(&file).unwrap();
+    assert_eq!(read, metadata);
+    assert_eq!(read.decode_data(data).unwrap().as_ref(), [1, 2, 3, 4]);
+
+    let metadata = metadata.with_data_file(Some(String::from("scan.raw"))).with_compressed(false);
+    let header = metadata.to_nrrd_header();
+    let (read, data) = VolumeMd::from_nrrd(header.as_bytes()).unwrap();
+    assert_eq!(read, metadata);
+    assert!(data.is_empty());
+  }
+
+  #[test]
+  fn decode_data() {
+    let metadata = VolumeMd::new(2, 1, 1).with_element_type(ElementType::UChar);
+    assert_eq!(
+      metadata
+        .clone()
+        .with_header_size(HeaderSize::Bytes(1))
+        .decode_data(&[0, 1, 2])
+        .unwrap()
+        .as_ref(),
+      [1, 2]
+    );
+    assert_eq!(
+      metadata
+        .clone()
+        .with_header_size(HeaderSize::Auto)
+        .decode_data(&[0, 0, 1, 2])
+        .unwrap()
+        .as_ref(),
+      [1, 2]
+    );
+    assert_eq!(metadata.decode_data(&[0, 1, 2]), Err(MedvizErr::new_data_size_mismatch(3, 2)));
+  }
+
+  #[test]
+  fn decode_compressed_nrrd_data() {
+    // The byte skip is applied to the decompressed data.
+    let input = b"NRRD0004\n\
+                  type: uchar\n\
+                  sizes: 2 1 1\n\
+                  encoding: gzip\n\
+                  byte skip: 1\n\
+                  \n";
+    let (metadata, _) = VolumeMd::from_nrrd(input).unwrap();
+    let data = crate::compression::gzip(&[9, 1, 2]);
+    assert_eq!(metadata.decode_data(&data).unwrap().as_ref(), [1, 2]);
+
+    let data = crate::compression::gzip(&[0; 1 << 20]);
+    assert_eq!(metadata.decode_data(&data), Err(MedvizErr::new_data_size_mismatch(3, 2)));
+  }
+
+  #[test]
+  fn from_nrrd_fail() {
+    assert_eq!(VolumeMd::from_nrrd(b"DimSize = 1 2 3\n"), Err(MedvizErr::NrrdInvalidMagic));
+    assert_eq!(
+      VolumeMd::from_nrrd(b"NRRD0004\ntype: block\n"),
+      Err(MedvizErr::new_md_invalid_value(2, "type", "block".into()))
+    );
+    assert_eq!(
+      VolumeMd::from_nrrd(b"NRRD0004\nencoding: bzip2\n"),
+      Err(MedvizErr::new_md_invalid_value(2, "encoding", "bzip2".into()))
+    );
+    assert_eq!(
+      VolumeMd::from_nrrd(b"NRRD0004\ndimension: 4\n"),
+      Err(MedvizErr::new_md_unsupported_n_dims(2, 4))
+    );
+    assert_eq!(
+      VolumeMd::from_nrrd(b"NRRD0004\nsizes: 1 1 1\nspace directions: (1,0,0) (0,1,0)\n"),
+      Err(MedvizErr::new_md_invalid_value(3, "space directions", "(1,0,0) (0,1,0)".into()))
+    );
+    assert_eq!(
+      VolumeMd::from_nrrd(b"NRRD0004\nsizes: 1 1 1\nsizes: 1 1 1\n"),
+      Err(MedvizErr::new_md_duplicate_key(3))
+    );
+    assert_eq!(VolumeMd::from_nrrd(b"NRRD0004\ntype: float\n"), Err(MedvizErr::MdDimSizeNotFound));
+  }
+}
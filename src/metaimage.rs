@@ -0,0 +1,163 @@
+//! Handles MetaImage files. The primary functions load an
+//! [image](Image) from a [MetaImage file](from_file), optionally
+//! [with another data file](from_files).
+
+use crate::metadata::DataFile;
+use crate::Image;
+use crate::MedvizErr;
+use crate::VolumeMd;
+use std::fs;
+use std::path::Path;
+
+/// Load an [image](Image) from a MetaImage header (`.mhd`) and its
+/// data files, or from a single-file MetaImage (`.mha`).
+///
+/// # Arguments
+///
+/// * `path` - The path of the MetaImage file. Data files are resolved
+///   relative to its directory.
+///
+/// # Returns
+///
+/// An [image](Image) or [an error](Err) in case a file cannot be
+/// read, the metadata is invalid, decompression fails or the size of
+/// the data does not match the metadata.
+///
+/// # Notes
+///
+/// Compressed data (`CompressedData = True`) is inflated with zlib,
+/// limited to `CompressedDataSize` bytes when given. Uncompressed data
+/// files are mapped into memory.
+pub fn from_file(path: &Path) -> Result<Image, MedvizErr> {
+  load(path, None)
+}
+
+/// Load an [image](Image) from a MetaImage header, with its data in
+/// another file than the one named by the header.
+///
+/// # Arguments
+///
+/// * `path` - The path of the MetaImage header.
+///
+/// * `data_file` - The path of the data file.
+///
+/// # Returns
+///
+/// An [image](Image) or [an error](Err), as for
+/// [`from_file`].
+pub fn from_files(path: &Path, data_file: &Path) -> Result<Image, MedvizErr> {
+  load(path, Some(data_file))
+}
+
+/// Load an [image](Image) from a MetaImage file, with its data in
+/// `data_file` if given.
+fn load(path: &Path, data_file: Option<&Path>) -> Result<Image, MedvizErr> {
+  let bytes =
+    fs::read(path).map_err(|e| MedvizErr::new_io(path.display().to_string(), e.to_string()))?;
+  let (metadata, local) = VolumeMd::from_bytes(&bytes)?;
+
+  let data_file = match data_file {
+    Some(data_file) => DataFile::Single(data_file.to_path_buf()),
+    None => metadata.resolve_data_file(path.parent().unwrap_or_else(|| Path::new("")))?,
+  };
+
+  Image::read(metadata, &data_file, local)
+}
+
+#[cfg(test)]
+mod metaimage_tests {
+  use super::{from_file, from_files};
+  use crate::compression;
+  use crate::metadata::ElementType;
+  use crate::{Image, MedvizErr, VolumeMd};
+  use std::fs;
+
+  #[test]
+  fn from_file_compressed() {
+    let dir = std::env::temp_dir().join(format!("medviz-metaimage-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let compressed = compression::deflate(&[1, 0, 2, 0, 3, 0, 4, 0]);
+    let header = format!(
+      "NDims = 3\n\
+       DimSize = 2 2 1\n\
+       ElementType = MET_USHORT\n\
+       CompressedData = True\n\
+       CompressedDataSize = {}\n",
+      compressed.len()
+    );
+
+    // A single `.mha` file, with trailing bytes after the compressed
+    // data.
+    let mut mha = format!("{}ElementDataFile = LOCAL\n", header).into_bytes();
+    mha.extend_from_slice(&compressed);
+    mha.extend_from_slice(b"trailing");
+    fs::write(dir.join("scan.mha"), mha).unwrap();
+
+    let image = from_file(&dir.join("scan.mha")).unwrap();
+    assert_eq!(image.metadata().element_type(), ElementType::UShort);
+    assert_eq!(image.metadata().compressed_data_size(), Some(compressed.len()));
+    assert_eq!(image.data(), [1, 0, 2, 0, 3, 0, 4, 0]);
+    assert_eq!(image.volume().unwrap().get(1, 1, 0).unwrap().value(), 4.0);
+
+    // A `.mhd` header and a `.zraw` data file next to it.
+    fs::write(dir.join("scan.mhd"), format!("{}ElementDataFile = scan.zraw\n", header)).unwrap();
+    fs::write(dir.join("scan.zraw"), &compressed).unwrap();
+    assert_eq!(from_file(&dir.join("scan.mhd")).unwrap().data(), image.data());
+
+    // The data is truncated.
+    fs::write(dir.join("scan.zraw"), &compressed[..compressed.len() - 1]).unwrap();
+    assert_eq!(
+      from_file(&dir.join("scan.mhd")),
+      Err(MedvizErr::new_data_size_mismatch(compressed.len() - 1, compressed.len()))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn write_round_trip() {
+    let dir = std::env::temp_dir().join(format!("medviz-metaimage-write-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let data = [1, 0, 2, 0, 3, 0, 4, 0];
+    let image = Image::new(
+      VolumeMd::new(2, 2, 1)
+        .with_element_type(ElementType::Short)
+        .with_spacing([0.5, 0.5, 2.0])
+        .with_offset([-10.0, 20.0, 0.25])
+        .with_data_file(Some(String::from("LOCAL"))),
+      data.to_vec(),
+    );
+
+    for compressed in [false, true] {
+      let metadata = image.metadata().clone().with_compressed(compressed);
+      let encoded = metadata.encode_metaimage_data(&data);
+      let metadata = metadata.with_compressed_data_size(compressed.then_some(encoded.len()));
+
+      let mut raw = Vec::new();
+      image.volume().unwrap().write_raw(&mut raw).unwrap();
+      assert_eq!(raw, data);
+
+      let mut mha = metadata.to_metaimage_header().into_bytes();
+      mha.extend_from_slice(&encoded);
+      fs::write(dir.join("scan.mha"), mha).unwrap();
+
+      let read = from_file(&dir.join("scan.mha")).unwrap();
+      assert_eq!(read.metadata(), &metadata);
+      assert_eq!(read.data(), data);
+    }
+
+    // A header whose data is in another file than the one it names.
+    let metadata = image.metadata().clone().with_data_file(Some(String::from("missing.raw")));
+    fs::write(dir.join("scan.mhd"), metadata.to_metaimage_header()).unwrap();
+    fs::write(dir.join("other.raw"), data).unwrap();
+    assert!(from_file(&dir.join("scan.mhd")).is_err());
+    assert_eq!(
+      from_files(&dir.join("scan.mhd"), &dir.join("other.raw")),
+      Ok(Image::new(metadata, data.to_vec()))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
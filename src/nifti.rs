@@ -0,0 +1,619 @@
+//! Handles NIfTI-1 and NIfTI-2 files. The primary function loads an
+//! [image](Image) [from the contents of a file](from_bytes).
+
+use crate::compression;
+use crate::metadata::{AnatomicalOrientation, ElementType};
+use crate::vector::{self, Vector};
+use crate::voxel;
+use crate::Image;
+use crate::MedvizErr;
+use crate::VolumeMd;
+use log::{debug, warn};
+use std::convert::TryFrom;
+use std::ops::Range;
+
+/// Size of a NIfTI-1 header in bytes.
+const NIFTI1_HEADER_SIZE: usize = 348;
+
+/// Size of a NIfTI-2 header in bytes.
+const NIFTI2_HEADER_SIZE: usize = 540;
+
+/// Load an [image](Image) from the contents of a NIfTI-1 or NIfTI-2
+/// file (`.nii`), optionally gzip-compressed (`.nii.gz`).
+///
+/// # Arguments
+///
+/// * `bytes` - The contents of the file, which is decompressed
+///   first if it starts like gzip data.
+///
+/// # Returns
+///
+/// The [image](Image) or [an error](Err) in case the header
+/// is invalid, the datatype is not supported or the file is too
+/// short for the voxels described by the header.
+///
+/// # Notes
+///
+/// The header is mapped onto [volume metadata](VolumeMd) as
+/// follows:
+///
+/// * `dim` gives the dimensions of the volume. Files with more than
+///   3 dimensions (e.g. time series) are reduced to their first
+///   volume.
+///
+/// * `datatype` gives the element type. Complex and RGB datatypes
+///   are not supported.
+///
+/// * The `sform` affine is used if `sform_code` is set, otherwise
+///   the `qform` quaternion if `qform_code` is set, otherwise
+///   `pixdim` alone. The affine is split into spacing, offset and
+///   transform, converted from the RAS+ world of NIfTI to the LPS+
+///   world of MetaImage.
+///
+/// * When `scl_slope` is set and not the identity, the voxels are
+///   rescaled to `value * scl_slope + scl_inter` and stored as
+///   `MET_FLOAT` (`MET_DOUBLE` for double voxels).
+pub fn from_bytes(bytes: &[u8]) -> Result<Image, MedvizErr> {
+  if compression::is_gzip(bytes) {
+    // The size of the image is only known from its header, so the
+    // header is decompressed first to bound the decompression.
+    let header = compression::gunzip(bytes, NIFTI2_HEADER_SIZE)?;
+    let end = Header::parse(&header)?.layout()?.data.end;
+    let bytes = compression::gunzip(bytes, end)?;
+    debug!("Decompressed {} bytes of NIfTI data", bytes.len());
+    return from_uncompressed(&bytes);
+  }
+
+  from_uncompressed(bytes)
+}
+
+/// Load an [image](Image) from uncompressed NIfTI contents.
+fn from_uncompressed(bytes: &[u8]) -> Result<Image, MedvizErr> {
+  let header = Header::parse(bytes)?;
+
+  let Layout { dims, volumes, element_type, data } = header.layout()?;
+  if volumes > 1 {
+    warn!("NIfTI image contains {} volumes, using the first one", volumes);
+  }
+
+  if data.end > bytes.len() {
+    let available = bytes.len().saturating_sub(data.start);
+    return Err(MedvizErr::new_data_size_mismatch(available, data.len()));
+  }
+
+  let mut data = bytes[data].to_vec();
+
+  let (spacing, offset, transform) = header.geometry();
+  let mut metadata = VolumeMd::new(dims[0], dims[1], dims[2])
+    .with_element_type(element_type)
+    .with_byte_order_msb(header.msb)
+    .with_spacing(spacing)
+    .with_offset(offset)
+    .with_transform(transform)
+    .with_orientation(AnatomicalOrientation::from_transform(transform));
+
+  let (slope, intercept) = (header.scl_slope, header.scl_inter);
+  if slope != 0.0 && slope.is_finite() && intercept.is_finite() && (slope, intercept) != (1.0, 0.0)
+  {
+    debug!("Rescaling NIfTI voxels with slope {} and intercept {}", slope, intercept);
+    let (element_type, rescaled) =
+      voxel::rescale(element_type, header.msb, &data, slope, intercept);
+    metadata = metadata.with_element_type(element_type).with_byte_order_msb(false);
+    data = rescaled;
+  }
+
+  Ok(Image::new(metadata, data))
+}
+
+/// The fields of a NIfTI-1 or NIfTI-2 header used by the library.
+#[derive(Debug)]
+struct Header {
+  /// Whether the header and voxels are big-endian.
+  msb: bool,
+
+  /// Number of dimensions followed by the size of each dimension.
+  dim: [i64; 8],
+
+  /// Type of the voxels.
+  datatype: i32,
+
+  /// `qfac` followed by the spacing of each dimension.
+  pixdim: [f64; 8],
+
+  /// Byte offset of the voxels in the file.
+  vox_offset: usize,
+
+  /// Slope applied to voxel values.
+  scl_slope: f64,
+
+  /// Intercept applied to voxel values.
+  scl_inter: f64,
+
+  /// Whether the quaternion transform is set.
+  qform_code: i32,
+
+  /// Whether the affine transform is set.
+  sform_code: i32,
+
+  /// The b, c and d parameters of the quaternion.
+  quatern: Vector,
+
+  /// The translation of the quaternion transform.
+  qoffset: Vector,
+
+  /// The rows of the affine transform.
+  srow: [[f64; 4]; 3],
+}
+
+/// Where the first volume of a NIfTI image is stored.
+struct Layout {
+  /// The dimensions of the first volume.
+  dims: [usize; 3],
+
+  /// The number of volumes in the image.
+  volumes: i64,
+
+  /// Type of the voxels.
+  element_type: ElementType,
+
+  /// The range of the voxels of the first volume in the file.
+  data: Range<usize>,
+}
+
+impl Header {
+  /// Parse a NIfTI-1 or NIfTI-2 header, detecting the version and
+  /// byte order from the `sizeof_hdr` field.
+  fn parse(bytes: &[u8]) -> Result<Self, MedvizErr> {
+    let sizeof_hdr = match bytes.get(..4) {
+      Some(sizeof_hdr) => [sizeof_hdr[0], sizeof_hdr[1], sizeof_hdr[2], sizeof_hdr[3]],
+      None => return Err(MedvizErr::new_nifti_invalid_header("file too short")),
+    };
+
+    let (size, msb) = [false, true]
+      .iter()
+      .find_map(|&msb| {
+        let size =
+          if msb { i32::from_be_bytes(sizeof_hdr) } else { i32::from_le_bytes(sizeof_hdr) };
+        match usize::try_from(size) {
+          Ok(size @ (NIFTI1_HEADER_SIZE | NIFTI2_HEADER_SIZE)) => Some((size, msb)),
+          _ => None,
+        }
+      })
+      .ok_or_else(|| MedvizErr::new_nifti_invalid_header("unknown header size"))?;
+
+    if bytes.len() < size {
+      return Err(MedvizErr::new_nifti_invalid_header("truncated header"));
+    }
+
+    let fields = Fields { bytes, msb };
+
+    if size == NIFTI1_HEADER_SIZE {
+      Self::parse_nifti1(&fields)
+    } else {
+      Self::parse_nifti2(&fields)
+    }
+  }
+
+  /// Parse the fields of a NIfTI-1 header.
+  fn parse_nifti1(fields: &Fields) -> Result<Self, MedvizErr> {
+    check_magic(&fields.bytes[344..348], b"n+1\0", b"ni1\0")?;
+
+    let vox_offset = fields.f32(108);
+    if vox_offset.fract() != 0.0 || vox_offset < NIFTI1_HEADER_SIZE as f64 {
+      return Err(MedvizErr::new_nifti_invalid_header("invalid voxel offset"));
+    }
+
+    Ok(Self {
+      msb: fields.msb,
+      dim: array(|i| i64::from(fields.i16(40 + i * 2))),
+      datatype: i32::from(fields.i16(70)),
+      pixdim: array(|i| fields.f32(76 + i * 4)),
+      vox_offset: vox_offset as usize,
+      scl_slope: fields.f32(112),
+      scl_inter: fields.f32(116),
+      qform_code: i32::from(fields.i16(252)),
+      sform_code: i32::from(fields.i16(254)),
+      quatern: array(|i| fields.f32(256 + i * 4)),
+      qoffset: array(|i| fields.f32(268 + i * 4)),
+      srow: array(|row| array(|i| fields.f32(280 + row * 16 + i * 4))),
+    })
+  }
+
+  /// Parse the fields of a NIfTI-2 header.
+  fn parse_nifti2(fields: &Fields) -> Result<Self, MedvizErr> {
+    check_magic(&fields.bytes[4..8], b"n+2\0", b"ni2\0")?;
+
+    let vox_offset = match usize::try_from(fields.i64(168)) {
+      Ok(vox_offset) if vox_offset >= NIFTI2_HEADER_SIZE => vox_offset,
+      _ => return Err(MedvizErr::new_nifti_invalid_header("invalid voxel offset")),
+    };
+
+    Ok(Self {
+      msb: fields.msb,
+      dim: array(|i| fields.i64(16 + i * 8)),
+      datatype: i32::from(fields.i16(12)),
+      pixdim: array(|i| fields.f64(104 + i * 8)),
+      vox_offset,
+      scl_slope: fields.f64(176),
+      scl_inter: fields.f64(184),
+      qform_code: fields.i32(344),
+      sform_code: fields.i32(348),
+      quatern: array(|i| fields.f64(352 + i * 8)),
+      qoffset: array(|i| fields.f64(376 + i * 8)),
+      srow: array(|row| array(|i| fields.f64(400 + row * 32 + i * 8))),
+    })
+  }
+
+  /// The affine mapping voxel indexes to RAS+ world coordinates,
+  /// given by its rows.
+  fn affine(&self) -> [[f64; 4]; 3] {
+    if self.sform_code > 0 {
+      return self.srow;
+    }
+
+    let spacing: Vector = array(|i| self.pixdim[i + 1]);
+
+    if self.qform_code <= 0 {
+      return array(|row| array(|i| if i == row { spacing[i].abs() } else { 0.0 }));
+    }
+
+    let [b, c, d] = self.quatern;
+    let a = (1.0 - (b * b + c * c + d * d)).max(0.0).sqrt();
+    let qfac = if self.pixdim[0] < 0.0 { -1.0 } else { 1.0 };
+
+    let rotation = [
+      [a * a + b * b - c * c - d * d, 2.0 * (b * c - a * d), 2.0 * (b * d + a * c)],
+      [2.0 * (b * c + a * d), a * a + c * c - b * b - d * d, 2.0 * (c * d - a * b)],
+      [2.0 * (b * d - a * c), 2.0 * (c * d + a * b), a * a + d * d - c * c - b * b],
+    ];
+
+    let scale = [spacing[0], spacing[1], spacing[2] * qfac];
+    array(|row| array(|i| if i < 3 { rotation[row][i] * scale[i] } else { self.qoffset[row] }))
+  }
+
+  /// Find the voxels of the first volume described by the header.
+  fn layout(&self) -> Result<Layout, MedvizErr> {
+    let ndims = self.dim[0];
+    if !(1..=7).contains(&ndims) {
+      return Err(MedvizErr::new_nifti_invalid_header("invalid number of dimensions"));
+    }
+
+    let mut dims = [1; 3];
+    for (axis, dim) in dims.iter_mut().enumerate().take(ndims as usize) {
+      *dim = match usize::try_from(self.dim[axis + 1]) {
+        Ok(value) if value > 0 => value,
+        _ => return Err(MedvizErr::new_nifti_invalid_header("invalid dimension size")),
+      };
+    }
+
+    let too_large = || MedvizErr::new_nifti_invalid_header("volume too large");
+
+    let volumes = self.dim[4..=ndims.max(3) as usize]
+      .iter()
+      .try_fold(1i64, |volumes, dim| volumes.checked_mul(*dim))
+      .ok_or_else(too_large)?;
+
+    let element_type = element_type(self.datatype)?;
+
+    let start = self.vox_offset;
+    let size = dims
+      .iter()
+      .try_fold(element_type.size(), |size, dim| size.checked_mul(*dim))
+      .ok_or_else(too_large)?;
+    let end = start.checked_add(size).ok_or_else(too_large)?;
+
+    Ok(Layout { dims, volumes, element_type, data: start..end })
+  }
+
+  /// The spacing, offset and transform of the volume in LPS+ world
+  /// coordinates.
+  fn geometry(&self) -> ([f64; 3], [f64; 3], [f64; 9]) {
+    // RAS+ to LPS+ negates the first two world coordinates.
+    let lps = |row: usize, value: f64| if row < 2 { -value } else { value };
+    let affine = self.affine();
+    let column = |i: usize| -> Vector { array(|row| lps(row, affine[row][i])) };
+
+    let mut spacing = [1.0; 3];
+    let mut transform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+    for axis in 0..3 {
+      let column = column(axis);
+      if let Some(direction) = vector::normalize(column) {
+        spacing[axis] = vector::norm(column);
+        transform[axis * 3..axis * 3 + 3].copy_from_slice(&direction);
+      }
+    }
+
+    (spacing, column(3), transform)
+  }
+}
+
+/// Reads header fields of a given byte order.
+struct Fields<'b> {
+  /// The header bytes.
+  bytes: &'b [u8],
+
+  /// Whether the fields are big-endian.
+  msb: bool,
+}
+
+impl Fields<'_> {
+  /// Read `N` bytes at `offset`, in little-endian order.
+  fn read<const N: usize>(&self, offset: usize) -> [u8; N] {
+    let mut res = [0; N];
+    res.copy_from_slice(&self.bytes[offset..offset + N]);
+    if self.msb {
+      res.reverse();
+    }
+    res
+  }
+
+  /// Read a 16-bit integer at `offset`.
+  fn i16(&self, offset: usize) -> i16 {
+    i16::from_le_bytes(self.read(offset))
+  }
+
+  /// Read a 32-bit integer at `offset`.
+  fn i32(&self, offset: usize) -> i32 {
+    i32::from_le_bytes(self.read(offset))
+  }
+
+  /// Read a 64-bit integer at `offset`.
+  fn i64(&self, offset: usize) -> i64 {
+    i64::from_le_bytes(self.read(offset))
+  }
+
+  /// Read a 32-bit float at `offset`.
+  fn f32(&self, offset: usize) -> f64 {
+    f64::from(f32::from_le_bytes(self.read(offset)))
+  }
+
+  /// Read a 64-bit float at `offset`.
+  fn f64(&self, offset: usize) -> f64 {
+    f64::from_le_bytes(self.read(offset))
+  }
+}
+
+/// Build an array from a function of the index.
+fn array<T, const N: usize>(f: impl FnMut(usize) -> T) -> [T; N] {
+  std::array::from_fn(f)
+}
+
+/// Check the magic string of a header.
+///
+/// # Arguments
+///
+/// * `magic` - The magic string found in the header.
+///
+/// * `single` - The magic string of single-file images (`.nii`).
+///
+/// * `pair` - The magic string of images split into a header and a
+///   data file (`.hdr` and `.img`), which are not supported.
+fn check_magic(magic: &[u8], single: &[u8], pair: &[u8]) -> Result<(), MedvizErr> {
+  if magic == single {
+    Ok(())
+  } else if magic == pair {
+    Err(MedvizErr::new_nifti_invalid_header("separate .hdr and .img files are not supported"))
+  } else {
+    Err(MedvizErr::new_nifti_invalid_header("invalid magic"))
+  }
+}
+
+/// The element type of a NIfTI datatype code.
+fn element_type(datatype: i32) -> Result<ElementType, MedvizErr> {
+  match datatype {
+    2 => Ok(ElementType::UChar),
+    4 => Ok(ElementType::Short),
+    8 => Ok(ElementType::Int),
+    16 => Ok(ElementType::Float),
+    64 => Ok(ElementType::Double),
+    256 => Ok(ElementType::Char),
+    512 => Ok(ElementType::UShort),
+    768 => Ok(ElementType::UInt),
+    1024 => Ok(ElementType::LongLong),
+    1280 => Ok(ElementType::ULongLong),
+    _ => Err(MedvizErr::new_nifti_unsupported_datatype(datatype)),
+  }
+}
+
+#[cfg(test)]
+mod nifti_tests {
+  use super::{from_bytes, NIFTI1_HEADER_SIZE, NIFTI2_HEADER_SIZE};
+  use crate::metadata::{AnatomicalOrientation, ElementType};
+  use crate::{MedvizErr, Voxel};
+  use flate2::write::GzEncoder;
+  use flate2::Compression;
+  use std::io::Write;
+
+  /// Write a value at `offset`, in little- or big-endian order.
+  fn put<const N: usize>(buffer: &mut [u8], offset: usize, msb: bool, mut bytes: [u8; N]) {
+    if msb {
+      bytes.reverse();
+    }
+    buffer[offset..offset + N].copy_from_slice(&bytes);
+  }
+
+  /// A 2x3x2 NIfTI-1 image of 16-bit voxels numbered from 0, with 4
+  /// bytes of extension flags between the header and the voxels.
+  fn nifti1(msb: bool) -> Vec<u8> {
+    let mut res = vec![0; NIFTI1_HEADER_SIZE + 4];
+    put(&mut res, 0, msb, 348i32.to_le_bytes());
+    for (i, dim) in [3i16, 2, 3, 2, 1, 1, 1, 1].iter().enumerate() {
+      put(&mut res, 40 + i * 2, msb, dim.to_le_bytes());
+    }
+    put(&mut res, 70, msb, 4i16.to_le_bytes());
+    put(&mut res, 72, msb, 16i16.to_le_bytes());
+    for (i, pixdim) in [1.0f32, 0.5, 0.75, 2.0].iter().enumerate() {
+      put(&mut res, 76 + i * 4, msb, pixdim.to_le_bytes());
+    }
+    put(&mut res, 108, msb, 352f32.to_le_bytes());
+    res[344..348].copy_from_slice(b"n+1\0");
+
+    for value in 0..12i16 {
+      let mut bytes = value.to_le_bytes();
+      if msb {
+        bytes.reverse();
+      }
+      res.extend_from_slice(&bytes);
+    }
+
+    res
+  }
+
+  /// Set the affine of a NIfTI-1 image.
+  fn set_sform(buffer: &mut [u8], srow: [[f32; 4]; 3]) {
+    put(buffer, 254, false, 1i16.to_le_bytes());
+    for (row, values) in srow.iter().enumerate() {
+      for (i, value) in values.iter().enumerate() {
+        put(buffer, 280 + row * 16 + i * 4, false, value.to_le_bytes());
+      }
+    }
+  }
+
+  #[test]
+  fn nifti1_pixdim() {
+    let nifti = from_bytes(&nifti1(false)).unwrap();
+    let metadata = nifti.metadata();
+    assert_eq!(metadata.dims(), [2, 3, 2]);
+    assert_eq!(metadata.spacing(), [0.5, 0.75, 2.0]);
+    assert_eq!(metadata.offset(), [0.0; 3]);
+    assert_eq!(metadata.element_type(), ElementType::Short);
+    assert!(!metadata.byte_order_msb());
+    // NIfTI indexes increase towards the right, front and head, so the
+    // X- and Y-axis are flipped in MetaImage coordinates.
+    assert_eq!(metadata.transform(), [-1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(metadata.orientation(), AnatomicalOrientation::from_code("LPI"));
+
+    let volume = nifti.volume().unwrap();
+    assert_eq!(volume.get(1, 2, 1), Ok(Voxel::I16(11)));
+  }
+
+  #[test]
+  fn nifti1_big_endian() {
+    let nifti = from_bytes(&nifti1(true)).unwrap();
+    assert!(nifti.metadata().byte_order_msb());
+    assert_eq!(nifti.volume().unwrap().get(1, 0, 0), Ok(Voxel::I16(1)));
+  }
+
+  #[test]
+  fn nifti1_sform() {
+    let mut bytes = nifti1(false);
+    set_sform(
+      &mut bytes,
+      [[-0.5, 0.0, 0.0, 10.0], [0.0, -0.75, 0.0, -20.0], [0.0, 0.0, 2.0, 30.0]],
+    );
+
+    let nifti = from_bytes(&bytes).unwrap();
+    let metadata = nifti.metadata();
+    assert_eq!(metadata.spacing(), [0.5, 0.75, 2.0]);
+    assert_eq!(metadata.offset(), [-10.0, 20.0, 30.0]);
+    assert_eq!(metadata.orientation(), Some(AnatomicalOrientation::RAI));
+    assert_eq!(metadata.index_to_world([1.0, 1.0, 1.0]), [-9.5, 20.75, 32.0]);
+  }
+
+  #[test]
+  fn nifti1_qform() {
+    let mut bytes = nifti1(false);
+    put(&mut bytes, 252, false, 1i16.to_le_bytes());
+    // A rotation of 180 degrees around the Z-axis.
+    put(&mut bytes, 264, false, 1f32.to_le_bytes());
+    put(&mut bytes, 268, false, 5f32.to_le_bytes());
+
+    let nifti = from_bytes(&bytes).unwrap();
+    let metadata = nifti.metadata();
+    assert_eq!(metadata.offset(), [-5.0, 0.0, 0.0]);
+    assert_eq!(metadata.orientation(), Some(AnatomicalOrientation::RAI));
+    assert_eq!(metadata.index_to_world([1.0, 0.0, 1.0]), [-4.5, 0.0, 2.0]);
+  }
+
+  #[test]
+  fn nifti1_rescale() {
+    let mut bytes = nifti1(false);
+    put(&mut bytes, 112, false, 2f32.to_le_bytes());
+    put(&mut bytes, 116, false, (-1024f32).to_le_bytes());
+
+    let nifti = from_bytes(&bytes).unwrap();
+    assert_eq!(nifti.metadata().element_type(), ElementType::Float);
+    assert_eq!(nifti.volume().unwrap().get(1, 2, 1), Ok(Voxel::F32(-1002.0)));
+  }
+
+  #[test]
+  fn nifti1_gzip() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&nifti1(false)).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    assert_eq!(from_bytes(&compressed), from_bytes(&nifti1(false)));
+
+    // Decompression stops after the voxels described by the header.
+    let mut padded = nifti1(false);
+    padded.resize(padded.len() + (1 << 20), 0);
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&padded).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    assert_eq!(from_bytes(&compressed), from_bytes(&nifti1(false)));
+  }
+
+  /// A NIfTI-2 image of 8-bit voxels with the given `dim` field and
+  /// two voxels, 7 and 9, after 4 bytes of extension flags.
+  fn nifti2_image(dim: [i64; 8]) -> Vec<u8> {
+    let mut bytes = vec![0; NIFTI2_HEADER_SIZE + 4];
+    bytes[0..4].copy_from_slice(&540i32.to_le_bytes());
+    bytes[4..12].copy_from_slice(b"n+2\0\r\n\x1a\n");
+    bytes[12..14].copy_from_slice(&2i16.to_le_bytes());
+    for (i, dim) in dim.iter().enumerate() {
+      bytes[16 + i * 8..24 + i * 8].copy_from_slice(&dim.to_le_bytes());
+    }
+    for (i, pixdim) in [1.0f64, 3.0, 1.0, 1.0].iter().enumerate() {
+      bytes[104 + i * 8..112 + i * 8].copy_from_slice(&pixdim.to_le_bytes());
+    }
+    bytes[168..176].copy_from_slice(&544i64.to_le_bytes());
+    bytes.extend_from_slice(&[7, 9]);
+    bytes
+  }
+
+  #[test]
+  fn nifti2_too_large() {
+    let too_large = Err(MedvizErr::NiftiInvalidHeader { reason: "volume too large" });
+    let huge = 1i64 << 32;
+    assert_eq!(from_bytes(&nifti2_image([3, huge, huge, 1, 1, 1, 1, 1])), too_large);
+    assert_eq!(from_bytes(&nifti2_image([3, huge, huge, huge, 1, 1, 1, 1])), too_large);
+    assert_eq!(from_bytes(&nifti2_image([5, 2, 1, 1, huge, huge, 1, 1])), too_large);
+    assert_eq!(
+      from_bytes(&nifti2_image([3, 2, 2, 1, 1, 1, 1, 1])),
+      Err(MedvizErr::new_data_size_mismatch(2, 4))
+    );
+  }
+
+  #[test]
+  fn nifti2() {
+    let nifti = from_bytes(&nifti2_image([3, 2, 1, 1, 1, 1, 1, 1])).unwrap();
+    assert_eq!(nifti.metadata().dims(), [2, 1, 1]);
+    assert_eq!(nifti.metadata().spacing(), [3.0, 1.0, 1.0]);
+    assert_eq!(nifti.metadata().element_type(), ElementType::UChar);
+    assert_eq!(nifti.data(), [7, 9]);
+  }
+
+  #[test]
+  fn fail() {
+    let invalid = |reason| Err(MedvizErr::NiftiInvalidHeader { reason });
+    assert_eq!(from_bytes(&[0; 3]), invalid("file too short"));
+    assert_eq!(from_bytes(&[0; 400]), invalid("unknown header size"));
+    assert_eq!(from_bytes(&nifti1(false)[..300]), invalid("truncated header"));
+
+    let mut bytes = nifti1(false);
+    bytes[344..348].copy_from_slice(b"ni1\0");
+    assert_eq!(from_bytes(&bytes), invalid("separate .hdr and .img files are not supported"));
+
+    let mut bytes = nifti1(false);
+    put(&mut bytes, 70, false, 32i16.to_le_bytes());
+    assert_eq!(from_bytes(&bytes), Err(MedvizErr::NiftiUnsupportedDatatype { datatype: 32 }));
+
+    let bytes = nifti1(false);
+    assert_eq!(
+      from_bytes(&bytes[..bytes.len() - 1]),
+      Err(MedvizErr::DataSizeMismatch { actual: 23, expected: 24 })
+    );
+  }
+}
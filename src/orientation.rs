@@ -0,0 +1,392 @@
+//! Handles the orientation of frames for display. The primary
+//! structure is the [frame orientation struct](FrameOrientation).
+//!
+//! Frames produced by a [volume](crate::Volume) keep the order of the
+//! voxels in the volume: rows go along the first axis of the frame and
+//! columns along the second one, with the Z-axis going up on frames on
+//! the X- and Y-axis. Whether that comes out the right way up depends
+//! on how the scanner laid out the volume, which is described by its
+//! [anatomical orientation](AnatomicalOrientation).
+
+use crate::metadata::{AnatomicalOrientation, Direction};
+use crate::Axis;
+use crate::Frame;
+use crate::MedvizErr;
+use crate::VolumeMd;
+use std::fmt;
+use std::str::FromStr;
+
+/// Which side of the patient is displayed on which side of axial and
+/// coronal frames.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Convention {
+  /// The patient's left on the right of the frame, as if facing the
+  /// patient.
+  Radiological,
+
+  /// The patient's left on the left of the frame, as if looking from
+  /// behind the patient.
+  Neurological,
+}
+
+impl fmt::Display for Convention {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Radiological => f.write_str("radiological"),
+      Self::Neurological => f.write_str("neurological"),
+    }
+  }
+}
+
+impl FromStr for Convention {
+  type Err = MedvizErr;
+
+  /// Parse a convention from its name (e.g. `radiological`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    match text.trim().to_ascii_lowercase().as_str() {
+      "radiological" => Ok(Self::Radiological),
+      "neurological" => Ok(Self::Neurological),
+      _ => Err(MedvizErr::new_invalid_orientation(text.into())),
+    }
+  }
+}
+
+/// A flip of a frame.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Flip {
+  /// Mirror the frame left to right.
+  Horizontal,
+
+  /// Mirror the frame top to bottom.
+  Vertical,
+}
+
+impl FromStr for Flip {
+  type Err = MedvizErr;
+
+  /// Parse a flip from its name or initial (e.g. `horizontal` or `h`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    match text.trim().to_ascii_lowercase().as_str() {
+      "h" | "horizontal" => Ok(Self::Horizontal),
+      "v" | "vertical" => Ok(Self::Vertical),
+      _ => Err(MedvizErr::new_invalid_orientation(text.into())),
+    }
+  }
+}
+
+/// A clockwise rotation of a frame.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rotation {
+  /// A quarter turn.
+  Cw90,
+
+  /// A half turn.
+  Cw180,
+
+  /// Three quarter turns.
+  Cw270,
+}
+
+impl FromStr for Rotation {
+  type Err = MedvizErr;
+
+  /// Parse a rotation from its angle in degrees (e.g. `90`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    match text.trim() {
+      "90" => Ok(Self::Cw90),
+      "180" => Ok(Self::Cw180),
+      "270" => Ok(Self::Cw270),
+      _ => Err(MedvizErr::new_invalid_orientation(text.into())),
+    }
+  }
+}
+
+/// A transformation of a frame made of flips and quarter turns.
+///
+/// The transformation maps a frame onto a new one by first optionally
+/// transposing it (swapping rows and columns), then optionally
+/// mirroring it horizontally and vertically.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FrameOrientation {
+  /// Whether rows and columns are swapped.
+  transpose: bool,
+
+  /// Whether the frame is mirrored left to right.
+  flip_horizontal: bool,
+
+  /// Whether the frame is mirrored top to bottom.
+  flip_vertical: bool,
+}
+
+impl FrameOrientation {
+  /// The transformation leaving frames as they are.
+  pub const IDENTITY: Self =
+    Self { transpose: false, flip_horizontal: false, flip_vertical: false };
+
+  /// Create the transformation that displays a frame according to a
+  /// convention.
+  ///
+  /// Axial frames are displayed with the patient's front at the top,
+  /// coronal frames with the patient's head at the top and sagittal
+  /// frames with the patient's head at the top and front on the left.
+  /// The convention decides on which side the patient's left ends up
+  /// on axial and coronal frames.
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis the frame is on.
+  ///
+  /// * `orientation` - The anatomical orientation of the volume.
+  ///
+  /// * `convention` - The display convention.
+  pub fn from_anatomical(
+    axis: Axis,
+    orientation: AnatomicalOrientation,
+    convention: Convention,
+  ) -> Self {
+    // Each axis of the volume goes away from the side it starts from.
+    let towards = |axis: Axis| match axis {
+      Axis::X => orientation.x().opposite(),
+      Axis::Y => orientation.y().opposite(),
+      Axis::Z => orientation.z().opposite(),
+    };
+
+    // The directions of the columns and rows of the frame as produced
+    // by the volume, where the Z-axis goes up.
+    let (right, down) = match axis {
+      Axis::X => (towards(Axis::Y), towards(Axis::Z).opposite()),
+      Axis::Y => (towards(Axis::X), towards(Axis::Z).opposite()),
+      Axis::Z => (towards(Axis::X), towards(Axis::Y)),
+    };
+
+    let patient_left = match convention {
+      Convention::Radiological => Direction::Left,
+      Convention::Neurological => Direction::Right,
+    };
+
+    // The directions the columns and rows should have.
+    let normal = towards(axis);
+    let (display_right, display_down) = if normal.is_parallel(Direction::Superior) {
+      (patient_left, Direction::Posterior)
+    } else if normal.is_parallel(Direction::Anterior) {
+      (patient_left, Direction::Inferior)
+    } else {
+      (Direction::Posterior, Direction::Inferior)
+    };
+
+    if right.is_parallel(display_right) {
+      Self {
+        transpose: false,
+        flip_horizontal: right != display_right,
+        flip_vertical: down != display_down,
+      }
+    } else {
+      Self {
+        transpose: true,
+        flip_horizontal: down != display_right,
+        flip_vertical: right != display_down,
+      }
+    }
+  }
+
+  /// Create the transformation that displays a frame of a volume
+  /// according to a convention.
+  ///
+  /// Same as [`from_anatomical`](FrameOrientation::from_anatomical),
+  /// using `RAI` for volumes without an anatomical orientation.
+  pub fn from_metadata(metadata: &VolumeMd, axis: Axis, convention: Convention) -> Self {
+    let orientation = metadata.orientation().unwrap_or(AnatomicalOrientation::RAI);
+    Self::from_anatomical(axis, orientation, convention)
+  }
+
+  /// Whether the transformation leaves frames as they are.
+  pub fn is_identity(&self) -> bool {
+    *self == Self::IDENTITY
+  }
+
+  /// Whether the transformation swaps rows and columns.
+  pub fn transposes(&self) -> bool {
+    self.transpose
+  }
+
+  /// The transformation as a matrix mapping column and row directions
+  /// of the original frame to the transformed one.
+  fn matrix(&self) -> [[i8; 2]; 2] {
+    let sign = |flip: bool| if flip { -1 } else { 1 };
+    let (h, v) = (sign(self.flip_horizontal), sign(self.flip_vertical));
+
+    if self.transpose {
+      [[0, h], [v, 0]]
+    } else {
+      [[h, 0], [0, v]]
+    }
+  }
+
+  /// Apply this transformation followed by `other`.
+  pub fn then(self, other: Self) -> Self {
+    let (a, b) = (other.matrix(), self.matrix());
+    let mut m = [[0; 2]; 2];
+
+    for (row, m_row) in m.iter_mut().enumerate() {
+      for (col, value) in m_row.iter_mut().enumerate() {
+        *value = a[row][0] * b[0][col] + a[row][1] * b[1][col];
+      }
+    }
+
+    // The product of flips and transpositions is again one of them.
+    let transpose = m[0][0] == 0;
+    let (h, v) = if transpose { (m[0][1], m[1][0]) } else { (m[0][0], m[1][1]) };
+    Self { transpose, flip_horizontal: h < 0, flip_vertical: v < 0 }
+  }
+
+  /// Apply this transformation followed by a flip.
+  pub fn flip(self, flip: Flip) -> Self {
+    let flip = match flip {
+      Flip::Horizontal => Self { flip_horizontal: true, ..Self::IDENTITY },
+      Flip::Vertical => Self { flip_vertical: true, ..Self::IDENTITY },
+    };
+
+    self.then(flip)
+  }
+
+  /// Apply this transformation followed by a clockwise rotation.
+  pub fn rotate(self, rotation: Rotation) -> Self {
+    let rotation = match rotation {
+      Rotation::Cw90 => Self { transpose: true, flip_horizontal: true, flip_vertical: false },
+      Rotation::Cw180 => Self { transpose: false, flip_horizontal: true, flip_vertical: true },
+      Rotation::Cw270 => Self { transpose: true, flip_horizontal: false, flip_vertical: true },
+    };
+
+    self.then(rotation)
+  }
+
+  /// The physical distance between voxels in a row and between rows
+  /// of a transformed frame.
+  pub fn apply_spacing(&self, (hspacing, vspacing): (f64, f64)) -> (f64, f64) {
+    if self.transpose {
+      (vspacing, hspacing)
+    } else {
+      (hspacing, vspacing)
+    }
+  }
+
+  /// Transform a frame.
+  pub fn apply(&self, frame: &Frame) -> Frame {
+    let (width, height) = if self.transpose {
+      (frame.height(), frame.width())
+    } else {
+      (frame.width(), frame.height())
+    };
+
+    let voxels = (0..width * height).map(|index| {
+      // `index` is below width * height, so width is not zero.
+      let (x, y) = (index % width, index / width);
+      let x_source = if self.flip_horizontal { width - 1 - x } else { x };
+      let y_source = if self.flip_vertical { height - 1 - y } else { y };
+      let voxel = if self.transpose {
+        frame.voxel(y_source, x_source)
+      } else {
+        frame.voxel(x_source, y_source)
+      };
+      (Ok(voxel), x, y)
+    });
+
+    // The iterator produces exactly width * height voxels.
+    Frame::from_iter(width, height, voxels).unwrap()
+  }
+}
+
+#[cfg(test)]
+mod orientation_tests {
+  use super::{Convention, Flip, FrameOrientation, Rotation};
+  use crate::metadata::AnatomicalOrientation;
+  use crate::{Axis, Frame, Voxel};
+
+  /// A 3x2 frame with values 0 to 5 in row-major order.
+  fn frame() -> Frame {
+    let iter = (0..6).map(|i| (Ok(Voxel::U8(i as u8)), i % 3, i / 3));
+    Frame::from_iter(3, 2, iter).unwrap()
+  }
+
+  fn values(frame: &Frame) -> (usize, usize, Vec<u8>) {
+    let values = frame.iter().map(|(voxel, _, _)| voxel.unwrap().value() as u8).collect();
+    (frame.width(), frame.height(), values)
+  }
+
+  #[test]
+  fn parse() {
+    assert_eq!("Neurological".parse(), Ok(Convention::Neurological));
+    assert_eq!("h".parse(), Ok(Flip::Horizontal));
+    assert_eq!("vertical".parse(), Ok(Flip::Vertical));
+    assert_eq!("270".parse(), Ok(Rotation::Cw270));
+    assert!("45".parse::<Rotation>().is_err());
+    assert!("sideways".parse::<Flip>().is_err());
+  }
+
+  #[test]
+  fn flips_and_rotations() {
+    let identity = FrameOrientation::IDENTITY;
+    assert_eq!(values(&identity.apply(&frame())), (3, 2, vec![0, 1, 2, 3, 4, 5]));
+
+    let flipped = identity.flip(Flip::Horizontal);
+    assert_eq!(values(&flipped.apply(&frame())), (3, 2, vec![2, 1, 0, 5, 4, 3]));
+
+    let flipped = identity.flip(Flip::Vertical);
+    assert_eq!(values(&flipped.apply(&frame())), (3, 2, vec![3, 4, 5, 0, 1, 2]));
+
+    let rotated = identity.rotate(Rotation::Cw90);
+    assert_eq!(values(&rotated.apply(&frame())), (2, 3, vec![3, 0, 4, 1, 5, 2]));
+
+    let rotated = identity.rotate(Rotation::Cw270);
+    assert_eq!(values(&rotated.apply(&frame())), (2, 3, vec![2, 5, 1, 4, 0, 3]));
+  }
+
+  #[test]
+  fn composition() {
+    let identity = FrameOrientation::IDENTITY;
+    let quarter = identity.rotate(Rotation::Cw90);
+    assert_eq!(quarter.rotate(Rotation::Cw90), identity.rotate(Rotation::Cw180));
+    assert_eq!(quarter.rotate(Rotation::Cw270), identity);
+    assert_eq!(
+      identity.flip(Flip::Horizontal).flip(Flip::Vertical),
+      identity.rotate(Rotation::Cw180)
+    );
+
+    let twice = quarter.then(quarter);
+    assert_eq!(values(&twice.apply(&frame())), values(&quarter.apply(&quarter.apply(&frame()))));
+  }
+
+  #[test]
+  fn anatomical_rai() {
+    // Frames are produced such that RAI volumes come out in the
+    // radiological convention.
+    let rai = AnatomicalOrientation::RAI;
+    for &axis in &Axis::ALL {
+      let orientation = FrameOrientation::from_anatomical(axis, rai, Convention::Radiological);
+      assert!(orientation.is_identity());
+    }
+
+    let neurological = FrameOrientation::from_anatomical(Axis::Z, rai, Convention::Neurological);
+    assert_eq!(neurological, FrameOrientation::IDENTITY.flip(Flip::Horizontal));
+
+    let sagittal = FrameOrientation::from_anatomical(Axis::X, rai, Convention::Neurological);
+    assert!(sagittal.is_identity());
+  }
+
+  #[test]
+  fn anatomical_other() {
+    let lps = AnatomicalOrientation::from_code("LPS").unwrap();
+    let axial = FrameOrientation::from_anatomical(Axis::Z, lps, Convention::Radiological);
+    assert_eq!(axial, FrameOrientation::IDENTITY.rotate(Rotation::Cw180));
+
+    let coronal = FrameOrientation::from_anatomical(Axis::Y, lps, Convention::Radiological);
+    assert_eq!(coronal, FrameOrientation::IDENTITY.rotate(Rotation::Cw180));
+
+    // Sagittal frames stored with the Z-axis going from anterior to
+    // posterior and the Y-axis from inferior to superior.
+    let ais = AnatomicalOrientation::from_code("RIA").unwrap();
+    let sagittal = FrameOrientation::from_anatomical(Axis::X, ais, Convention::Radiological);
+    assert!(sagittal.transposes());
+    assert_eq!(values(&sagittal.apply(&frame())).0, 2);
+  }
+}
@@ -0,0 +1,78 @@
+//! Handles positions of frames along an axis. The primary structure
+//! is the [frame position enum](FramePosition).
+
+use crate::MedvizErr;
+use std::fmt;
+use std::str::FromStr;
+
+/// Position of a frame along an axis.
+///
+/// Positions are resolved to frame indexes with
+/// [`VolumeMd::frame_index`](crate::VolumeMd::frame_index).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FramePosition {
+  /// The index of the frame.
+  Index(usize),
+
+  /// A percentage of the way from the first to the last frame.
+  Percent(f64),
+
+  /// A physical distance from the first frame in millimetres.
+  Millimetres(f64),
+}
+
+impl FromStr for FramePosition {
+  type Err = MedvizErr;
+
+  /// Parse a frame position.
+  ///
+  /// Accepts a frame index (e.g. `120`), a percentage (e.g. `50%`) or
+  /// a distance in millimetres (e.g. `42.5mm`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let text = text.trim();
+    let invalid = || MedvizErr::new_invalid_frame_position(text.into());
+
+    if let Some(percent) = text.strip_suffix('%') {
+      let percent = percent.trim().parse().map_err(|_| invalid())?;
+      Ok(Self::Percent(percent))
+    } else if let Some(millimetres) = text.strip_suffix("mm") {
+      let millimetres = millimetres.trim().parse().map_err(|_| invalid())?;
+      Ok(Self::Millimetres(millimetres))
+    } else {
+      let index = text.parse().map_err(|_| invalid())?;
+      Ok(Self::Index(index))
+    }
+  }
+}
+
+impl fmt::Display for FramePosition {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Index(index) => write!(f, "{}", index),
+      Self::Percent(percent) => write!(f, "{}%", percent),
+      Self::Millimetres(millimetres) => write!(f, "{}mm", millimetres),
+    }
+  }
+}
+
+#[cfg(test)]
+mod frame_position_tests {
+  use super::FramePosition;
+  use crate::MedvizErr;
+
+  #[test]
+  fn parse() {
+    assert_eq!("120".parse(), Ok(FramePosition::Index(120)));
+    assert_eq!("50%".parse(), Ok(FramePosition::Percent(50.0)));
+    assert_eq!(" 12.5 %".parse(), Ok(FramePosition::Percent(12.5)));
+    assert_eq!("42.5mm".parse(), Ok(FramePosition::Millimetres(42.5)));
+  }
+
+  #[test]
+  fn parse_fail() {
+    for text in &["", "-1", "abc", "%", "1.5", "mm", "5cm"] {
+      let err = text.parse::<FramePosition>();
+      assert_eq!(err, Err(MedvizErr::InvalidFramePosition { position: String::from(*text) }));
+    }
+  }
+}
@@ -0,0 +1,104 @@
+//! Handles intensity projections, which combine the frames of a slab
+//! of a volume into a single frame.
+
+use crate::MedvizErr;
+use std::fmt;
+use std::str::FromStr;
+
+/// A way of combining voxels along an axis into a single voxel.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Projection {
+  /// Maximum intensity projection (MIP).
+  Maximum,
+
+  /// Minimum intensity projection (MinIP).
+  Minimum,
+
+  /// Average intensity projection (AvgIP).
+  Average,
+}
+
+impl Projection {
+  /// All projections, in order.
+  pub const ALL: [Self; 3] = [Self::Maximum, Self::Minimum, Self::Average];
+
+  /// The value of a projection before any voxels are combined.
+  pub(crate) fn initial(&self) -> f64 {
+    match self {
+      Self::Maximum => f64::NEG_INFINITY,
+      Self::Minimum => f64::INFINITY,
+      Self::Average => 0.0,
+    }
+  }
+
+  /// Combine a voxel value into a projection value.
+  pub(crate) fn combine(&self, projected: f64, value: f64) -> f64 {
+    match self {
+      Self::Maximum => projected.max(value),
+      Self::Minimum => projected.min(value),
+      Self::Average => projected + value,
+    }
+  }
+
+  /// The final value of a projection after `count` voxels have been
+  /// combined.
+  pub(crate) fn finish(&self, projected: f64, count: usize) -> f64 {
+    match self {
+      Self::Maximum | Self::Minimum => projected,
+      Self::Average => projected / count as f64,
+    }
+  }
+}
+
+impl fmt::Display for Projection {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Maximum => f.write_str("mip"),
+      Self::Minimum => f.write_str("minip"),
+      Self::Average => f.write_str("avgip"),
+    }
+  }
+}
+
+impl FromStr for Projection {
+  type Err = MedvizErr;
+
+  /// Parse a projection from its abbreviation (e.g. `mip`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let name = text.trim();
+    Self::ALL
+      .iter()
+      .copied()
+      .find(|projection| projection.to_string().eq_ignore_ascii_case(name))
+      .ok_or_else(|| MedvizErr::new_invalid_projection(text.into()))
+  }
+}
+
+#[cfg(test)]
+mod projection_tests {
+  use super::Projection;
+  use crate::MedvizErr;
+
+  #[test]
+  fn parse() {
+    assert_eq!("mip".parse(), Ok(Projection::Maximum));
+    assert_eq!("MinIP".parse(), Ok(Projection::Minimum));
+    assert_eq!(" avgip ".parse(), Ok(Projection::Average));
+    assert_eq!(
+      "sum".parse::<Projection>(),
+      Err(MedvizErr::InvalidProjection { projection: String::from("sum") })
+    );
+  }
+
+  #[test]
+  fn combine() {
+    for (projection, expected) in
+      [(Projection::Maximum, 3.0), (Projection::Minimum, -1.0), (Projection::Average, 1.0)]
+    {
+      let projected = [3.0, -1.0, 1.0]
+        .iter()
+        .fold(projection.initial(), |projected, value| projection.combine(projected, *value));
+      assert_eq!(projection.finish(projected, 3), expected);
+    }
+  }
+}
@@ -1,7 +1,10 @@
 //! Utilities for working with volumetric data.
 
+use crate::vector;
+use crate::Colormap;
 use crate::MedvizErr;
 use crate::Voxel;
+use crate::Window;
 use bmp::{px, Image, Pixel};
 use std::convert::TryFrom;
 
@@ -13,6 +16,12 @@ use std::convert::TryFrom;
 ///
 /// * `dim2` - The second dimension the frame is composed of.
 ///
+/// * `window` - The [window](Window) to map voxel values through, or
+///   `None` to [normalize](Voxel::value_normalized) them.
+///
+/// * `colormap` - The [colormap](Colormap) to render intensities
+///   with, or `None` for grayscale.
+///
 /// * `frame_iter` - The row-major iterator over frame voxels.
 ///
 /// # Returns
@@ -22,6 +31,8 @@ use std::convert::TryFrom;
 pub fn frame_bmp(
   dim1: usize,
   dim2: usize,
+  window: Option<&Window>,
+  colormap: Option<&Colormap>,
   frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
 ) -> Result<Image, MedvizErr> {
   let dim1 = u32::try_from(dim1)?;
@@ -36,9 +47,312 @@ pub fn frame_bmp(
     let voxel = voxel?;
     let x = u32::try_from(x)?;
     let y = u32::try_from(y)?;
-    let normalized = voxel.value_normalized();
-    image.set_pixel(x, y, px!(normalized, normalized, normalized));
+    let intensity = intensity(&voxel, window);
+    let pixel = match colormap {
+      Some(colormap) => {
+        let [red, green, blue] = colormap.color(intensity);
+        px!(red, green, blue)
+      }
+      None => px!(intensity, intensity, intensity),
+    };
+    image.set_pixel(x, y, pixel);
   }
 
   Ok(image)
 }
+
+/// Bit depth of png images.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PngDepth {
+  /// 8-bit samples holding the [normalized](Voxel::value_normalized)
+  /// or [windowed](Voxel::value_windowed) voxel values.
+  Eight,
+
+  /// 16-bit samples holding the [voxel values](Voxel::value_u16),
+  /// which are exact for 8- and 16-bit voxels only.
+  Sixteen,
+}
+
+/// The 8-bit display intensity of a voxel.
+fn intensity(voxel: &Voxel, window: Option<&Window>) -> u8 {
+  match window {
+    Some(window) => voxel.value_windowed(window),
+    None => voxel.value_normalized(),
+  }
+}
+
+/// Produce a png image out of a frame.
+///
+/// # Arguments
+///
+/// * `dim1` - The first dimension the frame is composed of.
+///
+/// * `dim2` - The second dimension the frame is composed of.
+///
+/// * `depth` - The bit depth of the image.
+///
+/// * `window` - The [window](Window) to map voxel values of 8-bit
+///   images through, or `None` to [normalize](Voxel::value_normalized)
+///   them. 16-bit images always hold the full voxel values.
+///
+/// * `colormap` - The [colormap](Colormap) to render intensities of
+///   8-bit images with, or `None` for grayscale. 16-bit images are
+///   always grayscale.
+///
+/// * `frame_iter` - The row-major iterator over frame voxels.
+///
+/// # Returns
+///
+/// The encoded png image or an error in case conversions from
+/// `usize` to `u32` fail (i.e. overflow) or encoding fails.
+pub fn frame_png(
+  dim1: usize,
+  dim2: usize,
+  depth: PngDepth,
+  window: Option<&Window>,
+  colormap: Option<&Colormap>,
+  frame_iter: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+) -> Result<Vec<u8>, MedvizErr> {
+  let width = u32::try_from(dim1)?;
+  let height = u32::try_from(dim2)?;
+
+  let colormap = match depth {
+    PngDepth::Eight => colormap,
+    PngDepth::Sixteen => None,
+  };
+
+  let sample_size = match (depth, colormap) {
+    (PngDepth::Eight, None) => 1,
+    (PngDepth::Eight, Some(_)) => 3,
+    (PngDepth::Sixteen, _) => 2,
+  };
+
+  let mut pixels = vec![0; dim1 * dim2 * sample_size];
+
+  for (voxel, x, y) in frame_iter {
+    let voxel = voxel?;
+    let pixel_index = (y * dim1 + x) * sample_size;
+    let pixel = &mut pixels[pixel_index..pixel_index + sample_size];
+
+    match (depth, colormap) {
+      (PngDepth::Eight, None) => pixel[0] = intensity(&voxel, window),
+      (PngDepth::Eight, Some(colormap)) => {
+        pixel.copy_from_slice(&colormap.color(intensity(&voxel, window)))
+      }
+      // Png samples are stored in big-endian.
+      (PngDepth::Sixteen, _) => pixel.copy_from_slice(&voxel.value_u16().to_be_bytes()),
+    }
+  }
+
+  let mut image = Vec::new();
+
+  {
+    let mut encoder = png::Encoder::new(&mut image, width, height);
+    encoder.set_color(match colormap {
+      Some(_) => png::ColorType::Rgb,
+      None => png::ColorType::Grayscale,
+    });
+    encoder.set_depth(match depth {
+      PngDepth::Eight => png::BitDepth::Eight,
+      PngDepth::Sixteen => png::BitDepth::Sixteen,
+    });
+
+    let png_err = |err: png::EncodingError| MedvizErr::new_png_encoding(err.to_string());
+    let mut writer = encoder.write_header().map_err(png_err)?;
+    writer.write_image_data(&pixels).map_err(png_err)?;
+    writer.finish().map_err(png_err)?;
+  }
+
+  Ok(image)
+}
+
+/// Find the directions of the rows and columns of an oblique frame
+/// from the normal of its plane, for use with
+/// [`Volume::oblique_frame`](crate::Volume::oblique_frame).
+///
+/// The rows follow the first axis of the volume (X, then Y, then Z)
+/// that is not close to perpendicular to the plane, projected onto the
+/// plane. The columns follow the cross product of the normal and the
+/// rows, so a normal along the Z-axis produces the same orientation as
+/// a frame on the Z-axis.
+///
+/// # Arguments
+///
+/// * `normal` - The normal of the plane.
+///
+/// # Returns
+///
+/// The unit directions of the rows and the columns, or an error in
+/// case `normal` has no direction.
+pub fn plane_axes(normal: [f64; 3]) -> Result<([f64; 3], [f64; 3]), MedvizErr> {
+  let normal = vector::normalize(normal)
+    .ok_or_else(|| MedvizErr::new_invalid_oblique_frame("The normal has no direction"))?;
+
+  // At least two axes have a projection of length 0.7 or more, so
+  // this always finds one.
+  let u_axis = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+    .iter()
+    .map(|&axis| vector::add(axis, vector::scale(normal, -vector::dot(axis, normal))))
+    .find(|projection| vector::norm(*projection) >= 0.5)
+    .and_then(vector::normalize)
+    .ok_or_else(|| MedvizErr::new_invalid_oblique_frame("The normal has no direction"))?;
+
+  Ok((u_axis, vector::cross(normal, u_axis)))
+}
+
+/// Produce a filename for a frame out of a template.
+///
+/// The template must contain an `{index}` placeholder, which is
+/// replaced with the index of the frame. The placeholder can also be
+/// written as `{index:0N}` to pad the index with zeros to `N` digits
+/// (e.g. `out/z_{index:04}.bmp`).
+///
+/// # Arguments
+///
+/// * `template` - The filename template.
+///
+/// * `index` - The index of the frame.
+///
+/// # Returns
+///
+/// The filename or an error in case the template is invalid.
+pub fn format_index(template: &str, index: usize) -> Result<String, MedvizErr> {
+  const PLACEHOLDER: &str = "{index";
+
+  let invalid = || MedvizErr::new_invalid_template(template.into());
+
+  let start = template.find(PLACEHOLDER).ok_or_else(invalid)?;
+  let spec_start = start + PLACEHOLDER.len();
+  let end = spec_start + template[spec_start..].find('}').ok_or_else(invalid)?;
+
+  let width = match &template[spec_start..end] {
+    "" => 0,
+    spec => match spec.strip_prefix(":0") {
+      Some(width) if !width.is_empty() && width.bytes().all(|b| b.is_ascii_digit()) => {
+        width.parse().map_err(|_| invalid())?
+      }
+      _ => return Err(invalid()),
+    },
+  };
+
+  Ok(format!("{}{:0width$}{}", &template[..start], index, &template[end + 1..], width = width))
+}
+
+#[cfg(test)]
+mod utils_tests {
+  use super::{format_index, frame_bmp, frame_png, plane_axes, PngDepth};
+  use crate::{Colormap, MedvizErr, Voxel, Window};
+
+  fn decode_png(image: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+    let decoder = png::Decoder::new(image);
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    pixels.truncate(info.buffer_size());
+    (info, pixels)
+  }
+
+  #[test]
+  fn frame_png_8bit() {
+    let voxels = [0, 2048, 4095, 1000];
+    let iter = voxels.iter().enumerate().map(|(i, v)| (Ok(Voxel::U16(*v)), i % 2, i / 2));
+    let image = frame_png(2, 2, PngDepth::Eight, None, None, iter).unwrap();
+
+    let (info, pixels) = decode_png(&image);
+    assert_eq!((info.width, info.height), (2, 2));
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    assert_eq!(pixels, vec![0, 128, 255, 62]);
+  }
+
+  #[test]
+  fn frame_png_window() {
+    let voxels = [-1024, 0, 40, 80];
+    let iter = voxels.iter().enumerate().map(|(i, v)| (Ok(Voxel::I16(*v)), i % 2, i / 2));
+    let image = frame_png(2, 2, PngDepth::Eight, Some(&Window::BRAIN), None, iter).unwrap();
+
+    let (_, pixels) = decode_png(&image);
+    assert_eq!(pixels, vec![0, 0, 128, 255]);
+  }
+
+  #[test]
+  fn frame_bmp_window() {
+    let voxels = [-1024, 0, 40, 80];
+    let iter = voxels.iter().enumerate().map(|(i, v)| (Ok(Voxel::I16(*v)), i % 2, i / 2));
+    let image = frame_bmp(2, 2, Some(&Window::BRAIN), None, iter).unwrap();
+    assert_eq!(image.get_pixel(0, 0), bmp::consts::BLACK);
+    assert_eq!(image.get_pixel(0, 1), bmp::Pixel::new(128, 128, 128));
+    assert_eq!(image.get_pixel(1, 1), bmp::consts::WHITE);
+  }
+
+  #[test]
+  fn frame_png_colormap() {
+    let colormap = Colormap::from_stops(&[[255, 0, 0], [0, 0, 255]]);
+    let voxels = [0, 4095];
+    let iter = voxels.iter().enumerate().map(|(i, v)| (Ok(Voxel::U16(*v)), i, 0));
+    let image = frame_png(2, 1, PngDepth::Eight, None, Some(&colormap), iter).unwrap();
+
+    let (info, pixels) = decode_png(&image);
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    assert_eq!(pixels, vec![255, 0, 0, 0, 0, 255]);
+  }
+
+  #[test]
+  fn frame_bmp_colormap() {
+    let colormap = Colormap::builtin("viridis").unwrap();
+    let iter = [0, 4095].iter().enumerate().map(|(i, v)| (Ok(Voxel::U16(*v)), i, 0));
+    let image = frame_bmp(2, 1, None, Some(&colormap), iter).unwrap();
+    assert_eq!(image.get_pixel(0, 0), bmp::Pixel::new(68, 1, 84));
+    assert_eq!(image.get_pixel(1, 0), bmp::Pixel::new(253, 231, 37));
+  }
+
+  #[test]
+  fn frame_png_16bit() {
+    let hot = Colormap::builtin("hot").unwrap();
+    let voxels = [0, 2048, 4095, 65535, 1, 258];
+    let iter = voxels.iter().enumerate().map(|(i, v)| (Ok(Voxel::U16(*v)), i % 3, i / 3));
+    let image = frame_png(3, 2, PngDepth::Sixteen, Some(&Window::BRAIN), Some(&hot), iter).unwrap();
+
+    let (info, pixels) = decode_png(&image);
+    assert_eq!((info.width, info.height), (3, 2));
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    let samples: Vec<u16> =
+      pixels.chunks(2).map(|sample| u16::from_be_bytes([sample[0], sample[1]])).collect();
+    assert_eq!(samples, voxels.to_vec());
+  }
+
+  #[test]
+  fn format_index_success() {
+    assert_eq!(format_index("z_{index}.bmp", 7), Ok(String::from("z_7.bmp")));
+    assert_eq!(format_index("out/z_{index:04}.bmp", 7), Ok(String::from("out/z_0007.bmp")));
+    assert_eq!(format_index("{index:02}", 123), Ok(String::from("123")));
+  }
+
+  #[test]
+  fn format_index_fail() {
+    for template in &["z.bmp", "z_{index.bmp", "z_{index:4}.bmp", "z_{index:0}.bmp", "{index:0x}"] {
+      let err = format_index(template, 7);
+      assert_eq!(err, Err(MedvizErr::InvalidTemplate { template: String::from(*template) }));
+    }
+  }
+
+  #[test]
+  fn plane_axes_aligned() {
+    assert_eq!(plane_axes([0.0, 0.0, 2.0]), Ok(([1.0, 0.0, 0.0], [0.0, 1.0, 0.0])));
+    assert_eq!(plane_axes([-1.0, 0.0, 0.0]), Ok(([0.0, 1.0, 0.0], [0.0, 0.0, -1.0])));
+    assert_eq!(plane_axes([0.0, 1.0, 0.0]), Ok(([1.0, 0.0, 0.0], [0.0, 0.0, -1.0])));
+    assert!(plane_axes([0.0; 3]).is_err());
+  }
+
+  #[test]
+  fn plane_axes_oblique() {
+    let normal = [1.0, 2.0, 3.0];
+    let (u_axis, v_axis) = plane_axes(normal).unwrap();
+    let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    assert!(dot(u_axis, normal).abs() < 1e-9);
+    assert!(dot(v_axis, normal).abs() < 1e-9);
+    assert!(dot(u_axis, v_axis).abs() < 1e-9);
+    assert!((dot(u_axis, u_axis) - 1.0).abs() < 1e-9);
+    assert!((dot(v_axis, v_axis) - 1.0).abs() < 1e-9);
+  }
+}
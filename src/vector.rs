@@ -0,0 +1,91 @@
+//! Small helpers for working with 3D vectors.
+
+/// A 3D vector.
+pub(crate) type Vector = [f64; 3];
+
+/// Add two vectors.
+pub(crate) fn add(a: Vector, b: Vector) -> Vector {
+  [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+/// Multiply a vector by a scalar.
+pub(crate) fn scale(a: Vector, factor: f64) -> Vector {
+  [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+/// The dot product of two vectors.
+pub(crate) fn dot(a: Vector, b: Vector) -> f64 {
+  a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// The cross product of two vectors.
+pub(crate) fn cross(a: Vector, b: Vector) -> Vector {
+  [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Solve the linear system `A x = b` for `x`, with the matrix `A`
+/// given by its columns.
+///
+/// # Returns
+///
+/// The solution, or `None` in case the matrix cannot be inverted.
+pub(crate) fn solve(columns: [Vector; 3], b: Vector) -> Option<Vector> {
+  // Cramer's rule, using the scalar triple product for determinants.
+  let det = |a: Vector, b: Vector, c: Vector| dot(a, cross(b, c));
+  let [c0, c1, c2] = columns;
+  let denominator = det(c0, c1, c2);
+
+  if !denominator.is_finite() || denominator.abs() < 1e-12 {
+    return None;
+  }
+
+  Some([det(b, c1, c2) / denominator, det(c0, b, c2) / denominator, det(c0, c1, b) / denominator])
+}
+
+/// The length of a vector.
+pub(crate) fn norm(a: Vector) -> f64 {
+  dot(a, a).sqrt()
+}
+
+/// Scale a vector to unit length.
+///
+/// # Returns
+///
+/// The unit vector, or `None` in case the vector has no usable
+/// length (i.e. zero or not finite).
+pub(crate) fn normalize(a: Vector) -> Option<Vector> {
+  let length = norm(a);
+
+  if length.is_finite() && length > 1e-9 {
+    Some(scale(a, 1.0 / length))
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod vector_tests {
+  use super::{add, cross, dot, normalize, scale, solve};
+
+  #[test]
+  fn arithmetic() {
+    assert_eq!(add([1.0, 2.0, 3.0], [1.0, 1.0, 1.0]), [2.0, 3.0, 4.0]);
+    assert_eq!(scale([1.0, 2.0, 3.0], 2.0), [2.0, 4.0, 6.0]);
+    assert_eq!(dot([1.0, 2.0, 3.0], [4.0, 5.0, 6.0]), 32.0);
+    assert_eq!(cross([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+  }
+
+  #[test]
+  fn normalize_vector() {
+    assert_eq!(normalize([0.0, 0.0, 2.0]), Some([0.0, 0.0, 1.0]));
+    assert_eq!(normalize([0.0; 3]), None);
+    assert_eq!(normalize([f64::NAN, 0.0, 0.0]), None);
+  }
+
+  #[test]
+  fn solve_system() {
+    let columns = [[2.0, 0.0, 0.0], [0.0, 0.0, 4.0], [0.0, -1.0, 0.0]];
+    assert_eq!(solve(columns, [2.0, 3.0, 8.0]), Some([1.0, 2.0, -3.0]));
+    assert_eq!(solve([[1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 0.0, 1.0]], [1.0; 3]), None);
+  }
+}
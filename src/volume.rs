@@ -1,12 +1,28 @@
 //! Handles data related to 3D volumetric data. The primary structure
 //! is the [volume struct](Volume).
 
+use crate::index::{Connectivity, VoxelIndex};
+use crate::interpolation::BSpline;
+use crate::metadata::ElementType;
+use crate::vector::{self, Vector};
+use crate::Axis;
+use crate::Frame;
+use crate::Interpolation;
 use crate::MedvizErr;
+use crate::Projection;
+use crate::VolumeBuf;
 use crate::VolumeMd;
 use crate::Voxel;
+use std::io::{self, Write};
+use std::mem;
+use std::ops::Range;
+
+/// A boxed iterator over the voxels in a frame and their
+/// corresponding coordinates.
+pub type FrameIter<'d> = Box<dyn Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd>;
 
 /// Volume data.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Volume<'d> {
   /// Metadata related to the volume.
   metadata: VolumeMd,
@@ -31,58 +47,256 @@ impl<'d> Volume<'d> {
   /// of `data` does not match the expected size provided by
   /// `metadata`.
   pub fn from_slice(metadata: VolumeMd, data: &'d [u8]) -> Result<Self, MedvizErr> {
-    let expected = metadata.xdim() * metadata.ydim() * metadata.zdim() * Voxel::size();
+    let voxel_size = metadata.element_type().size();
+    let expected = metadata.data_size()?;
 
     if data.len() != expected {
       return Err(MedvizErr::new_data_size_mismatch(data.len(), expected));
     }
 
-    if data.len() % Voxel::size() != 0 {
+    if !data.len().is_multiple_of(voxel_size) {
       return Err(MedvizErr::new_data_size_uneven(data.len()));
     }
 
     Ok(Self { metadata, data })
   }
 
+  /// Metadata related to the volume.
+  pub fn metadata(&self) -> &VolumeMd {
+    &self.metadata
+  }
+
+  /// Write the volume data as a raw data file, in the byte order
+  /// given by the [metadata](Volume::metadata).
+  ///
+  /// # Arguments
+  ///
+  /// * `writer` - Where to write the data, e.g. a buffered file.
+  ///
+  /// # Returns
+  ///
+  /// [An error](io::Error) in case writing fails.
+  pub fn write_raw(&self, mut writer: impl Write) -> io::Result<()> {
+    writer.write_all(self.data)?;
+    writer.flush()
+  }
+
+  /// The size of a voxel in bytes.
+  fn voxel_size(&self) -> usize {
+    self.metadata.element_type().size()
+  }
+
+  /// The type and byte order of the voxels.
+  fn voxel_encoding(&self) -> (ElementType, bool) {
+    (self.metadata.element_type(), self.metadata.byte_order_msb())
+  }
+
+  /// A voxel of the type of the volume with a value of zero.
+  fn zero_voxel(&self) -> Voxel {
+    Voxel::zero(self.metadata.element_type())
+  }
+
+  /// Return an error if `index` is outside the range of frames on
+  /// `axis`.
+  fn check_frame_index(&self, axis: Axis, index: usize) -> Result<(), MedvizErr> {
+    self.metadata.check_frame_index(axis, index)
+  }
+
   /// Return a slice of bytes of a frame on the Z-axis.
-  fn zframe_bytes(&'d self, zframe_index: usize) -> &'d [u8] {
+  fn zframe_bytes(&self, zframe_index: usize) -> Result<&'d [u8], MedvizErr> {
+    self.check_frame_index(Axis::Z, zframe_index)?;
+
     // Size in bytes of a frame on the Z-axis.
-    let zframe_size = self.metadata.zframe_len() * Voxel::size();
+    let zframe_size = self.metadata.zframe_len() * self.voxel_size();
     let zframe_byte_index = zframe_size * zframe_index;
-    &self.data[zframe_byte_index..zframe_byte_index + zframe_size]
+    Ok(&self.data[zframe_byte_index..zframe_byte_index + zframe_size])
+  }
+
+  /// Return an iterator of voxels over a slice of bytes.
+  ///
+  /// In case `bytes` is an error, the iterator only produces that
+  /// error.
+  fn voxel_iter(
+    &self,
+    bytes: Result<&'d [u8], MedvizErr>,
+  ) -> impl Iterator<Item = Result<Voxel, MedvizErr>> + 'd {
+    let (element_type, byte_order_msb) = self.voxel_encoding();
+
+    let (bytes, err) = match bytes {
+      Ok(bytes) => (bytes, None),
+      Err(err) => (&[][..], Some(Err(err))),
+    };
+
+    err.into_iter().chain(
+      bytes
+        .chunks(self.voxel_size())
+        .map(move |bytes| Ok(Voxel::from_slice(element_type, byte_order_msb, bytes))),
+    )
   }
 
   /// Return an iterator of voxels of a frame on the Z-axis.
   fn zframe_iter(
-    &'d self,
+    &self,
     zframe_index: usize,
   ) -> impl Iterator<Item = Result<Voxel, MedvizErr>> + 'd {
-    self.zframe_bytes(zframe_index).chunks(Voxel::size()).map(Voxel::from_slice)
+    self.voxel_iter(self.zframe_bytes(zframe_index))
   }
 
   /// Return a slice of bytes of a row on a frame on the Z-axis.
-  fn zframe_row_bytes(&'d self, zframe_index: usize, row_index: usize) -> &'d [u8] {
+  fn zframe_row_bytes(&self, zframe_index: usize, row_index: usize) -> Result<&'d [u8], MedvizErr> {
+    self.check_frame_index(Axis::Y, row_index)?;
+
     // Size in bytes of a row on a frame on the Z-axis.
-    let row_size = self.metadata.xdim() * Voxel::size();
+    let row_size = self.metadata.xdim() * self.voxel_size();
     let row_byte_index = row_size * row_index;
-    let zframe = self.zframe_bytes(zframe_index);
-    &zframe[row_byte_index..row_byte_index + row_size]
+    let zframe = self.zframe_bytes(zframe_index)?;
+    Ok(&zframe[row_byte_index..row_byte_index + row_size])
   }
 
   /// Return an iterator of voxels of a row on a frame on the Z-axis.
   fn zframe_row_iter(
-    &'d self,
+    &self,
     zframe_index: usize,
     row_index: usize,
   ) -> impl Iterator<Item = Result<Voxel, MedvizErr>> + 'd {
-    self.zframe_row_bytes(zframe_index, row_index).chunks(Voxel::size()).map(Voxel::from_slice)
+    self.voxel_iter(self.zframe_row_bytes(zframe_index, row_index))
   }
 
   /// Return a slice of bytes of a voxel on a frame on the Z-axis.
-  fn zframe_voxel_bytes(&'d self, zframe_index: usize, x: usize, y: usize) -> &'d [u8] {
-    let row = self.zframe_row_bytes(zframe_index, y);
-    let voxel_byte_index = x * Voxel::size();
-    &row[voxel_byte_index..voxel_byte_index + Voxel::size()]
+  fn zframe_voxel_bytes(
+    &self,
+    zframe_index: usize,
+    x: usize,
+    y: usize,
+  ) -> Result<&'d [u8], MedvizErr> {
+    self.check_frame_index(Axis::X, x)?;
+
+    let row = self.zframe_row_bytes(zframe_index, y)?;
+    let voxel_byte_index = x * self.voxel_size();
+    Ok(&row[voxel_byte_index..voxel_byte_index + self.voxel_size()])
+  }
+
+  /// Return the voxel at `x`, `y` and `z`.
+  fn voxel_at(&self, x: usize, y: usize, z: usize) -> Result<Voxel, MedvizErr> {
+    let bytes = self.zframe_voxel_bytes(z, x, y)?;
+    let (element_type, byte_order_msb) = self.voxel_encoding();
+    Ok(Voxel::from_slice(element_type, byte_order_msb, bytes))
+  }
+
+  /// Prepare sampling the volume with an interpolation, computing
+  /// the spline coefficients of [cubic](Interpolation::Cubic)
+  /// interpolation over `region`, the voxel ranges on the X-, Y- and
+  /// Z-axis that will be sampled.
+  fn sampler(&self, interpolation: Interpolation, region: [Range<usize>; 3]) -> Sampler {
+    match interpolation {
+      Interpolation::Nearest => Sampler::Nearest,
+      Interpolation::Trilinear => Sampler::Trilinear,
+      Interpolation::Cubic => {
+        let dims = [region[0].len(), region[1].len(), region[2].len()];
+        let origin = [region[0].start, region[1].start, region[2].start];
+        let [x_range, y_range, z_range] = region;
+
+        let values = z_range.flat_map(move |z| {
+          let x_range = x_range.clone();
+          y_range.clone().flat_map(move |y| x_range.clone().map(move |x| (x, y, z)))
+        });
+        let values = values.map(|(x, y, z)| self.voxel_at(x, y, z).map_or(0.0, |v| v.value()));
+
+        Sampler::Cubic { spline: BSpline::new(dims, values), origin }
+      }
+    }
+  }
+
+  /// The voxel ranges on the X-, Y- and Z-axis around a plane whose
+  /// `corners` are given in (fractional) voxel indexes, with a
+  /// [margin](BSpline::MARGIN) for cubic interpolation.
+  fn plane_region(&self, corners: [Vector; 4]) -> [Range<usize>; 3] {
+    let dims = self.metadata.dims();
+    let margin = BSpline::MARGIN as f64;
+
+    let mut region = [0..0, 0..0, 0..0];
+    for axis in 0..3 {
+      let min = corners.iter().map(|corner| corner[axis]).fold(f64::INFINITY, f64::min);
+      let max = corners.iter().map(|corner| corner[axis]).fold(f64::NEG_INFINITY, f64::max);
+
+      // Float to integer casts saturate, so the region is clamped to
+      // the volume.
+      let end = ((max.ceil() + margin + 1.0) as usize).min(dims[axis]);
+      let start = ((min.floor() - margin) as usize).min(end);
+      region[axis] = start..end;
+    }
+
+    region
+  }
+
+  /// Sample the volume at a position given in (fractional) voxel
+  /// indexes.
+  ///
+  /// # Returns
+  ///
+  /// The sampled voxel, or `None` in case `position` lies outside the
+  /// voxel centers of the volume.
+  fn sample(&self, position: Vector, sampler: &Sampler) -> Result<Option<Voxel>, MedvizErr> {
+    // Allows positions that end up slightly outside the volume due to
+    // rounding errors.
+    const TOLERANCE: f64 = 1e-6;
+
+    let dims = [self.metadata.xdim(), self.metadata.ydim(), self.metadata.zdim()];
+    let mut clamped = [0.0; 3];
+    let mut lower = [0; 3];
+    let mut upper = [0; 3];
+    let mut frac = [0.0; 3];
+
+    for axis in 0..3 {
+      let last = dims[axis] as f64 - 1.0;
+
+      // Also rejects NaN positions.
+      if !(position[axis] >= -TOLERANCE && position[axis] <= last + TOLERANCE) {
+        return Ok(None);
+      }
+
+      // The position is within the volume, so these casts are safe.
+      clamped[axis] = position[axis].max(0.0).min(last);
+      lower[axis] = clamped[axis].floor() as usize;
+      upper[axis] = (lower[axis] + 1).min(dims[axis] - 1);
+      frac[axis] = clamped[axis] - lower[axis] as f64;
+    }
+
+    match sampler {
+      Sampler::Nearest => {
+        let index = |axis: usize| if frac[axis] < 0.5 { lower[axis] } else { upper[axis] };
+        self.voxel_at(index(0), index(1), index(2)).map(Some)
+      }
+      Sampler::Trilinear => {
+        let mut value = 0.0;
+
+        for corner in 0..8 {
+          let mut index = [0; 3];
+          let mut weight = 1.0;
+
+          for axis in 0..3 {
+            if corner & (1 << axis) == 0 {
+              index[axis] = lower[axis];
+              weight *= 1.0 - frac[axis];
+            } else {
+              index[axis] = upper[axis];
+              weight *= frac[axis];
+            }
+          }
+
+          if weight > 0.0 {
+            value += weight * self.voxel_at(index[0], index[1], index[2])?.value();
+          }
+        }
+
+        let voxel = self.voxel_at(lower[0], lower[1], lower[2])?;
+        Ok(Some(voxel.with_value(value)))
+      }
+      Sampler::Cubic { spline, origin } => {
+        let position = [0, 1, 2].map(|axis| clamped[axis] - origin[axis] as f64);
+        Ok(Some(self.zero_voxel().with_value(spline.value(position))))
+      }
+    }
   }
 
   /// Return an iterator of voxels of a column on a frame on the
@@ -97,19 +311,225 @@ impl<'d> Volume<'d> {
     col_index: usize,
   ) -> impl Iterator<Item = Result<Voxel, MedvizErr>> + 'd {
     (0..self.metadata.ydim()).map(move |row_index| {
-      let bytes = self.zframe_voxel_bytes(frame_index, col_index, row_index);
-      Voxel::from_slice(bytes)
+      let bytes = self.zframe_voxel_bytes(frame_index, col_index, row_index)?;
+      let (element_type, byte_order_msb) = self.voxel_encoding();
+      Ok(Voxel::from_slice(element_type, byte_order_msb, bytes))
     })
   }
 
-  /// Create an iterator over the voxels in a frame on the X-axis.
+  /// The voxel at `x`, `y` and `z`.
   ///
-  /// The returned iterator also produces the coordinates for each
-  /// voxel value returned.
+  /// # Returns
+  ///
+  /// The voxel or [an error](Err) in case the position is outside the
+  /// volume.
+  pub fn get(&self, x: usize, y: usize, z: usize) -> Result<Voxel, MedvizErr> {
+    self.get_index(VoxelIndex::new(x, y, z))
+  }
+
+  /// The voxel at a [voxel index](VoxelIndex).
+  ///
+  /// # Returns
+  ///
+  /// The voxel or [an error](Err) in case the position is outside the
+  /// volume.
+  pub fn get_index(&self, index: VoxelIndex) -> Result<Voxel, MedvizErr> {
+    if !index.is_inside(self.metadata.dims()) {
+      return Err(MedvizErr::new_voxel_out_of_range(index.x, index.y, index.z));
+    }
+
+    self.voxel_at(index.x, index.y, index.z)
+  }
+
+  /// Create an iterator over the neighbors of a voxel.
+  ///
+  /// # Arguments
+  ///
+  /// * `index` - The position of the voxel.
+  ///
+  /// * `connectivity` - Which voxels count as neighbors.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the neighbors inside the volume and their
+  /// positions, or [an error](Err) in case `index` is outside the
+  /// volume.
+  pub fn neighbors(
+    &'d self,
+    index: VoxelIndex,
+    connectivity: Connectivity,
+  ) -> Result<impl Iterator<Item = (VoxelIndex, Voxel)> + 'd, MedvizErr> {
+    self.get_index(index)?;
+
+    Ok(index.neighbors(connectivity, self.metadata.dims()).filter_map(move |neighbor| {
+      // Neighbors are always inside the volume, so this never skips
+      // a neighbor.
+      self.get_index(neighbor).ok().map(|voxel| (neighbor, voxel))
+    }))
+  }
+
+  /// Copy a region of interest of the volume into an [owned
+  /// volume](VolumeBuf).
+  ///
+  /// # Arguments
+  ///
+  /// * `x_range` - The indexes of the voxels to keep on the X-axis.
+  ///
+  /// * `y_range` - The indexes of the voxels to keep on the Y-axis.
+  ///
+  /// * `z_range` - The indexes of the voxels to keep on the Z-axis.
+  ///
+  /// # Returns
+  ///
+  /// The cropped volume, or [an error](Err) in case a range is empty
+  /// or outside the volume.
   ///
   /// # Notes
   ///
-  /// Panics if `xframe_index` is outside the range of frames.
+  /// The `Offset` of the cropped volume is the world position of its
+  /// first voxel, so that every voxel keeps its world position. The
+  /// spacing and directions are unchanged.
+  pub fn crop(
+    &self,
+    x_range: Range<usize>,
+    y_range: Range<usize>,
+    z_range: Range<usize>,
+  ) -> Result<VolumeBuf, MedvizErr> {
+    for (axis, range) in [(Axis::X, &x_range), (Axis::Y, &y_range), (Axis::Z, &z_range)] {
+      let len = self.metadata.dim(axis);
+      if range.start >= range.end || range.end > len {
+        return Err(MedvizErr::new_invalid_crop(axis, range.start, range.end, len));
+      }
+    }
+
+    let mut voxels = Vec::with_capacity(x_range.len() * y_range.len() * z_range.len());
+    for z in z_range.clone() {
+      for y in y_range.clone() {
+        for x in x_range.clone() {
+          voxels.push(self.voxel_at(x, y, z)?);
+        }
+      }
+    }
+
+    let start = [x_range.start as f64, y_range.start as f64, z_range.start as f64];
+    let metadata = self
+      .metadata
+      .clone()
+      .with_dims(x_range.len(), y_range.len(), z_range.len())
+      .with_offset(self.metadata.index_to_world(start));
+
+    VolumeBuf::from_voxels(metadata, voxels)
+  }
+
+  /// Resample the volume to a new voxel spacing (e.g. to make it
+  /// isotropic).
+  ///
+  /// # Arguments
+  ///
+  /// * `spacing` - The new distance between voxel centers on the X-,
+  ///   Y- and Z-axis.
+  ///
+  /// * `interpolation` - How to sample the volume between voxel
+  ///   centers.
+  ///
+  /// # Returns
+  ///
+  /// The resampled volume, or [an error](Err) in case `spacing` or
+  /// the spacing of the volume are not positive, or the resampled
+  /// volume would not fit in memory.
+  ///
+  /// # Notes
+  ///
+  /// The first voxel keeps its position, so the `Offset` and
+  /// directions are unchanged. The number of voxels on each axis is
+  /// chosen to cover the same extent as the volume, and samples past
+  /// its last voxel center take the value at the edge.
+  pub fn resample(
+    &self,
+    spacing: [f64; 3],
+    interpolation: Interpolation,
+  ) -> Result<VolumeBuf, MedvizErr> {
+    let old_spacing = self.metadata.spacing();
+    let positive =
+      |spacing: [f64; 3]| spacing.iter().all(|value| value.is_finite() && *value > 0.0);
+
+    for spacing in [spacing, old_spacing] {
+      if !positive(spacing) {
+        return Err(MedvizErr::new_invalid_spacing(format!(
+          "{},{},{}",
+          spacing[0], spacing[1], spacing[2]
+        )));
+      }
+    }
+
+    let too_large = || {
+      MedvizErr::new_resample_too_large(format!(
+        "{:?},{:?},{:?}",
+        spacing[0], spacing[1], spacing[2]
+      ))
+    };
+
+    let old_dims = self.metadata.dims();
+    let mut dims = [0; 3];
+    let mut scale = [0.0; 3];
+    for axis in 0..3 {
+      let dim = (old_dims[axis] as f64 * old_spacing[axis] / spacing[axis]).round();
+      if dim >= usize::MAX as f64 {
+        return Err(too_large());
+      }
+
+      // The spacings are positive and `dim` is below usize::MAX, so
+      // the cast is exact.
+      dims[axis] = (dim as usize).max(1);
+      scale[axis] = spacing[axis] / old_spacing[axis];
+    }
+
+    // Both the decoded voxels and their bytes must fit in memory.
+    let len =
+      dims.iter().try_fold(1usize, |len, dim| len.checked_mul(*dim)).ok_or_else(too_large)?;
+    for size in [mem::size_of::<Voxel>(), self.voxel_size()] {
+      match len.checked_mul(size) {
+        Some(bytes) if bytes <= isize::MAX as usize => {}
+        _ => return Err(too_large()),
+      }
+    }
+
+    let region = [0..old_dims[0], 0..old_dims[1], 0..old_dims[2]];
+    let sampler = self.sampler(interpolation, region);
+    let zero = self.zero_voxel();
+    let mut voxels = Vec::with_capacity(len);
+
+    for z in 0..dims[2] {
+      for y in 0..dims[1] {
+        for x in 0..dims[0] {
+          let mut position = [x as f64 * scale[0], y as f64 * scale[1], z as f64 * scale[2]];
+          for axis in 0..3 {
+            position[axis] = position[axis].min(old_dims[axis] as f64 - 1.0);
+          }
+
+          voxels.push(self.sample(position, &sampler)?.unwrap_or(zero));
+        }
+      }
+    }
+
+    let metadata = self.metadata.clone().with_dims(dims[0], dims[1], dims[2]).with_spacing(spacing);
+
+    VolumeBuf::from_voxels(metadata, voxels)
+  }
+
+  /// Decode all voxels of the volume into an [owned
+  /// volume](VolumeBuf).
+  pub fn to_buf(&self) -> VolumeBuf {
+    let voxels = self.voxel_iter(Ok(self.data)).filter_map(Result::ok).collect();
+    // The size of the data was checked on creation, so the number of
+    // voxels matches the metadata.
+    VolumeBuf::from_voxels(self.metadata.clone(), voxels).unwrap()
+  }
+
+  /// Create an iterator over the voxels in a frame on the X-axis.
+  ///
+  /// The returned iterator also produces the coordinates for each
+  /// voxel value returned.
   ///
   /// # Arguments
   ///
@@ -118,18 +538,24 @@ impl<'d> Volume<'d> {
   /// # Returns
   ///
   /// An iterator over the voxels in the frame and their corresponding
-  /// coordinates.
+  /// coordinates, or [an error](Err) in case `xframe_index` is
+  /// outside the range of frames.
   pub fn xframe(
     &'d self,
     xframe_index: usize,
-  ) -> impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd {
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd, MedvizErr> {
+    self.check_frame_index(Axis::X, xframe_index)?;
+
     // This works by going over every frame on the Z-axis. At each of
     // those frames, creates a "line" (iterator) out of the relevant
     // column.
     //
     // The .rev() call is used to start going over the frames on the
-    // Z-axis in reverse, since that seems to produce the expected
-    // result.
+    // Z-axis in reverse, so that the Z-axis goes up in the frame. For
+    // volumes in the default RAI orientation, where the Z-axis goes
+    // from the patient's feet to their head, this puts the head at
+    // the top. Other orientations and display conventions are handled
+    // by FrameOrientation.
     //
     // This causes the creation of multiple iterators, one per
     // column. These iterators then need to be chained together by
@@ -141,15 +567,17 @@ impl<'d> Volume<'d> {
     // The remaining step is to create the coordinates out of the
     // index and pack up each voxel and its coordinates in a
     // triple. Both of these are done in the final mapping.
-    (0..self.metadata.zdim())
-      .rev()
-      .flat_map(move |zframe_index| self.zframe_col_iter(zframe_index, xframe_index))
-      .enumerate()
-      .map(move |(index, voxel)| {
-        // `index` was produced by the call to .enumerate().
-        let ydim = self.metadata.ydim();
-        (voxel, index % ydim, index / ydim)
-      })
+    Ok(
+      (0..self.metadata.zdim())
+        .rev()
+        .flat_map(move |zframe_index| self.zframe_col_iter(zframe_index, xframe_index))
+        .enumerate()
+        .map(move |(index, voxel)| {
+          // `index` was produced by the call to .enumerate().
+          let ydim = self.metadata.ydim();
+          (voxel, index % ydim, index / ydim)
+        }),
+    )
   }
 
   /// Create an iterator over the voxels in a frame on the Y-axis.
@@ -157,10 +585,6 @@ impl<'d> Volume<'d> {
   /// The returned iterator also produces the coordinates for each
   /// voxel value returned.
   ///
-  /// # Notes
-  ///
-  /// Panics if `yframe_index` is outside the range of frames.
-  ///
   /// # Arguments
   ///
   /// * `yframe_index` - The index of the frame on the Y-axis.
@@ -168,18 +592,24 @@ impl<'d> Volume<'d> {
   /// # Returns
   ///
   /// An iterator over the voxels in the frame and their corresponding
-  /// coordinates.
+  /// coordinates, or [an error](Err) in case `yframe_index` is
+  /// outside the range of frames.
   pub fn yframe(
     &'d self,
     yframe_index: usize,
-  ) -> impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd {
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd, MedvizErr> {
+    self.check_frame_index(Axis::Y, yframe_index)?;
+
     // This works by going over every frame on the Z-axis. At each of
     // those frames, creates a "line" (iterator) out of the relevant
     // row.
     //
     // The .rev() call is used to start going over the frames on the
-    // Z-axis in reverse, since that seems to produce the expected
-    // result.
+    // Z-axis in reverse, so that the Z-axis goes up in the frame. For
+    // volumes in the default RAI orientation, where the Z-axis goes
+    // from the patient's feet to their head, this puts the head at
+    // the top. Other orientations and display conventions are handled
+    // by FrameOrientation.
     //
     // This causes the creation of multiple iterators, one per
     // row. These iterators then need to be chained together by
@@ -191,15 +621,17 @@ impl<'d> Volume<'d> {
     // The remaining step is to create the coordinates out of the
     // index and pack up each voxel and its coordinates in a
     // triple. Both of these are done in the final mapping.
-    (0..self.metadata.zdim())
-      .rev()
-      .flat_map(move |zframe_index| self.zframe_row_iter(zframe_index, yframe_index))
-      .enumerate()
-      .map(move |(index, voxel)| {
-        // `index` was produced by the call to .enumerate().
-        let xdim = self.metadata.xdim();
-        (voxel, index % xdim, index / xdim)
-      })
+    Ok(
+      (0..self.metadata.zdim())
+        .rev()
+        .flat_map(move |zframe_index| self.zframe_row_iter(zframe_index, yframe_index))
+        .enumerate()
+        .map(move |(index, voxel)| {
+          // `index` was produced by the call to .enumerate().
+          let xdim = self.metadata.xdim();
+          (voxel, index % xdim, index / xdim)
+        }),
+    )
   }
 
   /// Create an iterator over the voxels in a frame on the Z-axis.
@@ -207,10 +639,6 @@ impl<'d> Volume<'d> {
   /// The returned iterator also produces the coordinates for each
   /// voxel value returned.
   ///
-  /// # Notes
-  ///
-  /// Panics if `zframe_index` is outside the range of frames.
-  ///
   /// # Arguments
   ///
   /// * `zframe_index` - The index of the frame on the Z-axis.
@@ -218,15 +646,665 @@ impl<'d> Volume<'d> {
   /// # Returns
   ///
   /// An iterator over the voxels in the frame and their corresponding
-  /// coordinates.
+  /// coordinates, or [an error](Err) in case `zframe_index` is
+  /// outside the range of frames.
   pub fn zframe(
     &'d self,
     zframe_index: usize,
-  ) -> impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd {
-    self.zframe_iter(zframe_index).enumerate().map(move |(index, voxel)| {
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd, MedvizErr> {
+    self.check_frame_index(Axis::Z, zframe_index)?;
+
+    Ok(self.zframe_iter(zframe_index).enumerate().map(move |(index, voxel)| {
       // `index` was produced by the call to .enumerate().
       let xdim = self.metadata.xdim();
       (voxel, index % xdim, index / xdim)
+    }))
+  }
+
+  /// Create an iterator over the voxels in a frame on an axis.
+  ///
+  /// Dispatches to [`xframe`](Volume::xframe),
+  /// [`yframe`](Volume::yframe) or [`zframe`](Volume::zframe), which
+  /// should be preferred when the axis is known in advance.
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis the frame is on.
+  ///
+  /// * `index` - The index of the frame on `axis`.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the voxels in the frame and their corresponding
+  /// coordinates, or [an error](Err) in case `index` is outside the
+  /// range of frames.
+  pub fn frame(&'d self, axis: Axis, index: usize) -> Result<FrameIter<'d>, MedvizErr> {
+    Ok(match axis {
+      Axis::X => Box::new(self.xframe(index)?),
+      Axis::Y => Box::new(self.yframe(index)?),
+      Axis::Z => Box::new(self.zframe(index)?),
     })
   }
+
+  /// Create an iterator over the voxels in an oblique frame, a plane
+  /// at an arbitrary position and orientation through the volume
+  /// (also known as multiplanar reformation).
+  ///
+  /// Positions and distances are in millimetres, measured from the
+  /// center of the first voxel of the volume along its axes using the
+  /// spacing of the volume. Samples outside the volume have the
+  /// lowest value of the [nominal range](Voxel::nominal_range) of the
+  /// voxels.
+  ///
+  /// [Cubic](Interpolation::Cubic) interpolation computes spline
+  /// coefficients for the voxels around the frame, taking 4 bytes per
+  /// voxel of the box bounding the frame plus a margin of 16 voxels.
+  ///
+  /// # Arguments
+  ///
+  /// * `origin` - The position of the first voxel of the frame.
+  ///
+  /// * `u_axis` - The direction of the rows of the frame.
+  ///
+  /// * `v_axis` - The direction of the columns of the frame. Usually
+  ///   perpendicular to `u_axis`, which is not required.
+  ///
+  /// * `width` - The number of voxels in a row.
+  ///
+  /// * `height` - The number of rows.
+  ///
+  /// * `step` - The distance between neighboring voxels of the frame.
+  ///
+  /// * `interpolation` - How to sample the volume between voxel
+  ///   centers.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the voxels in the frame and their corresponding
+  /// coordinates, or [an error](Err) in case the axes have no
+  /// direction, `step` or the spacing of the volume are not positive,
+  /// or the frame is too large to hold in memory.
+  #[allow(clippy::too_many_arguments)]
+  pub fn oblique_frame(
+    &'d self,
+    origin: [f64; 3],
+    u_axis: [f64; 3],
+    v_axis: [f64; 3],
+    width: usize,
+    height: usize,
+    step: f64,
+    interpolation: Interpolation,
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'd, MedvizErr> {
+    let invalid = MedvizErr::new_invalid_oblique_frame;
+
+    let u_axis = vector::normalize(u_axis).ok_or_else(|| invalid("The u-axis has no direction"))?;
+    let v_axis = vector::normalize(v_axis).ok_or_else(|| invalid("The v-axis has no direction"))?;
+
+    if !(step.is_finite() && step > 0.0) {
+      return Err(invalid("The step is not positive"));
+    }
+
+    let spacing = self.metadata.spacing();
+
+    if !spacing.iter().all(|spacing| spacing.is_finite() && *spacing > 0.0) {
+      return Err(invalid("The spacing of the volume is not positive"));
+    }
+
+    // Frames are usually collected, so they must fit in memory.
+    let len = width
+      .checked_mul(height)
+      .filter(|len| {
+        len.checked_mul(mem::size_of::<Voxel>()).is_some_and(|size| size <= isize::MAX as usize)
+      })
+      .ok_or_else(|| invalid("The frame is too large"))?;
+
+    let background = self.zero_voxel();
+    let background = background.with_value(background.nominal_range().0);
+
+    // Cubic interpolation only needs the spline around the plane.
+    let to_index = |position: Vector| [0, 1, 2].map(|axis| position[axis] / spacing[axis]);
+    let u_end = vector::scale(u_axis, width.saturating_sub(1) as f64 * step);
+    let v_end = vector::scale(v_axis, height.saturating_sub(1) as f64 * step);
+    let corners = [
+      origin,
+      vector::add(origin, u_end),
+      vector::add(origin, v_end),
+      vector::add(origin, vector::add(u_end, v_end)),
+    ];
+    let sampler = self.sampler(interpolation, self.plane_region(corners.map(to_index)));
+
+    Ok((0..len).map(move |index| {
+      // `index` is below width * height, so width is not zero.
+      let (u, v) = (index % width, index / width);
+
+      let position = vector::add(
+        origin,
+        vector::add(vector::scale(u_axis, u as f64 * step), vector::scale(v_axis, v as f64 * step)),
+      );
+
+      let position = [position[0] / spacing[0], position[1] / spacing[1], position[2] / spacing[2]];
+      let voxel = self.sample(position, &sampler).map(|voxel| voxel.unwrap_or(background));
+      (voxel, u, v)
+    }))
+  }
+
+  /// Project all frames on an axis into a single frame (e.g. a
+  /// maximum intensity projection).
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis to project along.
+  ///
+  /// * `projection` - How to combine the voxels along `axis`.
+  ///
+  /// # Returns
+  ///
+  /// The projected frame, with the same dimensions and orientation as
+  /// the frames on `axis`, or [an error](Err) in case there are no
+  /// frames on `axis`.
+  pub fn projection(&'d self, axis: Axis, projection: Projection) -> Result<Frame, MedvizErr> {
+    self.slab_projection(axis, projection, 0..self.metadata.dim(axis))
+  }
+
+  /// Project a slab of frames on an axis into a single frame (e.g. a
+  /// maximum intensity projection of a thick slab).
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis to project along.
+  ///
+  /// * `projection` - How to combine the voxels along `axis`.
+  ///
+  /// * `slab` - The indexes of the frames on `axis` to project.
+  ///
+  /// # Returns
+  ///
+  /// The projected frame, with the same dimensions and orientation as
+  /// the frames on `axis`, or [an error](Err) in case `slab` is empty
+  /// or outside the range of frames.
+  pub fn slab_projection(
+    &'d self,
+    axis: Axis,
+    projection: Projection,
+    slab: Range<usize>,
+  ) -> Result<Frame, MedvizErr> {
+    let len = self.metadata.dim(axis);
+
+    if slab.start >= slab.end || slab.end > len {
+      return Err(MedvizErr::new_invalid_slab(axis, slab.start, slab.end, len));
+    }
+
+    let (width, height) = self.metadata.frame_dims(axis);
+    let mut projected = vec![projection.initial(); width * height];
+
+    for index in slab.clone() {
+      for (voxel, x, y) in self.frame(axis, index)? {
+        let value = &mut projected[y * width + x];
+        *value = projection.combine(*value, voxel?.value());
+      }
+    }
+
+    let voxel = self.zero_voxel();
+    let count = slab.len();
+
+    Frame::from_iter(
+      width,
+      height,
+      projected.into_iter().enumerate().map(|(index, value)| {
+        // `index` was produced by the call to .enumerate().
+        (Ok(voxel.with_value(projection.finish(value, count))), index % width, index / width)
+      }),
+    )
+  }
+
+  /// Create an iterator over all frames on an axis, in order.
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis the frames are on.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the index of each frame and an iterator over
+  /// its voxels, as produced by [`frame`](Volume::frame).
+  pub fn frames(&'d self, axis: Axis) -> impl Iterator<Item = (usize, FrameIter<'d>)> + 'd {
+    (0..self.metadata.dim(axis)).filter_map(move |index| {
+      // All indexes are within the range of frames, so this never
+      // skips a frame.
+      self.frame(axis, index).ok().map(|frame| (index, frame))
+    })
+  }
+}
+
+/// How to sample a volume between voxel centers, holding what an
+/// [interpolation](Interpolation) needs to be computed once.
+enum Sampler {
+  /// [Nearest neighbor](Interpolation::Nearest) sampling.
+  Nearest,
+
+  /// [Trilinear](Interpolation::Trilinear) sampling.
+  Trilinear,
+
+  /// [Cubic B-spline](Interpolation::Cubic) sampling with the spline
+  /// through the voxels of a region of the volume.
+  Cubic {
+    /// The spline through the voxels of the region.
+    spline: BSpline,
+
+    /// The first voxel of the region.
+    origin: [usize; 3],
+  },
+}
+
+#[cfg(test)]
+mod volume_tests {
+  use super::Volume;
+  use crate::index::{Connectivity, VoxelIndex};
+  use crate::interpolation::BSpline;
+  use crate::{Axis, Interpolation, MedvizErr, Projection, VolumeMd, Voxel};
+
+  fn metadata(element_type: &str) -> VolumeMd {
+    let input = format!("DimSize = 2 2 2\nElementType = {}\n", element_type);
+    VolumeMd::from_buffer(&input).unwrap()
+  }
+
+  fn metadata_msb(element_type: &str) -> VolumeMd {
+    let input =
+      format!("DimSize = 2 2 2\nElementType = {}\nElementByteOrderMSB = True\n", element_type);
+    VolumeMd::from_buffer(&input).unwrap()
+  }
+
+  /// Collect the voxels of all X, Y and Z frames of a volume.
+  fn all_frames(volume: &Volume) -> Vec<Voxel> {
+    let mut voxels = Vec::new();
+    for index in 0..2 {
+      voxels.extend(volume.xframe(index).unwrap().map(|(voxel, _, _)| voxel.unwrap()));
+      voxels.extend(volume.yframe(index).unwrap().map(|(voxel, _, _)| voxel.unwrap()));
+      voxels.extend(volume.zframe(index).unwrap().map(|(voxel, _, _)| voxel.unwrap()));
+    }
+    voxels
+  }
+
+  #[test]
+  fn crop() {
+    let data: Vec<u8> = (0..8).collect();
+    let metadata =
+      metadata("MET_UCHAR").with_spacing([0.5, 2.0, 3.0]).with_offset([10.0, 20.0, 30.0]);
+    let volume = Volume::from_slice(metadata, &data).unwrap();
+
+    let cropped = volume.crop(1..2, 0..2, 1..2).unwrap();
+    assert_eq!(cropped.metadata().dims(), [1, 2, 1]);
+    assert_eq!(cropped.metadata().offset(), [10.5, 20.0, 33.0]);
+    assert_eq!(cropped.metadata().spacing(), [0.5, 2.0, 3.0]);
+    assert_eq!(cropped.voxels(), [Voxel::U8(5), Voxel::U8(7)]);
+    assert_eq!(
+      cropped.metadata().index_to_world([0.0, 1.0, 0.0]),
+      volume.metadata().index_to_world([1.0, 1.0, 1.0])
+    );
+
+    assert_eq!(volume.crop(0..2, 0..2, 0..2).unwrap(), volume.to_buf());
+    assert_eq!(volume.crop(0..3, 0..2, 0..2), Err(MedvizErr::new_invalid_crop(Axis::X, 0, 3, 2)));
+    assert_eq!(volume.crop(0..2, 1..1, 0..2), Err(MedvizErr::new_invalid_crop(Axis::Y, 1, 1, 2)));
+  }
+
+  #[test]
+  fn resample() {
+    let data: Vec<u8> = (0..8).map(|value| value * 10).collect();
+    let metadata = metadata("MET_UCHAR").with_spacing([1.0, 1.0, 2.0]).with_offset([5.0, 6.0, 7.0]);
+    let volume = Volume::from_slice(metadata, &data).unwrap();
+
+    for &interpolation in &Interpolation::ALL {
+      // The same spacing leaves the volume unchanged.
+      let resampled = volume.resample([1.0, 1.0, 2.0], interpolation).unwrap();
+      assert_eq!(resampled, volume.to_buf(), "{}", interpolation);
+
+      let resampled = volume.resample([1.0, 1.0, 1.0], interpolation).unwrap();
+      assert_eq!(resampled.metadata().dims(), [2, 2, 4]);
+      assert_eq!(resampled.metadata().spacing(), [1.0, 1.0, 1.0]);
+      assert_eq!(resampled.metadata().offset(), [5.0, 6.0, 7.0]);
+      assert_eq!(resampled.get(1, 1, 0), Ok(Voxel::U8(30)));
+      assert_eq!(resampled.get(1, 1, 2), Ok(Voxel::U8(70)));
+      // Past the last voxel center, the edge value is used.
+      assert_eq!(resampled.get(1, 1, 3), Ok(Voxel::U8(70)));
+    }
+
+    // Between voxel centers.
+    let z_values = |interpolation| {
+      let resampled = volume.resample([1.0, 1.0, 1.0], interpolation).unwrap();
+      (0..4).map(|z| resampled.get(0, 0, z).unwrap()).collect::<Vec<_>>()
+    };
+    let voxels = |values: &[u8]| values.iter().map(|value| Voxel::U8(*value)).collect::<Vec<_>>();
+    assert_eq!(z_values(Interpolation::Nearest), voxels(&[0, 40, 40, 40]));
+    assert_eq!(z_values(Interpolation::Trilinear), voxels(&[0, 20, 40, 40]));
+    assert_eq!(z_values(Interpolation::Cubic), voxels(&[0, 20, 40, 40]));
+
+    assert_eq!(
+      volume.resample([1.0, 0.0, 1.0], Interpolation::Nearest),
+      Err(MedvizErr::new_invalid_spacing(String::from("1,0,1")))
+    );
+    for spacing in [1e-300, 1e-6] {
+      assert_eq!(
+        volume.resample([spacing, spacing, spacing], Interpolation::Nearest),
+        Err(MedvizErr::new_resample_too_large(format!(
+          "{:?},{:?},{:?}",
+          spacing, spacing, spacing
+        )))
+      );
+    }
+  }
+
+  #[test]
+  fn write_raw() {
+    let data = [1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 7, 0, 8, 0];
+    let volume = Volume::from_slice(metadata("MET_USHORT"), &data).unwrap();
+
+    let mut raw = Vec::new();
+    volume.write_raw(&mut raw).unwrap();
+    assert_eq!(raw, data);
+    assert_eq!(Volume::from_slice(volume.metadata().clone(), &raw).unwrap(), volume);
+  }
+
+  #[test]
+  fn from_slice_size_mismatch() {
+    let data = [0u8; 8];
+    let err = Volume::from_slice(metadata("MET_USHORT"), &data);
+    assert_eq!(err, Err(MedvizErr::DataSizeMismatch { actual: 8, expected: 16 }));
+    assert!(Volume::from_slice(metadata("MET_UCHAR"), &data).is_ok());
+  }
+
+  #[test]
+  fn zframe_uchar() {
+    let data = [0, 1, 2, 3, 4, 5, 6, 7];
+    let volume = Volume::from_slice(metadata("MET_UCHAR"), &data).unwrap();
+    let frame: Vec<_> =
+      volume.zframe(1).unwrap().map(|(voxel, x, y)| (voxel.unwrap(), x, y)).collect();
+    assert_eq!(
+      frame,
+      vec![(Voxel::U8(4), 0, 0), (Voxel::U8(5), 1, 0), (Voxel::U8(6), 0, 1), (Voxel::U8(7), 1, 1)]
+    );
+  }
+
+  #[test]
+  fn xframe_short() {
+    let data: Vec<u8> = (-4i16..4).flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    let volume = Volume::from_slice(metadata("MET_SHORT"), &data).unwrap();
+    let frame: Vec<_> = volume.xframe(1).unwrap().map(|(voxel, _, _)| voxel.unwrap()).collect();
+    assert_eq!(frame, vec![Voxel::I16(1), Voxel::I16(3), Voxel::I16(-3), Voxel::I16(-1)]);
+  }
+
+  #[test]
+  fn yframe_float() {
+    let data: Vec<u8> = (0..8).flat_map(|value| (value as f32).to_le_bytes().to_vec()).collect();
+    let volume = Volume::from_slice(metadata("MET_FLOAT"), &data).unwrap();
+    let frame: Vec<_> = volume.yframe(0).unwrap().map(|(voxel, _, _)| voxel.unwrap()).collect();
+    assert_eq!(frame, vec![Voxel::F32(4.0), Voxel::F32(5.0), Voxel::F32(0.0), Voxel::F32(1.0)]);
+  }
+
+  #[test]
+  fn msb_ushort() {
+    let values = [0u16, 1, 0x0102, 4095, 4096, 0x8000, 0xfffe, 0xffff];
+    let le: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    let be: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes().to_vec()).collect();
+    let le_volume = Volume::from_slice(metadata("MET_USHORT"), &le).unwrap();
+    let be_volume = Volume::from_slice(metadata_msb("MET_USHORT"), &be).unwrap();
+    assert_eq!(all_frames(&le_volume), all_frames(&be_volume));
+
+    let frame: Vec<_> = be_volume.zframe(0).unwrap().map(|(voxel, _, _)| voxel.unwrap()).collect();
+    assert_eq!(frame, vec![Voxel::U16(0), Voxel::U16(1), Voxel::U16(0x0102), Voxel::U16(4095)]);
+  }
+
+  #[test]
+  fn msb_int() {
+    let values = [-5i32, 1, 0x01020304, -0x01020304, i32::MIN, i32::MAX, 0, 7];
+    let le: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    let be: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes().to_vec()).collect();
+    let le_volume = Volume::from_slice(metadata("MET_INT"), &le).unwrap();
+    let be_volume = Volume::from_slice(metadata_msb("MET_INT"), &be).unwrap();
+    assert_eq!(all_frames(&le_volume), all_frames(&be_volume));
+  }
+
+  #[test]
+  fn msb_double() {
+    let values = [-1.5f64, 0.0, 1.0e-3, 2.0, 3071.0, -1024.0, 0.5, 8.25];
+    let le: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    let be: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes().to_vec()).collect();
+    let le_volume = Volume::from_slice(metadata("MET_DOUBLE"), &le).unwrap();
+    let be_volume = Volume::from_slice(metadata_msb("MET_DOUBLE"), &be).unwrap();
+    assert_eq!(all_frames(&le_volume), all_frames(&be_volume));
+  }
+
+  #[test]
+  fn frame_index_out_of_range() {
+    let data = [0u8; 24];
+    let input = "DimSize = 2 3 4\nElementType = MET_UCHAR\n";
+    let volume = Volume::from_slice(VolumeMd::from_buffer(input).unwrap(), &data).unwrap();
+
+    assert!(volume.xframe(1).is_ok());
+    assert!(volume.yframe(2).is_ok());
+    assert!(volume.zframe(3).is_ok());
+
+    let err = volume.xframe(2).err();
+    assert_eq!(err, Some(MedvizErr::FrameIndexOutOfRange { axis: Axis::X, index: 2, len: 2 }));
+    let err = volume.yframe(3).err();
+    assert_eq!(err, Some(MedvizErr::FrameIndexOutOfRange { axis: Axis::Y, index: 3, len: 3 }));
+    let err = volume.zframe(usize::MAX).err();
+    assert_eq!(
+      err,
+      Some(MedvizErr::FrameIndexOutOfRange { axis: Axis::Z, index: usize::MAX, len: 4 })
+    );
+  }
+
+  #[test]
+  fn frame() {
+    let data: Vec<u8> = (0..24).collect();
+    let input = "DimSize = 2 3 4\nElementType = MET_UCHAR\n";
+    let volume = Volume::from_slice(VolumeMd::from_buffer(input).unwrap(), &data).unwrap();
+
+    for axis in &Axis::ALL {
+      let len = volume.metadata().dim(*axis);
+      for index in 0..len {
+        let expected: Vec<_> = match axis {
+          Axis::X => volume.xframe(index).unwrap().map(|(v, x, y)| (v.unwrap(), x, y)).collect(),
+          Axis::Y => volume.yframe(index).unwrap().map(|(v, x, y)| (v.unwrap(), x, y)).collect(),
+          Axis::Z => volume.zframe(index).unwrap().map(|(v, x, y)| (v.unwrap(), x, y)).collect(),
+        };
+        let actual: Vec<_> =
+          volume.frame(*axis, index).unwrap().map(|(v, x, y)| (v.unwrap(), x, y)).collect();
+        assert_eq!(actual, expected);
+      }
+      assert!(volume.frame(*axis, len).is_err());
+    }
+  }
+
+  #[test]
+  fn frames() {
+    let data: Vec<u8> = (0..24).collect();
+    let input = "DimSize = 2 3 4\nElementType = MET_UCHAR\n";
+    let volume = Volume::from_slice(VolumeMd::from_buffer(input).unwrap(), &data).unwrap();
+
+    for axis in &Axis::ALL {
+      let frames: Vec<_> = volume.frames(*axis).collect();
+      assert_eq!(frames.len(), volume.metadata().dim(*axis));
+
+      for (index, frame) in frames {
+        let expected: Vec<_> = volume.frame(*axis, index).unwrap().map(|(v, _, _)| v).collect();
+        assert_eq!(frame.map(|(v, _, _)| v).collect::<Vec<_>>(), expected);
+      }
+    }
+
+    let zvalues: Vec<_> = volume
+      .frames(Axis::Z)
+      .flat_map(|(_, frame)| frame.map(|(v, _, _)| v.unwrap().value()))
+      .collect();
+    assert_eq!(zvalues, (0..24).map(f64::from).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn oblique_frame_axis_aligned() {
+    let data = [0, 1, 2, 3, 4, 5, 6, 7];
+    let volume = Volume::from_slice(metadata("MET_UCHAR"), &data).unwrap();
+
+    let zframe: Vec<_> =
+      volume.zframe(1).unwrap().map(|(voxel, x, y)| (voxel.unwrap(), x, y)).collect();
+    for &interpolation in &Interpolation::ALL {
+      let frame: Vec<_> = volume
+        .oblique_frame([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 2.0, 0.0], 2, 2, 1.0, interpolation)
+        .unwrap()
+        .map(|(voxel, x, y)| (voxel.unwrap(), x, y))
+        .collect();
+      assert_eq!(frame, zframe);
+    }
+  }
+
+  #[test]
+  fn oblique_frame_interpolation() {
+    let input = "DimSize = 2 2 2\nElementType = MET_FLOAT\nElementSpacing = 2 2 2\n";
+    let data: Vec<u8> = (0..8).flat_map(|value| (value as f32).to_le_bytes().to_vec()).collect();
+    let volume = Volume::from_slice(VolumeMd::from_buffer(input).unwrap(), &data).unwrap();
+
+    let sample = |origin, interpolation| {
+      let mut frame = volume
+        .oblique_frame(origin, [1.0, 1.0, 0.0], [0.0, 0.0, 1.0], 1, 1, 1.0, interpolation)
+        .unwrap();
+      frame.next().unwrap().0.unwrap()
+    };
+
+    assert_eq!(sample([1.0, 1.0, 1.0], Interpolation::Trilinear), Voxel::F32(3.5));
+    assert_eq!(sample([1.0, 0.5, 0.5], Interpolation::Trilinear), Voxel::F32(2.0));
+    assert_eq!(sample([1.2, 0.8, 1.2], Interpolation::Nearest), Voxel::F32(5.0));
+
+    // Outside the volume.
+    assert_eq!(sample([-1.0, 0.0, 0.0], Interpolation::Nearest), Voxel::F32(-1024.0));
+    assert_eq!(sample([0.0, 0.0, 2.5], Interpolation::Trilinear), Voxel::F32(-1024.0));
+  }
+
+  #[test]
+  fn oblique_frame_cubic_region() {
+    // The frame covers a few voxels in the middle of a long line, so
+    // the spline is only computed around them.
+    let values: Vec<f32> = (0..80).map(|index| ((index * 37) % 11) as f32).collect();
+    let data: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    let input = "DimSize = 80 1 1\nElementType = MET_FLOAT\n";
+    let volume = Volume::from_slice(VolumeMd::from_buffer(input).unwrap(), &data).unwrap();
+    let spline = BSpline::new([80, 1, 1], values.iter().map(|value| f64::from(*value)));
+
+    let frame = volume
+      .oblique_frame(
+        [40.25, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        4,
+        1,
+        0.5,
+        Interpolation::Cubic,
+      )
+      .unwrap();
+    for (voxel, u, _) in frame {
+      let expected = spline.value([40.25 + u as f64 * 0.5, 0.0, 0.0]);
+      assert!((voxel.unwrap().value() - expected).abs() < 1e-4, "{} at {}", expected, u);
+    }
+  }
+
+  #[test]
+  fn oblique_frame_invalid() {
+    let data = [0; 8];
+    let volume = Volume::from_slice(metadata("MET_UCHAR"), &data).unwrap();
+    let frame = |u_axis, step| {
+      volume
+        .oblique_frame([0.0; 3], u_axis, [0.0, 1.0, 0.0], 2, 2, step, Interpolation::Nearest)
+        .err()
+    };
+
+    assert_eq!(
+      frame([0.0; 3], 1.0),
+      Some(MedvizErr::InvalidObliqueFrame { reason: "The u-axis has no direction" })
+    );
+    assert_eq!(
+      frame([1.0, 0.0, 0.0], 0.0),
+      Some(MedvizErr::InvalidObliqueFrame { reason: "The step is not positive" })
+    );
+    assert_eq!(frame([1.0, 0.0, 0.0], 1.0), None);
+
+    let too_large = |width, height| {
+      volume
+        .oblique_frame(
+          [0.0; 3],
+          [1.0, 0.0, 0.0],
+          [0.0, 1.0, 0.0],
+          width,
+          height,
+          1.0,
+          Interpolation::Nearest,
+        )
+        .err()
+    };
+    let reason = "The frame is too large";
+    assert_eq!(too_large(usize::MAX, 2), Some(MedvizErr::InvalidObliqueFrame { reason }));
+    assert_eq!(too_large(1 << 31, 1 << 31), Some(MedvizErr::InvalidObliqueFrame { reason }));
+  }
+
+  #[test]
+  fn projection() {
+    let data = [0, 7, 2, 3, 4, 5, 6, 1];
+    let volume = Volume::from_slice(metadata("MET_UCHAR"), &data).unwrap();
+    let values = |axis, projection| {
+      let frame = volume.projection(axis, projection).unwrap();
+      frame.iter().map(|(voxel, _, _)| voxel.unwrap()).collect::<Vec<_>>()
+    };
+
+    let voxels = |values: &[u8]| values.iter().map(|value| Voxel::U8(*value)).collect::<Vec<_>>();
+    assert_eq!(values(Axis::Z, Projection::Maximum), voxels(&[4, 7, 6, 3]));
+    assert_eq!(values(Axis::Z, Projection::Minimum), voxels(&[0, 5, 2, 1]));
+    assert_eq!(values(Axis::Z, Projection::Average), voxels(&[2, 6, 4, 2]));
+    assert_eq!(values(Axis::X, Projection::Maximum), voxels(&[5, 6, 7, 3]));
+  }
+
+  #[test]
+  fn slab_projection() {
+    let data = [0, 7, 2, 3, 4, 5, 6, 1];
+    let volume = Volume::from_slice(metadata("MET_UCHAR"), &data).unwrap();
+
+    let frame = volume.slab_projection(Axis::Y, Projection::Maximum, 1..2).unwrap();
+    let projected: Vec<_> = frame.iter().map(|(voxel, _, _)| voxel.unwrap()).collect();
+    let yframe: Vec<_> = volume.yframe(1).unwrap().map(|(voxel, _, _)| voxel.unwrap()).collect();
+    assert_eq!(projected, yframe);
+
+    for slab in [1..1, 0..3] {
+      let err = volume.slab_projection(Axis::Y, Projection::Maximum, slab.clone()).err();
+      assert_eq!(err, Some(MedvizErr::new_invalid_slab(Axis::Y, slab.start, slab.end, 2)));
+    }
+  }
+
+  #[test]
+  fn get() {
+    let data: Vec<u8> = (0..8i16).flat_map(|value| (value - 4).to_be_bytes().to_vec()).collect();
+    let volume = Volume::from_slice(metadata_msb("MET_SHORT"), &data).unwrap();
+    assert_eq!(volume.get(0, 0, 0), Ok(Voxel::I16(-4)));
+    assert_eq!(volume.get(1, 0, 1), Ok(Voxel::I16(1)));
+    assert_eq!(volume.get_index(VoxelIndex::new(1, 1, 1)), Ok(Voxel::I16(3)));
+    assert_eq!(volume.get(0, 2, 0), Err(MedvizErr::new_voxel_out_of_range(0, 2, 0)));
+  }
+
+  #[test]
+  fn neighbors() {
+    let data = [0, 1, 2, 3, 4, 5, 6, 7];
+    let volume = Volume::from_slice(metadata("MET_UCHAR"), &data).unwrap();
+
+    let neighbors: Vec<_> =
+      volume.neighbors(VoxelIndex::new(1, 1, 0), Connectivity::Six).unwrap().collect();
+    assert_eq!(
+      neighbors,
+      vec![
+        (VoxelIndex::new(1, 0, 0), Voxel::U8(1)),
+        (VoxelIndex::new(0, 1, 0), Voxel::U8(2)),
+        (VoxelIndex::new(1, 1, 1), Voxel::U8(7)),
+      ]
+    );
+
+    let neighbors = volume.neighbors(VoxelIndex::new(0, 0, 0), Connectivity::TwentySix).unwrap();
+    let values: Vec<_> = neighbors.map(|(_, voxel)| voxel).collect();
+    assert_eq!(values, (1..8).map(Voxel::U8).collect::<Vec<_>>());
+
+    assert!(volume.neighbors(VoxelIndex::new(2, 0, 0), Connectivity::Six).is_err());
+  }
 }
@@ -0,0 +1,385 @@
+//! Handles volumes held in memory. The primary structure is the
+//! [owned volume struct](VolumeBuf).
+
+use crate::index::{Connectivity, VoxelIndex};
+use crate::Axis;
+use crate::FrameIter;
+use crate::MedvizErr;
+use crate::VolumeMd;
+use crate::Voxel;
+use std::mem;
+
+/// Volume data owned in memory.
+///
+/// Unlike a [volume](crate::Volume), which borrows the encoded bytes
+/// of a data file, an owned volume holds decoded voxels and can be
+/// built and modified in memory.
+///
+/// The voxels are always of the element type of the metadata, and the
+/// metadata always describes little-endian data so that it matches
+/// the bytes produced by [`to_bytes`](VolumeBuf::to_bytes).
+#[derive(Debug, PartialEq, Clone)]
+pub struct VolumeBuf {
+  /// Metadata related to the volume.
+  metadata: VolumeMd,
+
+  /// Voxels, X varying fastest and Z slowest.
+  voxels: Vec<Voxel>,
+}
+
+impl VolumeBuf {
+  /// Create an [owned volume](VolumeBuf) with all voxels set to zero.
+  ///
+  /// # Arguments
+  ///
+  /// * `metadata` - Metadata related to the volume.
+  ///
+  /// # Returns
+  ///
+  /// An [owned volume](VolumeBuf) or [an error](Err) in case the
+  /// volume is too large to hold in memory.
+  pub fn new(metadata: VolumeMd) -> Result<Self, MedvizErr> {
+    let len = metadata.voxel_count()?;
+    if len.checked_mul(mem::size_of::<Voxel>()).is_none_or(|size| size > isize::MAX as usize) {
+      return Err(MedvizErr::new_volume_too_large(
+        metadata.xdim(),
+        metadata.ydim(),
+        metadata.zdim(),
+      ));
+    }
+
+    let voxels = vec![Voxel::zero(metadata.element_type()); len];
+    Ok(Self { metadata: metadata.with_byte_order_msb(false), voxels })
+  }
+
+  /// Create an [owned volume](VolumeBuf) from metadata and voxels.
+  ///
+  /// # Arguments
+  ///
+  /// * `metadata` - Metadata related to the volume.
+  ///
+  /// * `voxels` - The voxels, X varying fastest and Z slowest. Voxels
+  ///   that are not of the element type of `metadata` are converted
+  ///   (see [`Voxel::with_value`]).
+  ///
+  /// # Returns
+  ///
+  /// An [owned volume](VolumeBuf) or [an error](Err) in case the
+  /// number of voxels does not match the dimensions in `metadata`.
+  pub fn from_voxels(metadata: VolumeMd, voxels: Vec<Voxel>) -> Result<Self, MedvizErr> {
+    let expected = metadata.voxel_count()?;
+
+    if voxels.len() != expected {
+      return Err(MedvizErr::new_data_size_mismatch(voxels.len(), expected));
+    }
+
+    let zero = Voxel::zero(metadata.element_type());
+    let voxels = voxels
+      .into_iter()
+      .map(|voxel| if voxel.same_type(&zero) { voxel } else { zero.with_value(voxel.value()) })
+      .collect();
+
+    Ok(Self { metadata: metadata.with_byte_order_msb(false), voxels })
+  }
+
+  /// Metadata related to the volume.
+  pub fn metadata(&self) -> &VolumeMd {
+    &self.metadata
+  }
+
+  /// All voxels, X varying fastest and Z slowest.
+  pub fn voxels(&self) -> &[Voxel] {
+    &self.voxels
+  }
+
+  /// The position of a voxel in the voxels, or an error in case it is
+  /// outside the volume.
+  fn voxel_index(&self, x: usize, y: usize, z: usize) -> Result<usize, MedvizErr> {
+    let (xdim, ydim, zdim) = (self.metadata.xdim(), self.metadata.ydim(), self.metadata.zdim());
+
+    if x >= xdim || y >= ydim || z >= zdim {
+      return Err(MedvizErr::new_voxel_out_of_range(x, y, z));
+    }
+
+    Ok((z * ydim + y) * xdim + x)
+  }
+
+  /// The voxel at `x`, `y` and `z`, or [an error](Err) in case the
+  /// position is outside the volume.
+  pub fn get(&self, x: usize, y: usize, z: usize) -> Result<Voxel, MedvizErr> {
+    Ok(self.voxels[self.voxel_index(x, y, z)?])
+  }
+
+  /// The voxel at a [voxel index](VoxelIndex), or [an error](Err) in
+  /// case the position is outside the volume.
+  pub fn get_index(&self, index: VoxelIndex) -> Result<Voxel, MedvizErr> {
+    self.get(index.x, index.y, index.z)
+  }
+
+  /// Create an iterator over the neighbors of a voxel.
+  ///
+  /// # Arguments
+  ///
+  /// * `index` - The position of the voxel.
+  ///
+  /// * `connectivity` - Which voxels count as neighbors.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the neighbors inside the volume and their
+  /// positions, or [an error](Err) in case `index` is outside the
+  /// volume.
+  pub fn neighbors(
+    &self,
+    index: VoxelIndex,
+    connectivity: Connectivity,
+  ) -> Result<impl Iterator<Item = (VoxelIndex, Voxel)> + '_, MedvizErr> {
+    self.get_index(index)?;
+
+    Ok(
+      index
+        .neighbors(connectivity, self.metadata.dims())
+        .map(move |neighbor| (neighbor, self.voxel(neighbor.x, neighbor.y, neighbor.z))),
+    )
+  }
+
+  /// Change the voxel at `x`, `y` and `z`.
+  ///
+  /// A voxel that is not of the element type of the volume is
+  /// converted (see [`Voxel::with_value`]).
+  ///
+  /// # Returns
+  ///
+  /// [An error](Err) in case the position is outside the volume.
+  pub fn set(&mut self, x: usize, y: usize, z: usize, voxel: Voxel) -> Result<(), MedvizErr> {
+    let index = self.voxel_index(x, y, z)?;
+    let current = &mut self.voxels[index];
+
+    *current = if voxel.same_type(current) { voxel } else { current.with_value(voxel.value()) };
+
+    Ok(())
+  }
+
+  /// Encode the voxels as little-endian bytes.
+  ///
+  /// Together with the [metadata](VolumeBuf::metadata), the bytes can
+  /// be used to create a borrowed [volume](crate::Volume) with
+  /// [`Volume::from_slice`](crate::Volume::from_slice).
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(self.voxels.len() * self.metadata.element_type().size());
+
+    for voxel in &self.voxels {
+      // Writing to a vector cannot fail.
+      voxel.write_le_bytes(&mut bytes).unwrap();
+    }
+
+    bytes
+  }
+
+  /// The voxel at `x`, `y` and `z`, which must be inside the volume.
+  fn voxel(&self, x: usize, y: usize, z: usize) -> Voxel {
+    self.voxels[(z * self.metadata.ydim() + y) * self.metadata.xdim() + x]
+  }
+
+  /// Create an iterator over the voxels in a frame, given the number
+  /// of voxels in a row, the number of rows and the voxel at each
+  /// coordinate.
+  fn frame_iter<'v>(
+    width: usize,
+    height: usize,
+    voxel: impl Fn(usize, usize) -> Voxel + 'v,
+  ) -> impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + 'v {
+    (0..width * height).map(move |index| {
+      // `index` is below width * height, so width is not zero.
+      let (col, row) = (index % width, index / width);
+      (Ok(voxel(col, row)), col, row)
+    })
+  }
+
+  /// Create an iterator over the voxels in a frame on the X-axis.
+  ///
+  /// Produces the same voxels and coordinates as
+  /// [`Volume::xframe`](crate::Volume::xframe).
+  ///
+  /// # Arguments
+  ///
+  /// * `xframe_index` - The index of the frame on the X-axis.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the voxels in the frame and their corresponding
+  /// coordinates, or [an error](Err) in case `xframe_index` is
+  /// outside the range of frames.
+  pub fn xframe(
+    &self,
+    xframe_index: usize,
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + '_, MedvizErr> {
+    self.metadata.check_frame_index(Axis::X, xframe_index)?;
+
+    // Frames on the Z-axis are gone over in reverse, like in
+    // Volume::xframe.
+    let zdim = self.metadata.zdim();
+    Ok(Self::frame_iter(self.metadata.ydim(), zdim, move |y, row| {
+      self.voxel(xframe_index, y, zdim - 1 - row)
+    }))
+  }
+
+  /// Create an iterator over the voxels in a frame on the Y-axis.
+  ///
+  /// Produces the same voxels and coordinates as
+  /// [`Volume::yframe`](crate::Volume::yframe).
+  ///
+  /// # Arguments
+  ///
+  /// * `yframe_index` - The index of the frame on the Y-axis.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the voxels in the frame and their corresponding
+  /// coordinates, or [an error](Err) in case `yframe_index` is
+  /// outside the range of frames.
+  pub fn yframe(
+    &self,
+    yframe_index: usize,
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + '_, MedvizErr> {
+    self.metadata.check_frame_index(Axis::Y, yframe_index)?;
+
+    // Frames on the Z-axis are gone over in reverse, like in
+    // Volume::yframe.
+    let zdim = self.metadata.zdim();
+    Ok(Self::frame_iter(self.metadata.xdim(), zdim, move |x, row| {
+      self.voxel(x, yframe_index, zdim - 1 - row)
+    }))
+  }
+
+  /// Create an iterator over the voxels in a frame on the Z-axis.
+  ///
+  /// Produces the same voxels and coordinates as
+  /// [`Volume::zframe`](crate::Volume::zframe).
+  ///
+  /// # Arguments
+  ///
+  /// * `zframe_index` - The index of the frame on the Z-axis.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the voxels in the frame and their corresponding
+  /// coordinates, or [an error](Err) in case `zframe_index` is
+  /// outside the range of frames.
+  pub fn zframe(
+    &self,
+    zframe_index: usize,
+  ) -> Result<impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)> + '_, MedvizErr> {
+    self.metadata.check_frame_index(Axis::Z, zframe_index)?;
+
+    Ok(Self::frame_iter(self.metadata.xdim(), self.metadata.ydim(), move |x, y| {
+      self.voxel(x, y, zframe_index)
+    }))
+  }
+
+  /// Create an iterator over the voxels in a frame on an axis.
+  ///
+  /// Dispatches to [`xframe`](VolumeBuf::xframe),
+  /// [`yframe`](VolumeBuf::yframe) or [`zframe`](VolumeBuf::zframe).
+  ///
+  /// # Arguments
+  ///
+  /// * `axis` - The axis the frame is on.
+  ///
+  /// * `index` - The index of the frame on `axis`.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the voxels in the frame and their corresponding
+  /// coordinates, or [an error](Err) in case `index` is outside the
+  /// range of frames.
+  pub fn frame(&self, axis: Axis, index: usize) -> Result<FrameIter<'_>, MedvizErr> {
+    Ok(match axis {
+      Axis::X => Box::new(self.xframe(index)?),
+      Axis::Y => Box::new(self.yframe(index)?),
+      Axis::Z => Box::new(self.zframe(index)?),
+    })
+  }
+}
+
+#[cfg(test)]
+mod volume_buf_tests {
+  use super::VolumeBuf;
+  use crate::metadata::ElementType;
+  use crate::{Axis, MedvizErr, Volume, VolumeMd, Voxel};
+
+  fn metadata(input: &str) -> VolumeMd {
+    VolumeMd::from_buffer(input).unwrap()
+  }
+
+  fn frame_voxels(
+    frame: impl Iterator<Item = (Result<Voxel, MedvizErr>, usize, usize)>,
+  ) -> Vec<(Voxel, usize, usize)> {
+    frame.map(|(voxel, x, y)| (voxel.unwrap(), x, y)).collect()
+  }
+
+  #[test]
+  fn new() {
+    let metadata = VolumeMd::new(2, 3, 4).with_element_type(ElementType::Short);
+    let volume = VolumeBuf::new(metadata).unwrap();
+    assert_eq!(volume.voxels().len(), 24);
+    assert_eq!(volume.get(1, 2, 3), Ok(Voxel::I16(0)));
+
+    let too_large = VolumeBuf::new(VolumeMd::new(1 << 21, 1 << 20, 1 << 20));
+    assert_eq!(too_large, Err(MedvizErr::new_volume_too_large(1 << 21, 1 << 20, 1 << 20)));
+  }
+
+  #[test]
+  fn get_set() {
+    let mut volume = VolumeBuf::new(VolumeMd::new(2, 2, 2)).unwrap();
+    volume.set(1, 0, 1, Voxel::U16(42)).unwrap();
+    volume.set(0, 1, 0, Voxel::F32(-3.0)).unwrap();
+    assert_eq!(volume.get(1, 0, 1), Ok(Voxel::U16(42)));
+    assert_eq!(volume.get(0, 1, 0), Ok(Voxel::U16(0)));
+    assert_eq!(volume.voxels()[5], Voxel::U16(42));
+
+    assert_eq!(volume.get(2, 0, 0), Err(MedvizErr::new_voxel_out_of_range(2, 0, 0)));
+    assert_eq!(volume.set(0, 0, 2, Voxel::U16(1)), Err(MedvizErr::new_voxel_out_of_range(0, 0, 2)));
+  }
+
+  #[test]
+  fn from_voxels_size_mismatch() {
+    let err = VolumeBuf::from_voxels(VolumeMd::new(2, 2, 2), vec![Voxel::U16(0); 7]);
+    assert_eq!(err, Err(MedvizErr::DataSizeMismatch { actual: 7, expected: 8 }));
+  }
+
+  #[test]
+  fn frames_match_volume() {
+    let metadata =
+      metadata("DimSize = 3 2 4\nElementType = MET_SHORT\nElementByteOrderMSB = True\n");
+    let data: Vec<u8> =
+      (0..24i16).flat_map(|value| (value * 100 - 1000).to_be_bytes().to_vec()).collect();
+    let volume = Volume::from_slice(metadata, &data).unwrap();
+    let buf = volume.to_buf();
+
+    for &axis in &Axis::ALL {
+      for index in 0..volume.metadata().dim(axis) {
+        let expected = frame_voxels(volume.frame(axis, index).unwrap());
+        assert_eq!(frame_voxels(buf.frame(axis, index).unwrap()), expected);
+      }
+    }
+
+    assert!(buf.xframe(3).is_err());
+    assert!(buf.yframe(2).is_err());
+    assert!(buf.zframe(4).is_err());
+  }
+
+  #[test]
+  fn to_volume() {
+    let mut buf =
+      VolumeBuf::new(VolumeMd::new(2, 2, 2).with_element_type(ElementType::Int)).unwrap();
+    buf.set(1, 1, 1, Voxel::I32(-7)).unwrap();
+
+    let bytes = buf.to_bytes();
+    let volume = Volume::from_slice(buf.metadata().clone(), &bytes).unwrap();
+    assert_eq!(volume.to_buf(), buf);
+
+    let zframe = frame_voxels(volume.zframe(1).unwrap());
+    assert_eq!(zframe[3], (Voxel::I32(-7), 1, 1));
+  }
+}
@@ -1,110 +1,357 @@
 //! Handles voxels.
 
-use crate::MedvizErr;
-use std::mem;
+use crate::metadata::ElementType;
+use crate::window::Window;
+use std::convert::TryInto;
+use std::io::{self, Write};
 
 /// A voxel.
-#[derive(Clone, Copy)]
-pub struct Voxel(u16);
+///
+/// The variant is determined by the [element type](ElementType) of
+/// the volume the voxel comes from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Voxel {
+  /// Signed 8-bit voxel (`MET_CHAR`).
+  I8(i8),
 
-impl Voxel {
-  /// Create a voxel.
-  ///
-  /// Returns an error if the provided value is out of the 0-4095
-  /// (12-bit) range.
-  pub fn from(value: u16) -> Result<Self, MedvizErr> {
-    // Voxels are actually 12-bits wide. Fail if the value is out of
-    // range.
-    if value > 4095 {
-      return Err(MedvizErr::new_voxel_value_oor(value));
-    }
+  /// Unsigned 8-bit voxel (`MET_UCHAR`).
+  U8(u8),
 
-    Ok(Self(value))
-  }
+  /// Signed 16-bit voxel (`MET_SHORT`).
+  I16(i16),
+
+  /// Unsigned 16-bit voxel (`MET_USHORT`).
+  U16(u16),
+
+  /// Signed 32-bit voxel (`MET_INT` and `MET_LONG`).
+  I32(i32),
+
+  /// Unsigned 32-bit voxel (`MET_UINT` and `MET_ULONG`).
+  U32(u32),
+
+  /// Signed 64-bit voxel (`MET_LONG_LONG`).
+  I64(i64),
+
+  /// Unsigned 64-bit voxel (`MET_ULONG_LONG`).
+  U64(u64),
 
-  /// Create a voxel from an array of two bytes.
+  /// 32-bit floating point voxel (`MET_FLOAT`).
+  F32(f32),
+
+  /// 64-bit floating point voxel (`MET_DOUBLE`).
+  F64(f64),
+}
+
+impl Voxel {
+  /// Create a voxel from a byteslice.
   ///
-  /// Returns an error if the provided value is out of the 0-4095
-  /// (12-bit) range.
+  /// # Arguments
+  ///
+  /// * `element_type` - The type of the voxel.
+  ///
+  /// * `byte_order_msb` - Whether the voxel is stored with the most
+  ///   significant byte first (big-endian).
+  ///
+  /// * `slice` - The bytes of the voxel.
   ///
   /// # Notes
   ///
+  /// Panics if slice does not contain at least as many bytes as the
+  /// size of `element_type`.
+  ///
   /// This function is small and should always end up being
-  /// inlined. Furthermore, since voxels are stored in little-endian
-  /// this should compile down to a no-op on LE machines.
-  pub fn from_array(bytes: [u8; 2]) -> Result<Self, MedvizErr> {
-    Self::from(u16::from_le_bytes(bytes))
+  /// inlined. Furthermore, when voxels are stored in the native byte
+  /// order this should compile down to a no-op.
+  pub fn from_slice(element_type: ElementType, byte_order_msb: bool, slice: &[u8]) -> Self {
+    /// Read a value of type `$type` from the start of the slice.
+    macro_rules! read {
+      ($type:ty) => {{
+        const SIZE: usize = std::mem::size_of::<$type>();
+        // The slice has at least SIZE bytes, so the conversion to an
+        // array cannot fail.
+        let bytes = slice[..SIZE].try_into().unwrap();
+        if byte_order_msb {
+          <$type>::from_be_bytes(bytes)
+        } else {
+          <$type>::from_le_bytes(bytes)
+        }
+      }};
+    }
+
+    match element_type {
+      ElementType::Char => Self::I8(read!(i8)),
+      ElementType::UChar => Self::U8(read!(u8)),
+      ElementType::Short => Self::I16(read!(i16)),
+      ElementType::UShort => Self::U16(read!(u16)),
+      ElementType::Int | ElementType::Long => Self::I32(read!(i32)),
+      ElementType::UInt | ElementType::ULong => Self::U32(read!(u32)),
+      ElementType::LongLong => Self::I64(read!(i64)),
+      ElementType::ULongLong => Self::U64(read!(u64)),
+      ElementType::Float => Self::F32(read!(f32)),
+      ElementType::Double => Self::F64(read!(f64)),
+    }
   }
 
-  /// Create a voxel from bytes.
-  pub fn from_bytes(byte0: u8, byte1: u8) -> Result<Self, MedvizErr> {
-    Self::from_array([byte0, byte1])
+  /// Create a voxel of an element type with a value of zero.
+  pub fn zero(element_type: ElementType) -> Self {
+    Self::from_slice(element_type, false, &[0; 8])
   }
 
-  /// Create a voxel from a byteslice.
-  ///
-  /// # Notes
+  /// Whether two voxels have the same type, regardless of their
+  /// values.
+  pub fn same_type(&self, other: &Self) -> bool {
+    std::mem::discriminant(self) == std::mem::discriminant(other)
+  }
+
+  /// Return the value.
   ///
-  /// Panics if slice does not contain at least 2 bytes.
+  /// 64-bit integer values may lose precision.
+  pub fn value(&self) -> f64 {
+    match *self {
+      Self::I8(value) => f64::from(value),
+      Self::U8(value) => f64::from(value),
+      Self::I16(value) => f64::from(value),
+      Self::U16(value) => f64::from(value),
+      Self::I32(value) => f64::from(value),
+      Self::U32(value) => f64::from(value),
+      Self::I64(value) => value as f64,
+      Self::U64(value) => value as f64,
+      Self::F32(value) => f64::from(value),
+      Self::F64(value) => value,
+    }
+  }
+
+  /// Create a voxel of the same type with a different value.
   ///
-  /// Returns an error if the provided value is out of the 0-4095
-  /// (12-bit) range.
-  pub fn from_slice(slice: &[u8]) -> Result<Self, MedvizErr> {
-    Self::from_array([slice[0], slice[1]])
+  /// Integer voxels round `value` and clamp it to the range of their
+  /// type.
+  pub fn with_value(&self, value: f64) -> Self {
+    // Float to integer casts saturate, which does the clamping.
+    match self {
+      Self::I8(_) => Self::I8(value.round() as i8),
+      Self::U8(_) => Self::U8(value.round() as u8),
+      Self::I16(_) => Self::I16(value.round() as i16),
+      Self::U16(_) => Self::U16(value.round() as u16),
+      Self::I32(_) => Self::I32(value.round() as i32),
+      Self::U32(_) => Self::U32(value.round() as u32),
+      Self::I64(_) => Self::I64(value.round() as i64),
+      Self::U64(_) => Self::U64(value.round() as u64),
+      Self::F32(_) => Self::F32(value as f32),
+      Self::F64(_) => Self::F64(value),
+    }
   }
 
-  /// Return the value.
-  pub fn value(&self) -> u16 {
-    self.0
+  /// The range of values that is mapped onto the normalized range.
+  ///
+  /// 8-bit voxels use their full range. Wider voxels are assumed to
+  /// hold 12-bit data as produced by most scanners: 0-4095 when
+  /// unsigned and -1024-3071 (Hounsfield units) when signed or
+  /// floating point.
+  pub fn nominal_range(&self) -> (f64, f64) {
+    match self {
+      Self::I8(_) => (f64::from(i8::MIN), f64::from(i8::MAX)),
+      Self::U8(_) => (f64::from(u8::MIN), f64::from(u8::MAX)),
+      Self::U16(_) | Self::U32(_) | Self::U64(_) => (0.0, 4095.0),
+      Self::I16(_) | Self::I32(_) | Self::I64(_) | Self::F32(_) | Self::F64(_) => (-1024.0, 3071.0),
+    }
   }
 
   /// Return the normalized value of a voxel to `u8`.
+  ///
+  /// Values outside the [nominal range](Voxel::nominal_range) are
+  /// clamped.
   pub fn value_normalized(&self) -> u8 {
-    const VOXEL_MAX: f32 = 4095.0;
-    const VOXEL_NORMALIZED_MAX: f32 = 255.0;
+    let (min, max) = self.nominal_range();
+    self.value_windowed(&Window::from_range(min, max))
+  }
 
-    let value = f32::from(self.0);
-    let normalized = ((value / VOXEL_MAX) * VOXEL_NORMALIZED_MAX).round();
+  /// Return the value of a voxel mapped through a
+  /// [window](Window) to `u8`.
+  ///
+  /// Values outside the window are clamped.
+  pub fn value_windowed(&self, window: &Window) -> u8 {
+    window.apply_u8(self.value())
+  }
+
+  /// Return the value of a voxel as `u16`, keeping the full range of
+  /// 8- and 16-bit voxels.
+  ///
+  /// Unsigned voxels keep their value, while signed and floating point
+  /// voxels are rounded and offset by 32768 so that negative values
+  /// are kept (e.g. `I16(-1024)` becomes 31744). Values that do not
+  /// fit in 0-65535 are clamped, so the mapping is lossy for 32- and
+  /// 64-bit voxels and for fractional values.
+  pub fn value_u16(&self) -> u16 {
+    const SIGNED_OFFSET: f64 = 32768.0;
 
-    // We've normalized the voxel value to the range of u8 values
-    // above, so it is now safe to "cast".
-    unsafe { normalized.to_int_unchecked::<u8>() }
+    // Float to integer casts saturate, which does the clamping.
+    match *self {
+      Self::U8(value) => u16::from(value),
+      Self::U16(value) => value,
+      Self::U32(_) | Self::U64(_) => self.value() as u16,
+      Self::I8(_) | Self::I16(_) | Self::I32(_) | Self::I64(_) | Self::F32(_) | Self::F64(_) => {
+        (self.value().round() + SIGNED_OFFSET) as u16
+      }
+    }
+  }
+
+  /// Write the value of the voxel in little-endian.
+  pub fn write_le_bytes(&self, writer: &mut impl Write) -> io::Result<()> {
+    match *self {
+      Self::I8(value) => writer.write_all(&value.to_le_bytes()),
+      Self::U8(value) => writer.write_all(&value.to_le_bytes()),
+      Self::I16(value) => writer.write_all(&value.to_le_bytes()),
+      Self::U16(value) => writer.write_all(&value.to_le_bytes()),
+      Self::I32(value) => writer.write_all(&value.to_le_bytes()),
+      Self::U32(value) => writer.write_all(&value.to_le_bytes()),
+      Self::I64(value) => writer.write_all(&value.to_le_bytes()),
+      Self::U64(value) => writer.write_all(&value.to_le_bytes()),
+      Self::F32(value) => writer.write_all(&value.to_le_bytes()),
+      Self::F64(value) => writer.write_all(&value.to_le_bytes()),
+    }
   }
+}
+
+/// Apply a slope and intercept to voxels.
+///
+/// # Returns
+///
+/// The element type of the rescaled voxels and the rescaled voxels
+/// in little-endian order.
+pub(crate) fn rescale(
+  element_type: ElementType,
+  byte_order_msb: bool,
+  data: &[u8],
+  slope: f64,
+  intercept: f64,
+) -> (ElementType, Vec<u8>) {
+  let rescaled_type = match element_type {
+    ElementType::Double => ElementType::Double,
+    _ => ElementType::Float,
+  };
+
+  let mut res = Vec::with_capacity(data.len() / element_type.size() * rescaled_type.size());
 
-  /// The size of a voxel.
-  pub fn size() -> usize {
-    mem::size_of::<u16>()
+  for bytes in data.chunks_exact(element_type.size()) {
+    let value = Voxel::from_slice(element_type, byte_order_msb, bytes).value() * slope + intercept;
+    let voxel = match rescaled_type {
+      ElementType::Double => Voxel::F64(value),
+      _ => Voxel::F32(value as f32),
+    };
+
+    // Writing to a vector cannot fail.
+    voxel.write_le_bytes(&mut res).unwrap();
   }
+
+  (rescaled_type, res)
 }
 
 #[cfg(test)]
 mod voxel_tests {
   use super::Voxel;
+  use crate::metadata::ElementType;
+  use crate::window::Window;
 
   #[test]
-  fn test_voxel_create() {
-    Voxel::from(0).unwrap();
-    Voxel::from(4095).unwrap();
+  fn from_slice() {
+    assert_eq!(Voxel::from_slice(ElementType::UChar, false, &[200]), Voxel::U8(200));
+    assert_eq!(Voxel::from_slice(ElementType::Char, false, &[0xff]), Voxel::I8(-1));
+    assert_eq!(Voxel::from_slice(ElementType::UShort, false, &[0x00, 0x10]), Voxel::U16(4096));
+    assert_eq!(Voxel::from_slice(ElementType::Short, false, &[0x00, 0xfc]), Voxel::I16(-1024));
+    assert_eq!(Voxel::from_slice(ElementType::Int, false, &[1, 0, 0, 0]), Voxel::I32(1));
+    assert_eq!(Voxel::from_slice(ElementType::ULong, false, &[1, 0, 0, 0]), Voxel::U32(1));
+    assert_eq!(
+      Voxel::from_slice(ElementType::Float, false, &1.5f32.to_le_bytes()),
+      Voxel::F32(1.5)
+    );
+    assert_eq!(
+      Voxel::from_slice(ElementType::Double, false, &(-2.5f64).to_le_bytes()),
+      Voxel::F64(-2.5)
+    );
   }
 
   #[test]
-  #[should_panic]
-  fn test_voxel_create_fail() {
-    Voxel::from(4096).unwrap();
+  fn from_slice_msb() {
+    assert_eq!(Voxel::from_slice(ElementType::UChar, true, &[200]), Voxel::U8(200));
+    assert_eq!(Voxel::from_slice(ElementType::UShort, true, &[0x10, 0x00]), Voxel::U16(4096));
+    assert_eq!(Voxel::from_slice(ElementType::Short, true, &[0xfc, 0x00]), Voxel::I16(-1024));
+    assert_eq!(Voxel::from_slice(ElementType::UInt, true, &[0, 0, 1, 2]), Voxel::U32(0x0102));
+    assert_eq!(Voxel::from_slice(ElementType::Float, true, &1.5f32.to_be_bytes()), Voxel::F32(1.5));
+    assert_eq!(
+      Voxel::from_slice(ElementType::Double, true, &(-2.5f64).to_be_bytes()),
+      Voxel::F64(-2.5)
+    );
+  }
+
+  #[test]
+  fn zero() {
+    assert_eq!(Voxel::zero(ElementType::Short), Voxel::I16(0));
+    assert_eq!(Voxel::zero(ElementType::Double), Voxel::F64(0.0));
+    assert!(Voxel::zero(ElementType::UChar).same_type(&Voxel::U8(7)));
+    assert!(!Voxel::zero(ElementType::UChar).same_type(&Voxel::I8(7)));
+  }
+
+  #[test]
+  fn with_value() {
+    assert_eq!(Voxel::U8(0).with_value(300.0), Voxel::U8(255));
+    assert_eq!(Voxel::U16(0).with_value(-5.0), Voxel::U16(0));
+    assert_eq!(Voxel::I16(0).with_value(-5.6), Voxel::I16(-6));
+    assert_eq!(Voxel::F32(0.0).with_value(0.25), Voxel::F32(0.25));
   }
 
   #[test]
   fn normalize_zero() {
-    assert_eq!(Voxel::from(0).unwrap().value_normalized(), 0);
+    assert_eq!(Voxel::U16(0).value_normalized(), 0);
   }
 
   #[test]
   fn normalize_max() {
-    assert_eq!(Voxel::from(4095).unwrap().value_normalized(), 255);
+    assert_eq!(Voxel::U16(4095).value_normalized(), 255);
   }
 
   #[test]
   fn normalize_mid() {
-    assert_eq!(Voxel::from(2048).unwrap().value_normalized(), 128);
+    assert_eq!(Voxel::U16(2048).value_normalized(), 128);
+  }
+
+  #[test]
+  fn normalize_clamp() {
+    assert_eq!(Voxel::U16(4096).value_normalized(), 255);
+    assert_eq!(Voxel::I16(-2000).value_normalized(), 0);
+  }
+
+  #[test]
+  fn normalize_types() {
+    assert_eq!(Voxel::U8(255).value_normalized(), 255);
+    assert_eq!(Voxel::I8(-128).value_normalized(), 0);
+    assert_eq!(Voxel::I16(-1024).value_normalized(), 0);
+    assert_eq!(Voxel::F32(3071.0).value_normalized(), 255);
+  }
+
+  #[test]
+  fn windowed() {
+    assert_eq!(Voxel::I16(-1024).value_windowed(&Window::BRAIN), 0);
+    assert_eq!(Voxel::I16(40).value_windowed(&Window::BRAIN), 128);
+    assert_eq!(Voxel::F32(100.0).value_windowed(&Window::BRAIN), 255);
+  }
+
+  #[test]
+  fn value_u16() {
+    assert_eq!(Voxel::U8(200).value_u16(), 200);
+    assert_eq!(Voxel::U16(65535).value_u16(), 65535);
+    assert_eq!(Voxel::U32(70000).value_u16(), 65535);
+    assert_eq!(Voxel::I16(-32768).value_u16(), 0);
+    assert_eq!(Voxel::I16(-1024).value_u16(), 31744);
+    assert_eq!(Voxel::I16(32767).value_u16(), 65535);
+    assert_eq!(Voxel::I8(-1).value_u16(), 32767);
+    assert_eq!(Voxel::F32(-40000.0).value_u16(), 0);
+  }
+
+  #[test]
+  fn write_le_bytes() {
+    let mut bytes = Vec::new();
+    Voxel::U16(0x0102).write_le_bytes(&mut bytes).unwrap();
+    Voxel::I8(-1).write_le_bytes(&mut bytes).unwrap();
+    assert_eq!(bytes, vec![0x02, 0x01, 0xff]);
   }
 }
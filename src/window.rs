@@ -0,0 +1,186 @@
+//! Handles the mapping of voxel values onto display intensities. The
+//! primary structure is the [window struct](Window).
+
+use crate::MedvizErr;
+use std::fmt;
+use std::str::FromStr;
+
+/// A window (also known as window/level) over voxel values.
+///
+/// Values inside the window are mapped linearly onto the full range
+/// of display intensities, values below it are black and values above
+/// it are white. Narrow windows bring out contrast between tissues of
+/// similar density.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Window {
+  /// The voxel value at the middle of the window (level).
+  center: f64,
+
+  /// The range of voxel values covered by the window.
+  width: f64,
+}
+
+impl Window {
+  /// Brain (CT, Hounsfield units).
+  pub const BRAIN: Self = Self { center: 40.0, width: 80.0 };
+
+  /// Lung (CT, Hounsfield units).
+  pub const LUNG: Self = Self { center: -600.0, width: 1500.0 };
+
+  /// Bone (CT, Hounsfield units).
+  pub const BONE: Self = Self { center: 400.0, width: 1800.0 };
+
+  /// Abdomen soft tissue (CT, Hounsfield units).
+  pub const ABDOMEN: Self = Self { center: 40.0, width: 400.0 };
+
+  /// All the presets with their names.
+  pub const PRESETS: [(&'static str, Self); 4] = [
+    ("brain", Self::BRAIN),
+    ("lung", Self::LUNG),
+    ("bone", Self::BONE),
+    ("abdomen", Self::ABDOMEN),
+  ];
+
+  /// Create a window.
+  ///
+  /// # Arguments
+  ///
+  /// * `center` - The voxel value at the middle of the window.
+  ///
+  /// * `width` - The range of voxel values covered by the window.
+  ///
+  /// # Returns
+  ///
+  /// The window or an error in case `width` is not positive.
+  pub fn new(center: f64, width: f64) -> Result<Self, MedvizErr> {
+    if !center.is_finite() || !width.is_finite() || width <= 0.0 {
+      return Err(MedvizErr::new_invalid_window(format!("{},{}", center, width)));
+    }
+
+    Ok(Self { center, width })
+  }
+
+  /// Create a window covering the values from `min` to `max`.
+  ///
+  /// # Notes
+  ///
+  /// Panics if `max` is not greater than `min`.
+  pub fn from_range(min: f64, max: f64) -> Self {
+    assert!(max > min);
+    Self { center: (min + max) / 2.0, width: max - min }
+  }
+
+  /// Find a preset window by name (e.g. `brain`).
+  pub fn preset(name: &str) -> Option<Self> {
+    Self::PRESETS.iter().find(|(preset, _)| preset.eq_ignore_ascii_case(name)).map(|(_, w)| *w)
+  }
+
+  /// The voxel value at the middle of the window.
+  pub fn center(&self) -> f64 {
+    self.center
+  }
+
+  /// The range of voxel values covered by the window.
+  pub fn width(&self) -> f64 {
+    self.width
+  }
+
+  /// The lowest voxel value inside the window.
+  pub fn min(&self) -> f64 {
+    self.center - self.width / 2.0
+  }
+
+  /// The highest voxel value inside the window.
+  pub fn max(&self) -> f64 {
+    self.center + self.width / 2.0
+  }
+
+  /// Map a voxel value onto the window, clamped to `0.0..=1.0`.
+  ///
+  /// NaN values are mapped to `0.0`.
+  pub fn apply(&self, value: f64) -> f64 {
+    let position = (value - self.min()) / self.width;
+
+    if position.is_nan() {
+      0.0
+    } else {
+      position.clamp(0.0, 1.0)
+    }
+  }
+
+  /// Map a voxel value onto the window as an 8-bit intensity.
+  pub fn apply_u8(&self, value: f64) -> u8 {
+    const INTENSITY_MAX: f64 = 255.0;
+
+    // The value has been clamped to 0..=1 by apply, so it is now safe
+    // to "cast".
+    unsafe { (self.apply(value) * INTENSITY_MAX).round().to_int_unchecked::<u8>() }
+  }
+}
+
+impl FromStr for Window {
+  type Err = MedvizErr;
+
+  /// Parse a window.
+  ///
+  /// Accepts a center and width separated by a comma (e.g. `40,80`)
+  /// or the name of a preset (e.g. `brain`).
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let text = text.trim();
+    let invalid = || MedvizErr::new_invalid_window(text.into());
+
+    if let Some(window) = Self::preset(text) {
+      return Ok(window);
+    }
+
+    let (center, width) = text.split_once(',').ok_or_else(invalid)?;
+    let center = center.trim().parse().map_err(|_| invalid())?;
+    let width = width.trim().parse().map_err(|_| invalid())?;
+    Self::new(center, width).map_err(|_| invalid())
+  }
+}
+
+impl fmt::Display for Window {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{},{}", self.center, self.width)
+  }
+}
+
+#[cfg(test)]
+mod window_tests {
+  use super::Window;
+  use crate::MedvizErr;
+
+  #[test]
+  fn parse() {
+    assert_eq!("40,80".parse(), Ok(Window::BRAIN));
+    assert_eq!(" -600 , 1500 ".parse(), Ok(Window::LUNG));
+    assert_eq!("bone".parse(), Ok(Window::BONE));
+    assert_eq!("Abdomen".parse(), Ok(Window::ABDOMEN));
+  }
+
+  #[test]
+  fn parse_fail() {
+    for text in &["", "40", "40,", "a,b", "40,0", "40,-10", "liver"] {
+      let err = text.parse::<Window>();
+      assert_eq!(err, Err(MedvizErr::InvalidWindow { window: String::from(*text) }));
+    }
+  }
+
+  #[test]
+  fn range() {
+    let window = Window::from_range(-1024.0, 3072.0);
+    assert_eq!((window.center(), window.width()), (1024.0, 4096.0));
+    assert_eq!((Window::BRAIN.min(), Window::BRAIN.max()), (0.0, 80.0));
+  }
+
+  #[test]
+  fn apply() {
+    assert_eq!(Window::BRAIN.apply_u8(-100.0), 0);
+    assert_eq!(Window::BRAIN.apply_u8(0.0), 0);
+    assert_eq!(Window::BRAIN.apply_u8(40.0), 128);
+    assert_eq!(Window::BRAIN.apply_u8(80.0), 255);
+    assert_eq!(Window::BRAIN.apply_u8(1000.0), 255);
+    assert_eq!(Window::BRAIN.apply_u8(f64::NAN), 0);
+  }
+}
@@ -5,7 +5,7 @@ mod reference {
   use medviz::{utils, Volume, VolumeMd};
   use memmap::{Mmap, MmapOptions};
   use std::fs::File;
-  use std::io::{BufWriter, Read, Write};
+  use std::io::{BufWriter, Read};
   use std::mem;
 
   fn read_file(filename: &str) -> Vec<u8> {
@@ -24,7 +24,7 @@ mod reference {
   #[test]
   fn raw_x() {
     let (metadata, map) = md_and_map();
-    let volume = Volume::from_slice(metadata, &map).unwrap();
+    let volume = Volume::from_slice(metadata.clone(), &map).unwrap();
 
     let expected = read_file("tests/data/x.raw");
     assert_eq!(expected.len(), metadata.ydim() * metadata.zdim() * mem::size_of::<u16>());
@@ -32,9 +32,8 @@ mod reference {
     let mut actual = Vec::new();
     {
       let mut writer = BufWriter::new(&mut actual);
-      for (voxel, _, _) in volume.xframe(metadata.xdim() / 2) {
-        let voxel = voxel.unwrap();
-        writer.write_all(&voxel.value().to_le_bytes()).unwrap();
+      for (voxel, _, _) in volume.xframe(metadata.xdim() / 2).unwrap() {
+        voxel.unwrap().write_le_bytes(&mut writer).unwrap();
       }
     }
 
@@ -44,7 +43,7 @@ mod reference {
   #[test]
   fn raw_y() {
     let (metadata, map) = md_and_map();
-    let volume = Volume::from_slice(metadata, &map).unwrap();
+    let volume = Volume::from_slice(metadata.clone(), &map).unwrap();
 
     let expected = read_file("tests/data/y.raw");
     assert_eq!(expected.len(), metadata.xdim() * metadata.zdim() * mem::size_of::<u16>());
@@ -52,9 +51,8 @@ mod reference {
     let mut actual = Vec::new();
     {
       let mut writer = BufWriter::new(&mut actual);
-      for (voxel, _, _) in volume.yframe(metadata.ydim() / 2) {
-        let voxel = voxel.unwrap();
-        writer.write_all(&voxel.value().to_le_bytes()).unwrap();
+      for (voxel, _, _) in volume.yframe(metadata.ydim() / 2).unwrap() {
+        voxel.unwrap().write_le_bytes(&mut writer).unwrap();
       }
     }
 
@@ -64,7 +62,7 @@ mod reference {
   #[test]
   fn raw_z() {
     let (metadata, map) = md_and_map();
-    let volume = Volume::from_slice(metadata, &map).unwrap();
+    let volume = Volume::from_slice(metadata.clone(), &map).unwrap();
 
     let expected = read_file("tests/data/z.raw");
     assert_eq!(expected.len(), metadata.xdim() * metadata.ydim() * mem::size_of::<u16>());
@@ -72,9 +70,8 @@ mod reference {
     let mut actual = Vec::new();
     {
       let mut writer = BufWriter::new(&mut actual);
-      for (voxel, _, _) in volume.zframe(metadata.zdim() / 2) {
-        let voxel = voxel.unwrap();
-        writer.write_all(&voxel.value().to_le_bytes()).unwrap();
+      for (voxel, _, _) in volume.zframe(metadata.zdim() / 2).unwrap() {
+        voxel.unwrap().write_le_bytes(&mut writer).unwrap();
       }
     }
 
@@ -84,17 +81,23 @@ mod reference {
   #[test]
   fn bmp_x() {
     let (metadata, map) = md_and_map();
-    let volume = Volume::from_slice(metadata, &map).unwrap();
+    let volume = Volume::from_slice(metadata.clone(), &map).unwrap();
 
     let expected = read_file("tests/data/x.bmp");
 
     let mut actual = Vec::new();
     {
       let mut writer = BufWriter::new(&mut actual);
-      utils::frame_bmp(metadata.ydim(), metadata.zdim(), volume.xframe(metadata.xdim() / 2))
-        .unwrap()
-        .to_writer(&mut writer)
-        .unwrap();
+      utils::frame_bmp(
+        metadata.ydim(),
+        metadata.zdim(),
+        None,
+        None,
+        volume.xframe(metadata.xdim() / 2).unwrap(),
+      )
+      .unwrap()
+      .to_writer(&mut writer)
+      .unwrap();
     }
 
     assert_eq!(actual, expected);
@@ -103,17 +106,23 @@ mod reference {
   #[test]
   fn bmp_y() {
     let (metadata, map) = md_and_map();
-    let volume = Volume::from_slice(metadata, &map).unwrap();
+    let volume = Volume::from_slice(metadata.clone(), &map).unwrap();
 
     let expected = read_file("tests/data/y.bmp");
 
     let mut actual = Vec::new();
     {
       let mut writer = BufWriter::new(&mut actual);
-      utils::frame_bmp(metadata.xdim(), metadata.zdim(), volume.yframe(metadata.ydim() / 2))
-        .unwrap()
-        .to_writer(&mut writer)
-        .unwrap();
+      utils::frame_bmp(
+        metadata.xdim(),
+        metadata.zdim(),
+        None,
+        None,
+        volume.yframe(metadata.ydim() / 2).unwrap(),
+      )
+      .unwrap()
+      .to_writer(&mut writer)
+      .unwrap();
     }
 
     assert_eq!(actual, expected);
@@ -122,17 +131,23 @@ mod reference {
   #[test]
   fn bmp_z() {
     let (metadata, map) = md_and_map();
-    let volume = Volume::from_slice(metadata, &map).unwrap();
+    let volume = Volume::from_slice(metadata.clone(), &map).unwrap();
 
     let expected = read_file("tests/data/z.bmp");
 
     let mut actual = Vec::new();
     {
       let mut writer = BufWriter::new(&mut actual);
-      utils::frame_bmp(metadata.xdim(), metadata.ydim(), volume.zframe(metadata.zdim() / 2))
-        .unwrap()
-        .to_writer(&mut writer)
-        .unwrap();
+      utils::frame_bmp(
+        metadata.xdim(),
+        metadata.ydim(),
+        None,
+        None,
+        volume.zframe(metadata.zdim() / 2).unwrap(),
+      )
+      .unwrap()
+      .to_writer(&mut writer)
+      .unwrap();
     }
 
     assert_eq!(actual, expected);